@@ -8,12 +8,74 @@ BEGIN
         SELECT json_agg(
             json_build_object(
                 'table_name', c.table_name,
+                'partitioned', pgc.relkind = 'p',
+                'estimated_rows', (
+                    SELECT COALESCE(sum(GREATEST(part.reltuples, 0)), 0)::bigint
+                    FROM pg_partition_tree(pgc.oid) tree
+                    JOIN pg_class part ON part.oid = tree.relid
+                ),
+                'estimated_bytes', (
+                    SELECT COALESCE(sum(pg_table_size(tree.relid)), 0)::bigint
+                    FROM pg_partition_tree(pgc.oid) tree
+                ),
                 'columns', (
                     SELECT json_agg(
                         json_build_object(
                             'column_name', cols.column_name,
                             'data_type', cols.data_type,
+                            'udt_name', cols.udt_name,
+                            'type_definition', (
+                                SELECT format_type(att.atttypid, att.atttypmod)
+                                FROM pg_attribute att
+                                WHERE att.attrelid = pgc.oid AND att.attname = cols.column_name
+                            ),
+                            'enum_values', (
+                                SELECT json_agg(e.enumlabel ORDER BY e.enumsortorder)
+                                FROM pg_attribute att
+                                JOIN pg_type t ON t.oid = att.atttypid AND t.typtype = 'e'
+                                JOIN pg_enum e ON e.enumtypid = t.oid
+                                WHERE att.attrelid = pgc.oid AND att.attname = cols.column_name
+                            ),
+                            'domain', (
+                                SELECT json_build_object(
+                                    'domain_name', t.typname,
+                                    'base_type', format_type(t.typbasetype, t.typtypmod),
+                                    'check_constraints', (
+                                        SELECT json_agg(
+                                            json_build_object(
+                                                'constraint_name', con.conname,
+                                                'definition', pg_get_constraintdef(con.oid)
+                                            )
+                                        )
+                                        FROM pg_constraint con
+                                        WHERE con.contypid = t.oid AND con.contype = 'c'
+                                    )
+                                )
+                                FROM pg_attribute att
+                                JOIN pg_type t ON t.oid = att.atttypid AND t.typtype = 'd'
+                                WHERE att.attrelid = pgc.oid AND att.attname = cols.column_name
+                            ),
+                            'composite_fields', (
+                                SELECT json_agg(
+                                    json_build_object(
+                                        'name', fatt.attname,
+                                        'type', format_type(fatt.atttypid, fatt.atttypmod)
+                                    )
+                                    ORDER BY fatt.attnum
+                                )
+                                FROM pg_attribute att
+                                JOIN pg_type t ON t.oid = att.atttypid AND t.typtype = 'c'
+                                JOIN pg_attribute fatt ON fatt.attrelid = t.typrelid AND fatt.attnum > 0 AND NOT fatt.attisdropped
+                                WHERE att.attrelid = pgc.oid AND att.attname = cols.column_name
+                            ),
                             'is_nullable', cols.is_nullable,
+                            'is_unique', (
+                                SELECT bool_or(true)
+                                FROM pg_constraint con
+                                JOIN pg_attribute att ON att.attrelid = con.conrelid AND att.attnum = con.conkey[1]
+                                WHERE con.conrelid = pgc.oid AND con.contype = 'u'
+                                  AND array_length(con.conkey, 1) = 1 AND att.attname = cols.column_name
+                            ),
                             'column_default', cols.column_default,
                             'primary_key', (
                                 SELECT bool_or(kcu.column_name IS NOT NULL)
@@ -40,18 +102,43 @@ BEGIN
                                 JOIN pg_class fr ON con.confrelid = fr.oid
                                 JOIN pg_attribute fratt ON fratt.attrelid = fr.oid AND fratt.attnum = ANY(con.confkey)
                                 WHERE cl.relname = c.table_name AND con.contype = 'f' AND att.attname = cols.column_name
+                                  -- The foreign keys cloned on the partitions reference the partitions, not the parent table.
+                                  AND con.conparentid = 0
                             )
                         )
+                        ORDER BY cols.ordinal_position
                     )
                     FROM information_schema.columns cols
                     WHERE cols.table_name = c.table_name AND cols.table_schema = 'public'
+                ),
+                'parent_tables', (
+                    SELECT json_agg(parent.relname ORDER BY inh.inhseqno)
+                    FROM pg_inherits inh
+                    JOIN pg_class parent ON parent.oid = inh.inhparent
+                    WHERE inh.inhrelid = pgc.oid
+                ),
+                'check_constraints', (
+                    SELECT json_agg(
+                        json_build_object(
+                            'constraint_name', con.conname,
+                            'definition', pg_get_constraintdef(con.oid),
+                            'columns', (
+                                SELECT json_agg(att.attname)
+                                FROM pg_attribute att
+                                WHERE att.attrelid = con.conrelid AND att.attnum = ANY(con.conkey)
+                            )
+                        )
+                    )
+                    FROM pg_constraint con
+                    WHERE con.conrelid = pgc.oid AND con.contype = 'c'
                 )
             )
             ORDER BY pgc.oid
         ) AS tables_metadata
         FROM information_schema.tables c
         JOIN pg_class pgc ON c.table_name = pgc.relname
-        WHERE c.table_schema = 'public'
+        -- Les partitions sont migrées avec leur table partitionnée
+        WHERE c.table_schema = 'public' AND c.table_type = 'BASE TABLE' AND NOT pgc.relispartition
     )
     
     SELECT tables_metadata 
@@ -60,4 +147,25 @@ BEGIN
     
     RETURN tables_metadata_json;
 END;
-$$ LANGUAGE plpgsql;
\ No newline at end of file
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION export_views_metadata()
+RETURNS JSON AS $$
+DECLARE
+    views_metadata_json JSON;
+BEGIN
+    -- Récupérer la définition des vues, pour les traduire en requêtes Cypher
+    SELECT json_agg(
+        json_build_object(
+            'view_name', v.viewname,
+            'definition', v.definition
+        )
+        ORDER BY v.viewname
+    )
+    INTO views_metadata_json
+    FROM pg_views v
+    WHERE v.schemaname = 'public';
+
+    RETURN COALESCE(views_metadata_json, '[]'::json);
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,227 @@
+//! This module migrate the binary columns : the ```bytea``` values exported by psql in hex (```\x...```) and
+//! the large objects referenced by an ```oid``` column. The values are stored in the nodes in base64 or as
+//! ```BYTE[]```, or in external files of the blob folder with their path and their SHA-256.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use polars::prelude::{CsvReadOptions, DataFrame, IntoColumn, SerReader, StringChunked};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+use crate::mapping::{BinaryFormat, BinaryStorage, Mapping};
+use crate::utils::{string_values, warning};
+
+/// A binary column, ```large_object``` when its values are the oid of large objects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryColumn {
+    pub storage: BinaryStorage,
+    pub large_object: bool,
+}
+
+/// Return true if the column reference large objects : its type is the domain ```lo``` of the extension
+/// ```lo```, or it's an ```oid``` column declared with ```"large_object": true``` in the mapping.
+pub fn is_large_object(column: &Value, table: &str, mapping: &Mapping) -> bool {
+    if column["domain"]["domain_name"].as_str() == Some("lo") {
+        return true;
+    }
+    match (column["udt_name"].as_str(), column["column_name"].as_str()) {
+        (Some("oid"), Some(column_name)) => mapping.is_large_object(table, column_name),
+        _ => false,
+    }
+}
+
+/// Return the columns (table, column) that reference large objects in the meta-data.
+pub fn large_object_columns(meta_data_path: &str, mapping: &Mapping) -> Result<Vec<(String, String)>, String> {
+    let content = fs::read_to_string(meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    let mut large_objects = Vec::new();
+    for table in json_object.as_array().into_iter().flatten() {
+        let Some(table_name) = table["table_name"].as_str() else {
+            continue;
+        };
+        for column in table["columns"].as_array().into_iter().flatten() {
+            if let Some(column_name) = column["column_name"].as_str() {
+                if is_large_object(column, table_name, mapping) {
+                    large_objects.push((String::from(table_name), String::from(column_name)));
+                }
+            }
+        }
+    }
+    Ok(large_objects)
+}
+
+/// Return the path of the CSV file that contains the large objects of the column, exported by
+/// ```PostgreSQL::export_large_objects```.
+pub fn large_objects_path(tables_folder: &str, table: &str, column_name: &str) -> String {
+    format!("{}LargeObjects/{}.{}.csv", tables_folder, table.to_lowercase(), column_name)
+}
+
+/// Decode the ```bytea``` written by psql in hex, like ```\x48656c6c6f```.
+pub fn decode_bytea(value: &str) -> Option<Vec<u8>> {
+    let hex = value.trim().strip_prefix("\\x")?;
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(hex.get(index..index + 2)?, 16).ok())
+        .collect()
+}
+
+/// Convert the bytes into a ```BYTE[]``` value of neo4j-admin, the bytes of Neo4j are signed.
+pub fn bytes_value(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|byte| (*byte as i8).to_string())
+        .collect::<Vec<String>>()
+        .join(&ARRAY_DELIMITER.to_string())
+}
+
+/// Write the bytes in the blob folder, in a file named by their SHA-256 so the same value is written once.
+/// Return the path of the file and the SHA-256.
+pub fn write_blob(bytes: &[u8], blob_folder: &str) -> Result<(String, String), String> {
+    let sha256 = Sha256::digest(bytes)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect::<String>();
+    let file_path = format!("{}{}.bin", blob_folder, sha256);
+    if !Path::new(&file_path).exists() {
+        fs::create_dir_all(blob_folder)
+            .map_err(|error| format!("ERROR : when try to create the folder {}\n{}", blob_folder, error))?;
+        fs::write(&file_path, bytes)
+            .map_err(|error| format!("ERROR : when try to write the file {}\n{}", file_path, error))?;
+    }
+    Ok((file_path, sha256))
+}
+
+/// Read the large objects exported in the CSV file, return their data (```bytea``` in hex) by oid.
+fn read_large_objects(file_path: &str) -> Result<HashMap<String, String>, String> {
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .try_into_reader_with_file_path(Some(file_path.into()))
+        .map_err(|e| format!("{}", e))?
+        .finish()
+        .map_err(|e| format!("ERROR : when try to read the large objects {}\n{}", file_path, e))?;
+    let oids = string_values(&df, "oid")?;
+    let data = string_values(&df, "data")?;
+    Ok(oids
+        .into_iter()
+        .zip(data)
+        .filter_map(|(oid, data)| Some((oid?, data?)))
+        .collect())
+}
+
+/// Convert the binary columns of the Dataframe of the table ```label```, read from the CSV file of the table.<br>
+/// The values stored in the nodes replace the column, the values stored in files are written in the
+/// ```blob_folder``` and their path and SHA-256 are added in the columns ```<column>_path``` and ```<column>_sha256```.
+pub fn apply_binary_columns(
+    df: &mut DataFrame,
+    binaries: &[(String, BinaryColumn)],
+    label: &str,
+    tables_folder: &str,
+    blob_folder: &str,
+) -> Result<(), String> {
+    for (column_name, binary) in binaries {
+        let mut values = string_values(df, column_name)?;
+        if binary.large_object {
+            let large_objects = read_large_objects(&large_objects_path(tables_folder, label, column_name))?;
+            let mut missing_values = 0;
+            for value in values.iter_mut() {
+                if let Some(oid) = value.take() {
+                    *value = large_objects.get(&oid).cloned();
+                    if value.is_none() {
+                        missing_values += 1;
+                    }
+                }
+            }
+            if missing_values > 0 {
+                warning(format!(
+                    "{} large objects of {}.{} don't exist in pg_largeobject.",
+                    missing_values, label, column_name
+                ));
+            }
+        }
+
+        let mut invalid_values = 0;
+        let mut stored_values = Vec::with_capacity(values.len());
+        let mut paths = Vec::with_capacity(values.len());
+        let mut hashes = Vec::with_capacity(values.len());
+        for value in values {
+            let bytes = value.and_then(|value| {
+                let bytes = decode_bytea(&value);
+                if bytes.is_none() {
+                    invalid_values += 1;
+                }
+                bytes
+            });
+            let Some(bytes) = bytes else {
+                stored_values.push(None);
+                paths.push(None);
+                hashes.push(None);
+                continue;
+            };
+            let external = binary.storage.format == BinaryFormat::Files
+                || binary.storage.threshold.is_some_and(|threshold| bytes.len() as u64 > threshold);
+            if external {
+                let (path, sha256) = write_blob(&bytes, blob_folder)?;
+                stored_values.push(None);
+                paths.push(Some(path));
+                hashes.push(Some(sha256));
+            } else {
+                stored_values.push(Some(match binary.storage.format {
+                    BinaryFormat::Bytes => bytes_value(&bytes),
+                    _ => STANDARD.encode(&bytes),
+                }));
+                paths.push(None);
+                hashes.push(None);
+            }
+        }
+        if invalid_values > 0 {
+            warning(format!(
+                "{} values of {}.{} aren't bytea in hex, they are ignored (check the bytea_output of PostgreSQL).",
+                invalid_values, label, column_name
+            ));
+        }
+
+        let mut columns = vec![(String::clone(column_name), stored_values)];
+        if binary.storage.is_external() {
+            columns.push((format!("{}_path", column_name), paths));
+            columns.push((format!("{}_sha256", column_name), hashes));
+        }
+        for (name, values) in columns {
+            let column = values.into_iter().collect::<StringChunked>().with_name(name.as_str().into());
+            df.with_column(column.into_column()).map_err(|e| format!("{}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_binary_columns() {
+    use crate::utils::string_column;
+
+    assert_eq!(decode_bytea("\\x48ff"), Some(vec![0x48, 0xff]));
+    assert_eq!(decode_bytea("\\x4"), None);
+    assert_eq!(decode_bytea("Hello"), None);
+    assert_eq!(bytes_value(&[1, 255]), format!("1{}-1", ARRAY_DELIMITER));
+
+    let blob_folder = format!("{}/binary_columns_test/", std::env::temp_dir().display());
+    let _ = fs::remove_dir_all(&blob_folder);
+    let mut df = DataFrame::new(vec![string_column("photo", ["\\x48656c6c6f", "\\x4869"].into_iter())]).unwrap();
+    let binary = BinaryColumn {
+        storage: BinaryStorage { format: BinaryFormat::Base64, threshold: Some(2) },
+        large_object: false,
+    };
+    apply_binary_columns(&mut df, &[(String::from("photo"), binary)], "USERS", "", &blob_folder).unwrap();
+    assert_eq!(string_values(&df, "photo").unwrap(), vec![None, Some(String::from("SGk="))]);
+    let sha256 = String::from("185f8db32271fe25f561a6fc938b2e264306ec304eda518007d1764826381969");
+    assert_eq!(string_values(&df, "photo_sha256").unwrap(), vec![Some(String::clone(&sha256)), None]);
+    assert_eq!(fs::read(format!("{}{}.bin", blob_folder, sha256)).unwrap(), b"Hello");
+    let _ = fs::remove_dir_all(&blob_folder);
+}
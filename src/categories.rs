@@ -0,0 +1,248 @@
+//! This module migrate the enum columns and the columns that reference a lookup table as categories :
+//! a property restricted to the allowed values, an extra label per value or a shared category node.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use polars::prelude::{DataFrame, IntoColumn, StringChunked, CsvReadOptions, SerReader};
+
+use crate::cypher::escape_string;
+use crate::format_to_neo4j::HEADERS_FK;
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+use crate::utils::*;
+
+/// How the values of a category column are migrated, when they aren't stored in a simple property.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CategoryColumn {
+    /// The values become extra labels of the nodes.
+    Labels,
+    /// The values are the category nodes with the ```label```, connected to the nodes by relationships.
+    Nodes { label: String, values: Vec<String>, relationship_type: String },
+    /// The foreign key is replaced by the ```value_column``` of the lookup table, stored in the ```property```
+    /// or as extra labels when the ```property``` is ```None```.
+    Lookup { table: String, key_column: String, value_column: String, property: Option<String> },
+}
+
+/// Convert the value into a label, like ```in transit``` into ```IN_TRANSIT```.
+pub fn category_label(value: &str) -> String {
+    value
+        .trim()
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+        .collect()
+}
+
+/// Generate the APOC trigger that restrict the values of the property to the values of the enum.
+pub fn enum_trigger(function_name: &str, label: &str, column_name: &str, values: &[String]) -> String {
+    let values = values
+        .iter()
+        .map(|value| format!("'{}'", escape_string(value)))
+        .collect::<Vec<String>>()
+        .join(", ");
+    // The statement of the trigger is a Cypher string between double quotes.
+    let values = values.replace('\\', "\\\\").replace('"', "\\\"");
+    format!(r#"CALL apoc.trigger.add('enum_{}',"MATCH (m:{}) WHERE m.{} IS NOT NULL AND NOT m.{} IN [{}] CALL apoc.util.validate(true, 'ERROR : The value of the field {} is not allowed by its enum.', []) RETURN m",{{phase: 'before'}});{}"#
+        ,function_name,label,column_name,column_name,values,column_name,"\n")
+}
+
+/// Generate the APOC trigger that restrict the values of the property to the values of the lookup table.
+pub fn lookup_trigger(function_name: &str, label: &str, property: &str, lookup_label: &str, value_column: &str) -> String {
+    format!(r#"CALL apoc.trigger.add('lookup_{}',"MATCH (m:{}) WHERE m.{} IS NOT NULL AND NOT EXISTS {{ MATCH (l:{}) WHERE l.{} = m.{} }} CALL apoc.util.validate(true, 'ERROR : The value of the field {} does not exist in {}.', []) RETURN m",{{phase: 'before'}});{}"#
+        ,function_name,label,property,lookup_label,value_column,property,property,lookup_label,"\n")
+}
+
+/// Read the lookup table and return its values by key.
+fn read_lookup_table(tables_folder: &str, table: &str, key_column: &str, value_column: &str) -> Result<HashMap<String, String>, String> {
+    let file_path = format!("{}{}.csv", tables_folder, table.to_lowercase());
+    let df = CsvReadOptions::default()
+        .with_has_header(true)
+        .try_into_reader_with_file_path(Some(file_path.clone().into()))
+        .map_err(|e| format!("{}", e))?
+        .finish()
+        .map_err(|e| format!("ERROR : when try to read the lookup table {}\n{}", file_path, e))?;
+    let keys = string_values(&df, key_column)?;
+    let values = string_values(&df, value_column)?;
+    Ok(keys
+        .into_iter()
+        .zip(values)
+        .filter_map(|(key, value)| Some((key?, value?)))
+        .collect())
+}
+
+/// Write the category nodes in the file ```<label>.csv``` of the import folder, once for all the columns
+/// of the same enum.
+fn write_category_nodes(label: &str, values: &[String], import_folder: &str) -> Result<(), String> {
+    let file_path = format!("{}{}.csv", import_folder, label);
+    if Path::new(&file_path).exists() {
+        return Ok(());
+    }
+    let ids = (0..values.len()).map(|index| format!("{}{}", label, index)).collect::<Vec<String>>();
+    let df = DataFrame::new(vec![
+        string_column("id", ids.iter().map(|id| id.as_str())),
+        string_column("value", values.iter().map(|value| value.as_str())),
+        string_column("label", values.iter().map(|_| label)),
+    ])
+    .map_err(|e| format!("{}", e))?;
+    write_file(String::from(":ID;value:STRING;:LABEL\n"), &file_path)?;
+    append_dataframe(df, &file_path)?;
+    println!("\nSuccessfully write the category nodes in {}", file_path);
+    Ok(())
+}
+
+/// The state of the category conversions of a table, kept from a batch to the next : the lookup tables read once,
+/// the parts of the files shared with other tables and the values missing in the lookup tables.
+#[derive(Debug, Default)]
+pub struct CategoryState {
+    /// The values of the lookup table of each column, by key.
+    lookups: HashMap<String, HashMap<String, String>>,
+    /// The files with their part (see ```merge_parts```).
+    pub parts: Vec<FilePart>,
+    /// The number of values missing in the lookup table of each column.
+    missing_values: Vec<(String, String, usize)>,
+}
+
+impl CategoryState {
+    /// Read the lookup tables of the category columns.
+    pub fn new(categories: &[(String, CategoryColumn)], tables_folder: &str) -> Result<Self, String> {
+        let mut state = Self::default();
+        for (column_name, category) in categories {
+            if let CategoryColumn::Lookup { table, key_column, value_column, .. } = category {
+                let lookup = read_lookup_table(tables_folder, table, key_column, value_column)?;
+                state.lookups.insert(String::clone(column_name), lookup);
+                state.missing_values.push((String::clone(column_name), String::clone(table), 0));
+            }
+        }
+        Ok(state)
+    }
+
+    /// Warn once for each column whose values don't all exist in its lookup table.
+    pub fn warn_missing_values(&self, label: &str) {
+        for (column_name, table, missing_values) in &self.missing_values {
+            if *missing_values > 0 {
+                warning(format!(
+                    "{} values of {}.{} don't exist in the lookup table {}.",
+                    missing_values, label, column_name, table
+                ));
+            }
+        }
+    }
+}
+
+/// Apply the category conversions to the Dataframe of the table ```label```, a batch of the CSV file of
+/// the table with all its columns whose first row is the row ```offset``` of the table.<br>
+/// The properties of the lookup values are added to the Dataframe, the relationships to the category nodes
+/// are buffered in a part of their file kept in the ```state```, the file can be shared by several tables.
+/// Return the extra labels of each node.
+pub fn apply_category_columns(
+    df: &mut DataFrame,
+    categories: &[(String, CategoryColumn)],
+    label: &str,
+    offset: usize,
+    import_folder: &str,
+    state: &mut CategoryState,
+) -> Result<Vec<Vec<String>>, String> {
+    let mut extra_labels = vec![Vec::new(); df.height()];
+    for (column_name, category) in categories {
+        let values = string_values(df, column_name)?;
+        match category {
+            CategoryColumn::Labels => {
+                for (labels, value) in extra_labels.iter_mut().zip(&values) {
+                    if let Some(value) = value {
+                        labels.push(category_label(value));
+                    }
+                }
+            }
+            CategoryColumn::Nodes { label: category, values: category_values, relationship_type } => {
+                write_category_nodes(category, category_values, import_folder)?;
+                let mut relationships = (Vec::new(), Vec::new());
+                for (index, value) in values.iter().enumerate() {
+                    if let Some(position) = value.as_ref().and_then(|value| category_values.iter().position(|v| v == value)) {
+                        relationships.0.push(format!("{}{}", label, offset + index));
+                        relationships.1.push(format!("{}{}", category, position));
+                    }
+                }
+                let relationships = DataFrame::new(vec![
+                    string_column("start", relationships.0.iter().map(|id| id.as_str())),
+                    string_column("end", relationships.1.iter().map(|id| id.as_str())),
+                    string_column("type", relationships.0.iter().map(|_| relationship_type.as_str())),
+                ])
+                .map_err(|e| format!("{}", e))?;
+                let file_path = format!("{}{}.csv", import_folder, relationship_type);
+                if !Path::new(&file_path).exists() {
+                    write_file(String::from(HEADERS_FK), &file_path)?;
+                }
+                let part_path = part_path(&file_path, label);
+                if !state.parts.iter().any(|(path, _)| *path == file_path) {
+                    // The part of the table is created by its first batch.
+                    write_file(String::new(), &part_path)?;
+                    state.parts.push((file_path, String::clone(&part_path)));
+                }
+                append_dataframe(relationships, &part_path)?;
+            }
+            CategoryColumn::Lookup { table, property, .. } => {
+                let lookup = state
+                    .lookups
+                    .get(column_name)
+                    .ok_or_else(|| format!("ERROR : The lookup table {} of {}.{} isn't read.", table, label, column_name))?;
+                let lookup_values = values
+                    .iter()
+                    .map(|key| key.as_ref().and_then(|key| lookup.get(key)))
+                    .collect::<Vec<Option<&String>>>();
+                let missing_values = values
+                    .iter()
+                    .zip(&lookup_values)
+                    .filter(|(key, value)| key.is_some() && value.is_none())
+                    .count();
+                if let Some((_, _, count)) = state.missing_values.iter_mut().find(|(column, _, _)| column == column_name) {
+                    *count += missing_values;
+                }
+                match property {
+                    Some(property) => {
+                        let column = lookup_values
+                            .into_iter()
+                            .collect::<StringChunked>()
+                            .with_name(property.as_str().into());
+                        df.with_column(column.into_column()).map_err(|e| format!("{}", e))?;
+                    }
+                    None => {
+                        for (labels, value) in extra_labels.iter_mut().zip(lookup_values) {
+                            if let Some(value) = value {
+                                labels.push(category_label(value));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(extra_labels)
+}
+
+/// Return the value of the ```:LABEL``` column : the label of the table and the extra labels.
+pub fn labels_value(label: &str, extra_labels: &[String]) -> String {
+    std::iter::once(label)
+        .chain(extra_labels.iter().map(|extra_label| extra_label.as_str()))
+        .collect::<Vec<&str>>()
+        .join(&ARRAY_DELIMITER.to_string())
+}
+
+#[test]
+fn test_categories() {
+    assert_eq!(category_label("in transit"), "IN_TRANSIT");
+    assert_eq!(
+        enum_trigger("orders_status", "ORDERS", "status", &[String::from("new"), String::from("it's")]),
+        "CALL apoc.trigger.add('enum_orders_status',\"MATCH (m:ORDERS) WHERE m.status IS NOT NULL AND NOT m.status IN ['new', 'it\\\\'s'] \
+         CALL apoc.util.validate(true, 'ERROR : The value of the field status is not allowed by its enum.', []) RETURN m\",{phase: 'before'});\n"
+    );
+    assert_eq!(labels_value("ORDERS", &[String::from("SHIPPED")]), format!("ORDERS{}SHIPPED", ARRAY_DELIMITER));
+
+    let mut df = DataFrame::new(vec![
+        string_column("status", ["new", "shipped", "new"].into_iter()),
+    ])
+    .unwrap();
+    let categories = [(String::from("status"), CategoryColumn::Labels)];
+    let mut state = CategoryState::new(&categories, "").unwrap();
+    let extra_labels = apply_category_columns(&mut df, &categories, "ORDERS", 0, "", &mut state).unwrap();
+    assert!(state.parts.is_empty());
+    assert_eq!(extra_labels, vec![vec!["NEW"], vec!["SHIPPED"], vec!["NEW"]]);
+}
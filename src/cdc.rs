@@ -0,0 +1,994 @@
+//! This module keep the graph in sync with PostgreSQL after the import, with the changes of a logical replication
+//! slot : the JSON lines of ```wal2json``` (format 1 and 2) or the binary messages of ```pgoutput```.<br>
+//! The inserts, updates and deletes are applied with ```MERGE```, ```SET``` and ```DETACH DELETE``` on the nodes
+//! found by their primary key, and the relationships of the foreign keys are rewired when their values change.
+//! The changes can be replayed from a file, without a replication slot.
+
+use std::collections::HashMap;
+use std::fs;
+use std::thread;
+use std::time::Duration;
+
+use serde_json::Value;
+
+use crate::binary::decode_bytea;
+use crate::cypher::escape_string;
+use crate::equivalence::{parse_psql_csv, Cell};
+use crate::format_to_neo4j::{inheritance_labels, normalize_temporal, parse_array_literal, plan_meta_data};
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+use crate::mapping::Mapping;
+use crate::neo4j::Neo4j;
+use crate::plan::header_properties;
+use crate::postgresql::PostgreSQL;
+use crate::spatial::{normalize_spatial, spatial_column, SpatialColumn};
+use crate::utils::{warning, write_file};
+
+/// The maximum number of changes read from the replication slot at once.
+const CHANGES_BY_BATCH: usize = 1000;
+
+/// The kind of a change of a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeKind {
+    Insert,
+    Update,
+    Delete,
+    Truncate,
+}
+
+/// A change of a row, with its values in the text format of PostgreSQL (```None``` for NULL).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub kind: ChangeKind,
+    pub table: String,
+    /// The new values of the row, the unchanged TOAST values are missing.
+    pub values: Vec<(String, Option<String>)>,
+    /// The old values of the replica identity (the primary key by default), when PostgreSQL send them.
+    pub identity: Vec<(String, Option<String>)>,
+}
+
+/// How a property of a table is synchronized.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyncProperty {
+    pub neo4j_type: String,
+    pub spatial: Option<SpatialColumn>,
+}
+
+/// The foreign key of a table, synchronized as the relationships of its type.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncForeignKey {
+    pub relationship_type: String,
+    pub referenced_label: String,
+    pub referenced_column: String,
+}
+
+/// How the rows of a table are synchronized with the nodes of its label.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SyncTable {
+    pub label: String,
+    /// The labels of the parents of the inherited tables.
+    pub parent_labels: Vec<String>,
+    pub primary_key: Vec<String>,
+    pub properties: HashMap<String, SyncProperty>,
+    pub foreign_keys: HashMap<String, SyncForeignKey>,
+    /// The columns converted by the mapping (categories, JSON, binaries...) that aren't synchronized, and the
+    /// ```depth``` and ```root``` properties of a hierarchy that aren't recomputed.
+    pub ignored_columns: Vec<String>,
+}
+
+/// Read how the tables are synchronized from the meta-data of the migration and the mapping.
+pub fn sync_tables(meta_data_path: &str, mapping: &Mapping) -> Result<HashMap<String, SyncTable>, String> {
+    let files = plan_meta_data(meta_data_path, mapping)?;
+    let content =
+        fs::read_to_string(meta_data_path).map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let meta_data: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    let tables = meta_data.as_array().cloned().unwrap_or_default();
+    let ancestors = inheritance_labels(&tables);
+
+    let mut sync_tables = HashMap::new();
+    for table in &tables {
+        let Some(table_name) = table["table_name"].as_str() else {
+            continue;
+        };
+        let label = table_name.to_uppercase();
+        let header_types = files
+            .headers
+            .iter()
+            .find(|(header_label, _)| *header_label == label)
+            .map(|(_, headers)| header_properties(headers))
+            .unwrap_or_default();
+        let mut sync_table = SyncTable {
+            parent_labels: ancestors.get(&label).cloned().unwrap_or_default(),
+            label: String::clone(&label),
+            ..Default::default()
+        };
+        for column in table["columns"].as_array().into_iter().flatten() {
+            let Some(column_name) = column["column_name"].as_str() else {
+                continue;
+            };
+            if column["primary_key"].as_bool() == Some(true) {
+                sync_table.primary_key.push(String::from(column_name));
+            }
+            let conversion = files
+                .conversions
+                .iter()
+                .find(|(conversion_label, conversion_column, _)| *conversion_label == label && conversion_column == column_name)
+                .map(|(_, _, conversion)| conversion.as_str());
+            let spatial = match conversion {
+                None => None,
+                Some(conversion) if conversion.starts_with("point") || conversion.starts_with("geometry") => spatial_column(
+                    column["type_definition"].as_str().unwrap_or_default(),
+                    conversion.contains("list of points"),
+                ),
+                // The self-referencing foreign keys are synchronized like the other foreign keys, but the depth
+                // of the nodes in the tree isn't recomputed.
+                Some(conversion) if conversion.starts_with("hierarchy") => {
+                    if mapping.hierarchy(&label, column_name)?.1 {
+                        sync_table.ignored_columns.extend([String::from("depth"), String::from("root")]);
+                    }
+                    None
+                }
+                Some(_) => {
+                    sync_table.ignored_columns.push(String::from(column_name));
+                    continue;
+                }
+            };
+            if let Some(foreign_key) = column["foreign_key"].as_array().and_then(|foreign_keys| foreign_keys.first()) {
+                let (Some(referenced_table), Some(referenced_column)) =
+                    (foreign_key["referenced_table"].as_str(), foreign_key["referenced_column"].as_str())
+                else {
+                    continue;
+                };
+                let relationship_type = mapping
+                    .relationship_type(&label, column_name)?
+                    .unwrap_or_else(|| format!("{}_ref_{}", label, column_name.to_uppercase()));
+                sync_table.foreign_keys.insert(
+                    String::from(column_name),
+                    SyncForeignKey {
+                        relationship_type,
+                        referenced_label: referenced_table.to_uppercase(),
+                        referenced_column: String::from(referenced_column),
+                    },
+                );
+                continue;
+            }
+            match header_types.iter().find(|(name, _)| name == column_name) {
+                Some((_, neo4j_type)) => {
+                    sync_table.properties.insert(
+                        String::from(column_name),
+                        SyncProperty {
+                            neo4j_type: String::clone(neo4j_type),
+                            spatial,
+                        },
+                    );
+                }
+                None => sync_table.ignored_columns.push(String::from(column_name)),
+            }
+        }
+        sync_tables.insert(String::from(table_name), sync_table);
+    }
+    Ok(sync_tables)
+}
+
+/// Return the text of a JSON value of wal2json, like psql would write it.
+fn json_text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(String::clone(text)),
+        Value::Bool(value) => Some(String::from(if *value { "t" } else { "f" })),
+        value => Some(value.to_string()),
+    }
+}
+
+/// Parse a line written by wal2json, in the format 2 (a change by line) or 1 (a transaction by line).
+pub fn parse_wal2json(line: &str) -> Result<Vec<Change>, String> {
+    let json: Value =
+        serde_json::from_str(line).map_err(|error| format!("ERROR : when try to parse the wal2json line : {}\n{}", line, error))?;
+    let table = |change: &Value| change["table"].as_str().map(String::from);
+
+    // The format 2 : {"action":"I","table":"t","columns":[{"name":"id","value":1}],"identity":[...]}
+    if let Some(action) = json["action"].as_str() {
+        let kind = match action {
+            "I" => ChangeKind::Insert,
+            "U" => ChangeKind::Update,
+            "D" => ChangeKind::Delete,
+            "T" => ChangeKind::Truncate,
+            // The begin and commit of the transactions and the messages.
+            _ => return Ok(Vec::new()),
+        };
+        let values = |key: &str| {
+            json[key]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|column| Some((String::from(column["name"].as_str()?), json_text(&column["value"]))))
+                .collect::<Vec<(String, Option<String>)>>()
+        };
+        let table = table(&json).ok_or_else(|| format!("ERROR : The wal2json change doesn't have a table : {}", line))?;
+        return Ok(vec![Change {
+            kind,
+            table,
+            values: values("columns"),
+            identity: values("identity"),
+        }]);
+    }
+
+    // The format 1 : {"change":[{"kind":"insert","table":"t","columnnames":[...],"columnvalues":[...],"oldkeys":{...}}]}
+    let mut changes = Vec::new();
+    for change in json["change"].as_array().into_iter().flatten() {
+        let kind = match change["kind"].as_str() {
+            Some("insert") => ChangeKind::Insert,
+            Some("update") => ChangeKind::Update,
+            Some("delete") => ChangeKind::Delete,
+            Some("truncate") => ChangeKind::Truncate,
+            _ => continue,
+        };
+        let zip = |names: &Value, values: &Value| {
+            names
+                .as_array()
+                .into_iter()
+                .flatten()
+                .zip(values.as_array().into_iter().flatten())
+                .filter_map(|(name, value)| Some((String::from(name.as_str()?), json_text(value))))
+                .collect::<Vec<(String, Option<String>)>>()
+        };
+        let table = table(change).ok_or_else(|| format!("ERROR : The wal2json change doesn't have a table : {}", change))?;
+        changes.push(Change {
+            kind,
+            table,
+            values: zip(&change["columnnames"], &change["columnvalues"]),
+            identity: zip(&change["oldkeys"]["keynames"], &change["oldkeys"]["keyvalues"]),
+        });
+    }
+    Ok(changes)
+}
+
+/// Decode the binary messages of pgoutput (protocol version 1), the relations are kept to name the columns.
+#[derive(Debug, Default)]
+pub struct PgOutputDecoder {
+    /// The relations (table, columns) by their oid.
+    relations: HashMap<u32, (String, Vec<String>)>,
+}
+
+/// A reader of the fields of a pgoutput message.
+struct MessageReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn take(&mut self, length: usize) -> Result<&'a [u8], String> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + length)
+            .ok_or_else(|| String::from("ERROR : The pgoutput message is truncated."))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn int16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn int32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.bytes[self.position..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| String::from("ERROR : The pgoutput string isn't terminated."))?;
+        let text = String::from_utf8_lossy(self.take(length)?).into_owned();
+        self.position += 1;
+        Ok(text)
+    }
+
+    /// Read a TupleData : ```None``` for the unchanged TOAST values, ```Some(None)``` for NULL.
+    fn tuple(&mut self) -> Result<Vec<Option<Option<String>>>, String> {
+        let count = self.int16()?;
+        let mut values = Vec::new();
+        for _ in 0..count {
+            match self.byte()? {
+                b'n' => values.push(Some(None)),
+                b'u' => values.push(None),
+                b't' | b'b' => {
+                    let length = self.int32()? as usize;
+                    values.push(Some(Some(String::from_utf8_lossy(self.take(length)?).into_owned())));
+                }
+                kind => return Err(format!("ERROR : Unknown kind of pgoutput value : '{}'", kind as char)),
+            }
+        }
+        Ok(values)
+    }
+}
+
+impl PgOutputDecoder {
+    /// Decode a message, return the changes of the rows (the other messages return nothing).
+    pub fn decode(&mut self, message: &[u8]) -> Result<Vec<Change>, String> {
+        let mut reader = MessageReader {
+            bytes: message,
+            position: 0,
+        };
+        let kind = reader.byte()?;
+        match kind {
+            b'R' => {
+                let oid = reader.int32()?;
+                let _namespace = reader.string()?;
+                let table = reader.string()?;
+                let _replica_identity = reader.byte()?;
+                let mut columns = Vec::new();
+                for _ in 0..reader.int16()? {
+                    let _flags = reader.byte()?;
+                    columns.push(reader.string()?);
+                    let _type_oid = reader.int32()?;
+                    let _type_modifier = reader.int32()?;
+                }
+                self.relations.insert(oid, (table, columns));
+                Ok(Vec::new())
+            }
+            b'I' | b'U' | b'D' => {
+                let oid = reader.int32()?;
+                let (table, columns) = self
+                    .relations
+                    .get(&oid)
+                    .cloned()
+                    .ok_or_else(|| format!("ERROR : The pgoutput relation {} is unknown, its 'R' message is missing.", oid))?;
+                let named = |values: Vec<Option<Option<String>>>, skip_nulls: bool| {
+                    columns
+                        .iter()
+                        .zip(values)
+                        .filter_map(|(column, value)| match value {
+                            Some(None) if skip_nulls => None,
+                            Some(value) => Some((String::clone(column), value)),
+                            None => None,
+                        })
+                        .collect::<Vec<(String, Option<String>)>>()
+                };
+                let mut change = Change {
+                    kind: match kind {
+                        b'I' => ChangeKind::Insert,
+                        b'U' => ChangeKind::Update,
+                        _ => ChangeKind::Delete,
+                    },
+                    table,
+                    values: Vec::new(),
+                    identity: Vec::new(),
+                };
+                loop {
+                    match reader.byte()? {
+                        // The old key ('K') only contains the columns of the replica identity, the others are NULL.
+                        b'K' => change.identity = named(reader.tuple()?, true),
+                        b'O' => change.identity = named(reader.tuple()?, false),
+                        b'N' => {
+                            change.values = named(reader.tuple()?, false);
+                            break;
+                        }
+                        other => return Err(format!("ERROR : Unknown pgoutput tuple : '{}'", other as char)),
+                    }
+                    if change.kind == ChangeKind::Delete {
+                        break;
+                    }
+                }
+                Ok(vec![change])
+            }
+            b'T' => {
+                let count = reader.int32()?;
+                let _options = reader.byte()?;
+                let mut changes = Vec::new();
+                for _ in 0..count {
+                    let oid = reader.int32()?;
+                    if let Some((table, _)) = self.relations.get(&oid) {
+                        changes.push(Change {
+                            kind: ChangeKind::Truncate,
+                            table: String::clone(table),
+                            values: Vec::new(),
+                            identity: Vec::new(),
+                        });
+                    }
+                }
+                Ok(changes)
+            }
+            // Begin, commit, origin, type and logical messages.
+            _ => Ok(Vec::new()),
+        }
+    }
+}
+
+/// Parse the changes of a file : the JSON lines of wal2json, or the pgoutput messages in hex (one by line, or
+/// in the last column of the CSV written by ```pg_logical_slot_get_binary_changes```).
+pub fn parse_changes(content: &str) -> Result<Vec<Change>, String> {
+    let mut decoder = PgOutputDecoder::default();
+    let mut changes = Vec::new();
+    for line in content.lines().map(|line| line.trim()).filter(|line| !line.is_empty()) {
+        if line.starts_with('{') {
+            changes.extend(parse_wal2json(line)?);
+        } else if let Some(index) = line.rfind("\\x") {
+            let message = decode_bytea(&line[index..]).ok_or_else(|| format!("ERROR : Invalid pgoutput message : {}", line))?;
+            changes.extend(decoder.decode(&message)?);
+        }
+    }
+    Ok(changes)
+}
+
+/// Return the Cypher literal of the value of a property.
+fn cypher_value(value: Option<&str>, neo4j_type: &str, spatial: Option<&SpatialColumn>) -> String {
+    let Some(value) = value else {
+        return String::from("null");
+    };
+    if let Some(spatial) = spatial {
+        // The points are written with the format of neo4j-admin, like '{crs:WGS-84,longitude:2.3,latitude:48.8}'.
+        let point = |point: &str| match point
+            .split_once("crs:")
+            .and_then(|(start, end)| end.split_once(',').map(|(crs, end)| (start, crs, end)))
+        {
+            Some((start, crs, end)) => format!("point({}crs:'{}',{})", start, crs, end),
+            None => format!("point({})", point),
+        };
+        return match (normalize_spatial(value, spatial), spatial) {
+            ((None, _), _) => String::from("null"),
+            ((Some(points), _), SpatialColumn::Geometry { as_points: true, .. }) => {
+                format!("[{}]", points.split(ARRAY_DELIMITER).map(point).collect::<Vec<String>>().join(", "))
+            }
+            ((Some(value), _), _) if neo4j_type == "POINT" => point(&value),
+            ((Some(value), _), _) => format!("'{}'", escape_string(&value)),
+        };
+    }
+    if let Some(element_type) = neo4j_type.strip_suffix("[]") {
+        let value = value.trim();
+        let value = match value.find("={") {
+            Some(index) if value.starts_with('[') => &value[index + 1..],
+            _ => value,
+        };
+        let elements = match parse_array_literal(value) {
+            Some((elements, _)) => elements,
+            None => vec![Some(String::from(value))],
+        };
+        let elements = elements
+            .iter()
+            .flatten()
+            .map(|element| cypher_value(Some(element), element_type, None))
+            .collect::<Vec<String>>();
+        return format!("[{}]", elements.join(", "));
+    }
+    let function = match neo4j_type {
+        "LONG" | "INT" => {
+            return value
+                .trim()
+                .parse::<i64>()
+                .map(|number| number.to_string())
+                .unwrap_or_else(|_| format!("'{}'", escape_string(value)))
+        }
+        "DOUBLE" | "FLOAT" => {
+            return value
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|number| number.is_finite())
+                .map(|number| format!("{:?}", number))
+                .unwrap_or_else(|| String::from("null"))
+        }
+        "BOOLEAN" => {
+            return String::from(if matches!(value.trim(), "t" | "true" | "TRUE") {
+                "true"
+            } else {
+                "false"
+            })
+        }
+        "DATE" => "date",
+        "LOCALTIME" => "localtime",
+        "TIME" => "time",
+        "LOCALDATETIME" => "localdatetime",
+        "DATETIME" => "datetime",
+        "DURATION" => "duration",
+        _ => return format!("'{}'", escape_string(value)),
+    };
+    match normalize_temporal(value, neo4j_type) {
+        Some(value) => format!("{}('{}')", function, escape_string(&value)),
+        None => String::from("null"),
+    }
+}
+
+fn backtick(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Return the Cypher map of the values, like ```{`id`: 1, `name`: 'Alice'}```.
+fn cypher_map(values: &[(String, Option<String>)], table: &SyncTable) -> String {
+    let entries = values
+        .iter()
+        .filter_map(|(column, value)| {
+            let property = table.properties.get(column)?;
+            Some(format!(
+                "{}: {}",
+                backtick(column),
+                cypher_value(value.as_deref(), &property.neo4j_type, property.spatial.as_ref())
+            ))
+        })
+        .collect::<Vec<String>>();
+    format!("{{{}}}", entries.join(", "))
+}
+
+/// Return the values that identify the node of the row : the primary key, or the replica identity of the tables
+/// without primary key.<br>
+/// Return ```None``` when a column of the key isn't stored in a property (a foreign key or a converted column) or
+/// doesn't have a value : the node can't be found without matching the other nodes of the label.
+fn node_key(change: &Change, table: &SyncTable) -> Option<Vec<(String, Option<String>)>> {
+    let source = if change.identity.is_empty() {
+        &change.values
+    } else {
+        &change.identity
+    };
+    let columns = if table.primary_key.is_empty() {
+        // Without primary key, the old values of all the columns are needed (REPLICA IDENTITY FULL).
+        if change.identity.is_empty() {
+            return None;
+        }
+        change.identity.iter().map(|(column, _)| String::clone(column)).collect()
+    } else {
+        table.primary_key.clone()
+    };
+    let mut key = Vec::new();
+    for column in columns {
+        let value = source.iter().find(|(name, _)| *name == column).and_then(|(_, value)| value.clone());
+        if !table.properties.contains_key(&column) || value.is_none() {
+            return None;
+        }
+        key.push((column, value));
+    }
+    Some(key)
+}
+
+/// Return the clauses that rewire the relationships of the foreign keys whose values are in the change.
+fn foreign_key_clauses(change: &Change, table: &SyncTable, tables: &HashMap<String, SyncTable>, rewire: bool) -> String {
+    let mut clauses = String::new();
+    let mut foreign_keys = change
+        .values
+        .iter()
+        .filter_map(|(column, value)| Some((column, value, table.foreign_keys.get(column)?)))
+        .collect::<Vec<_>>();
+    foreign_keys.sort_by(|a, b| a.0.cmp(b.0));
+    for (index, (_, value, foreign_key)) in foreign_keys.into_iter().enumerate() {
+        if rewire {
+            clauses.push_str(&format!(
+                " WITH n OPTIONAL MATCH (n)-[old{}:{}]->() DELETE old{} WITH DISTINCT n",
+                index,
+                backtick(&foreign_key.relationship_type),
+                index
+            ));
+        }
+        let Some(value) = value else {
+            continue;
+        };
+        let referenced_type = tables
+            .values()
+            .find(|table| table.label == foreign_key.referenced_label)
+            .and_then(|table| table.properties.get(&foreign_key.referenced_column))
+            .map(|property| property.neo4j_type.as_str())
+            .unwrap_or("STRING");
+        clauses.push_str(&format!(
+            " WITH n OPTIONAL MATCH (m{}:{} {{{}: {}}}) FOREACH (_ IN CASE WHEN m{} IS NULL THEN [] ELSE [1] END | MERGE (n)-[:{}]->(m{}))",
+            index,
+            backtick(&foreign_key.referenced_label),
+            backtick(&foreign_key.referenced_column),
+            cypher_value(Some(value), referenced_type, None),
+            index,
+            backtick(&foreign_key.relationship_type),
+            index
+        ));
+    }
+    clauses
+}
+
+/// Generate the Cypher statement that apply the change on the graph, an error if the table isn't synchronized
+/// or if the node of the row can't be identified.
+pub fn change_statement(change: &Change, tables: &HashMap<String, SyncTable>) -> Result<String, String> {
+    let table = tables
+        .get(&change.table)
+        .ok_or_else(|| format!("The table {} isn't in the meta-data, its changes are ignored.", change.table))?;
+    let label = backtick(&table.label);
+    let parent_labels = table
+        .parent_labels
+        .iter()
+        .map(|label| format!(", n:{}", backtick(label)))
+        .collect::<String>();
+    let key = || {
+        node_key(change, table).ok_or_else(|| {
+            format!(
+                "The node of a row of {} can't be identified (its key is a foreign key, a converted column or isn't in the replica identity), the change is ignored.",
+                change.table
+            )
+        })
+    };
+    let statement = match change.kind {
+        ChangeKind::Insert if table.primary_key.is_empty() => format!(
+            "CREATE (n:{}) SET n += {}{}{}",
+            label,
+            cypher_map(&change.values, table),
+            parent_labels,
+            foreign_key_clauses(change, table, tables, false)
+        ),
+        ChangeKind::Insert => format!(
+            "MERGE (n:{} {}) SET n += {}{}{}",
+            label,
+            cypher_map(&key()?, table),
+            cypher_map(&change.values, table),
+            parent_labels,
+            // The node may already exist (a replay or an incremental sync), its relationships are rewired.
+            foreign_key_clauses(change, table, tables, true)
+        ),
+        ChangeKind::Update => format!(
+            "MATCH (n:{} {}) SET n += {}{}",
+            label,
+            cypher_map(&key()?, table),
+            cypher_map(&change.values, table),
+            foreign_key_clauses(change, table, tables, true)
+        ),
+        ChangeKind::Delete => format!("MATCH (n:{} {}) DETACH DELETE n", label, cypher_map(&key()?, table)),
+        // The nodes of the inherited tables have the label of their parents, but aren't truncated with them.
+        ChangeKind::Truncate => {
+            let children = tables
+                .values()
+                .filter(|child| child.parent_labels.contains(&table.label))
+                .map(|child| format!(" AND NOT n:{}", backtick(&child.label)))
+                .collect::<String>();
+            format!("MATCH (n:{}) WHERE true{} DETACH DELETE n", label, children)
+        }
+    };
+    Ok(format!("{};\n", statement))
+}
+
+/// Apply the changes on the graph with a Cypher script, return the number of changes applied.
+pub fn apply_changes(
+    db_neo4j: &Neo4j,
+    changes: &[Change],
+    tables: &HashMap<String, SyncTable>,
+    script_path: &str,
+) -> Result<usize, String> {
+    let mut script = String::new();
+    let mut applied = 0;
+    for change in changes {
+        match change_statement(change, tables) {
+            Ok(statement) => {
+                script.push_str(&statement);
+                applied += 1;
+            }
+            Err(error) => warning(error),
+        }
+    }
+    if applied > 0 {
+        write_file(script, script_path)?;
+        db_neo4j
+            .execute_script(script_path)
+            .map_err(|error| format!("ERROR : when try to apply the changes of the script {}\n{}", script_path, error))?;
+    }
+    Ok(applied)
+}
+
+/// Where the changes are read.
+pub enum ChangeSource {
+    /// A file recorded from wal2json or pgoutput.
+    Replay(String),
+    /// A logical replication slot, read every ```interval``` until the process is stopped (or once).
+    Slot {
+        name: String,
+        publication: Option<String>,
+        /// The LSN of the snapshot of the export, the changes already exported are skipped.
+        start_lsn: Option<String>,
+        interval: Duration,
+        once: bool,
+    },
+}
+
+/// Read the changes of the replication slot without consuming them, return the changes and the last LSN.
+fn peek_slot(
+    db_postgresql: &PostgreSQL,
+    slot: &str,
+    publication: &Option<String>,
+    decoder: &mut PgOutputDecoder,
+) -> Result<(Vec<Change>, Option<String>), String> {
+    let slot = slot.replace('\'', "''");
+    let query = match publication {
+        // pgoutput need the publication, its messages are binary.
+        Some(publication) => format!(
+            "SELECT lsn, data FROM pg_logical_slot_peek_binary_changes('{}', NULL, {}, 'proto_version', '1', 'publication_names', '{}')",
+            slot,
+            CHANGES_BY_BATCH,
+            publication.replace('\'', "''")
+        ),
+        None => format!(
+            "SELECT lsn, data FROM pg_logical_slot_peek_changes('{}', NULL, {}, 'format-version', '2')",
+            slot, CHANGES_BY_BATCH
+        ),
+    };
+    let result = parse_psql_csv(&db_postgresql.execute_query(&query, true)?)?;
+    let mut changes = Vec::new();
+    let mut last_lsn = None;
+    for row in &result.rows {
+        let (Some(Cell::Text(lsn)), Some(Cell::Text(data))) = (row.first(), row.get(1)) else {
+            continue;
+        };
+        if publication.is_some() {
+            let message = decode_bytea(data).ok_or_else(|| format!("ERROR : Invalid pgoutput message : {}", data))?;
+            changes.extend(decoder.decode(&message)?);
+        } else {
+            changes.extend(parse_wal2json(data)?);
+        }
+        last_lsn = Some(String::clone(lsn));
+    }
+    Ok((changes, last_lsn))
+}
+
+/// Warn about the columns and the tables that can't be fully synchronized.
+pub fn warn_unsynchronized(tables: &HashMap<String, SyncTable>) {
+    for (table_name, table) in tables {
+        if !table.ignored_columns.is_empty() {
+            warning(format!(
+                "The columns {} of {} are converted or computed by the mapping, they aren't synchronized.",
+                table.ignored_columns.join(", "),
+                table_name
+            ));
+        }
+        if table.primary_key.is_empty() {
+            warning(format!(
+                "The table {} doesn't have a primary key, its rows are found by their replica identity.",
+                table_name
+            ));
+        }
+    }
+}
+
+/// Advance the replication slot to the LSN of the snapshot of the export, the changes committed before are in the
+/// exported tables (the LSN is a lower bound : a few changes after it can also be in the exported tables, they are
+/// applied again). The slot need to be created before the export, the changes older than the slot are lost.
+pub fn advance_to_snapshot(db_postgresql: &PostgreSQL, slot: &str, lsn: &str) -> Result<String, String> {
+    let slot = slot.replace('\'', "''");
+    let lsn = lsn.replace('\'', "''");
+    let result = parse_psql_csv(&db_postgresql.execute_query(
+        &format!(
+            "SELECT confirmed_flush_lsn <= '{}'::pg_lsn FROM pg_replication_slots WHERE slot_name = '{}'",
+            lsn, slot
+        ),
+        true,
+    )?)?;
+    match result.rows.first().and_then(|row| row.first()) {
+        Some(Cell::Boolean(true)) => {}
+        Some(_) => warning(format!(
+            "The slot {} was created after the snapshot {}, the changes committed between them are missing.",
+            slot, lsn
+        )),
+        None => return Err(format!("ERROR : The replication slot {} doesn't exist.", slot)),
+    }
+    // The slot doesn't move backwards, the changes already applied aren't read again.
+    db_postgresql.execute_query(&format!("SELECT pg_replication_slot_advance('{}', '{}')", slot, lsn), true)?;
+    Ok(format!("The slot {} starts at the snapshot {} of the export.", slot, lsn))
+}
+
+/// Synchronize the graph with the changes of the source.
+pub fn run_sync(
+    db_postgresql: &PostgreSQL,
+    db_neo4j: &Neo4j,
+    source: &ChangeSource,
+    tables: &HashMap<String, SyncTable>,
+    script_path: &str,
+) -> Result<String, String> {
+    warn_unsynchronized(tables);
+    match source {
+        ChangeSource::Replay(path) => {
+            let content = fs::read_to_string(path).map_err(|error| format!("ERROR : when try to read the file : {}\n{}", path, error))?;
+            let changes = parse_changes(&content)?;
+            let applied = apply_changes(db_neo4j, &changes, tables, script_path)?;
+            Ok(format!("Successfully replay {} changes of {} !", applied, path))
+        }
+        ChangeSource::Slot {
+            name,
+            publication,
+            start_lsn,
+            interval,
+            once,
+        } => {
+            if let Some(lsn) = start_lsn {
+                println!("{}", advance_to_snapshot(db_postgresql, name, lsn)?);
+            }
+            // The relations of pgoutput are only sent once by session, the decoder is kept between the batches.
+            let mut decoder = PgOutputDecoder::default();
+            let mut total = 0;
+            loop {
+                let (changes, last_lsn) = peek_slot(db_postgresql, name, publication, &mut decoder)?;
+                let applied = apply_changes(db_neo4j, &changes, tables, script_path)?;
+                // The slot only advance when the changes are applied, they are read again after an error.
+                if let Some(lsn) = last_lsn {
+                    db_postgresql.execute_query(
+                        &format!("SELECT pg_replication_slot_advance('{}', '{}')", name.replace('\'', "''"), lsn),
+                        true,
+                    )?;
+                }
+                total += applied;
+                if applied > 0 {
+                    println!("Apply {} changes ({} since the start).", applied, total);
+                }
+                if *once {
+                    return Ok(format!("Successfully apply {} changes of the slot {} !", total, name));
+                }
+                thread::sleep(*interval);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_sync_tables() {
+    let meta_data_path = format!("{}/cdc_meta_data.json", std::env::temp_dir().display());
+    let meta_data = serde_json::json!([{
+        "table_name": "employees",
+        "columns": [
+            { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+            { "column_name": "reports_to", "data_type": "integer", "foreign_key": [{ "referenced_table": "employees", "referenced_column": "id" }] }
+        ]
+    }]);
+    fs::write(&meta_data_path, meta_data.to_string()).unwrap();
+    let mapping = Mapping::from_value(serde_json::json!({ "tables": { "employees": { "columns": {
+        "reports_to": { "hierarchy": { "type": "REPORTS_TO", "depth": true } }
+    } } } }));
+    let tables = sync_tables(&meta_data_path, &mapping).unwrap();
+    let _ = fs::remove_file(&meta_data_path);
+    // The relationships of the hierarchy are synchronized, its depth and root aren't recomputed.
+    assert_eq!(tables["employees"].foreign_keys["reports_to"].relationship_type, "REPORTS_TO");
+    assert_eq!(tables["employees"].ignored_columns, vec![String::from("depth"), String::from("root")]);
+}
+
+#[test]
+fn test_change_statements() {
+    let property = |neo4j_type: &str| SyncProperty {
+        neo4j_type: String::from(neo4j_type),
+        spatial: None,
+    };
+    let mut tables = HashMap::new();
+    tables.insert(
+        String::from("customers"),
+        SyncTable {
+            label: String::from("CUSTOMERS"),
+            primary_key: vec![String::from("id")],
+            properties: HashMap::from([(String::from("id"), property("LONG"))]),
+            ..Default::default()
+        },
+    );
+    tables.insert(
+        String::from("orders"),
+        SyncTable {
+            label: String::from("ORDERS"),
+            primary_key: vec![String::from("id")],
+            properties: HashMap::from([
+                (String::from("id"), property("LONG")),
+                (String::from("created"), property("DATETIME")),
+                (String::from("tags"), property("STRING[]")),
+            ]),
+            foreign_keys: HashMap::from([(
+                String::from("customer_id"),
+                SyncForeignKey {
+                    relationship_type: String::from("ORDERS_ref_CUSTOMER_ID"),
+                    referenced_label: String::from("CUSTOMERS"),
+                    referenced_column: String::from("id"),
+                },
+            )]),
+            ..Default::default()
+        },
+    );
+
+    let changes = parse_changes(
+        "{\"action\":\"B\"}\n\
+         {\"action\":\"I\",\"schema\":\"public\",\"table\":\"orders\",\"columns\":[{\"name\":\"id\",\"type\":\"integer\",\"value\":1},\
+         {\"name\":\"created\",\"type\":\"timestamptz\",\"value\":\"2024-01-05 10:00:00+02\"},{\"name\":\"tags\",\"type\":\"text[]\",\"value\":\"{a,\\\"b c\\\"}\"},\
+         {\"name\":\"customer_id\",\"type\":\"integer\",\"value\":7}]}\n\
+         {\"change\":[{\"kind\":\"delete\",\"table\":\"orders\",\"oldkeys\":{\"keynames\":[\"id\"],\"keyvalues\":[1]}}]}\n",
+    )
+    .unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(
+        change_statement(&changes[0], &tables).unwrap(),
+        "MERGE (n:`ORDERS` {`id`: 1}) SET n += {`id`: 1, `created`: datetime('2024-01-05T10:00:00+02:00'), `tags`: ['a', 'b c']} \
+         WITH n OPTIONAL MATCH (n)-[old0:`ORDERS_ref_CUSTOMER_ID`]->() DELETE old0 WITH DISTINCT n \
+         WITH n OPTIONAL MATCH (m0:`CUSTOMERS` {`id`: 7}) FOREACH (_ IN CASE WHEN m0 IS NULL THEN [] ELSE [1] END | MERGE (n)-[:`ORDERS_ref_CUSTOMER_ID`]->(m0));\n"
+    );
+    assert_eq!(
+        change_statement(&changes[1], &tables).unwrap(),
+        "MATCH (n:`ORDERS` {`id`: 1}) DETACH DELETE n;\n"
+    );
+
+    // The pgoutput messages : a relation, then an update that change the foreign key to NULL.
+    let mut relation = vec![b'R'];
+    relation.extend(16384_u32.to_be_bytes());
+    relation.extend(b"public\0orders\0d");
+    relation.extend(2_u16.to_be_bytes());
+    for column in ["id", "customer_id"] {
+        relation.push(1);
+        relation.extend(column.as_bytes());
+        relation.push(0);
+        relation.extend(23_u32.to_be_bytes());
+        relation.extend(u32::MAX.to_be_bytes());
+    }
+    let mut update = vec![b'U'];
+    update.extend(16384_u32.to_be_bytes());
+    update.push(b'N');
+    update.extend(2_u16.to_be_bytes());
+    update.push(b't');
+    update.extend(1_u32.to_be_bytes());
+    update.push(b'1');
+    update.push(b'n');
+    let hex = |bytes: &[u8]| format!("\\x{}", bytes.iter().map(|byte| format!("{:02x}", byte)).collect::<String>());
+    let changes = parse_changes(&format!("lsn,data\n0/1,{}\n0/2,{}\n", hex(&relation), hex(&update))).unwrap();
+    assert_eq!(
+        changes,
+        vec![Change {
+            kind: ChangeKind::Update,
+            table: String::from("orders"),
+            values: vec![(String::from("id"), Some(String::from("1"))), (String::from("customer_id"), None)],
+            identity: Vec::new(),
+        }]
+    );
+    assert_eq!(
+        change_statement(&changes[0], &tables).unwrap(),
+        "MATCH (n:`ORDERS` {`id`: 1}) SET n += {`id`: 1} WITH n OPTIONAL MATCH (n)-[old0:`ORDERS_ref_CUSTOMER_ID`]->() DELETE old0 WITH DISTINCT n;\n"
+    );
+
+    // A primary key made of a foreign key isn't a property, the node can't be matched.
+    tables.insert(
+        String::from("order_details"),
+        SyncTable {
+            label: String::from("ORDER_DETAILS"),
+            primary_key: vec![String::from("order_id")],
+            properties: HashMap::from([(String::from("quantity"), property("LONG"))]),
+            foreign_keys: HashMap::from([(
+                String::from("order_id"),
+                SyncForeignKey {
+                    relationship_type: String::from("ORDER_DETAILS_ref_ORDER_ID"),
+                    referenced_label: String::from("ORDERS"),
+                    referenced_column: String::from("id"),
+                },
+            )]),
+            ..Default::default()
+        },
+    );
+    let delete = Change {
+        kind: ChangeKind::Delete,
+        table: String::from("order_details"),
+        values: Vec::new(),
+        identity: vec![(String::from("order_id"), Some(String::from("1")))],
+    };
+    assert!(change_statement(&delete, &tables).is_err());
+    let update = Change {
+        kind: ChangeKind::Update,
+        table: String::from("order_details"),
+        values: vec![(String::from("order_id"), Some(String::from("1"))), (String::from("quantity"), Some(String::from("3")))],
+        identity: Vec::new(),
+    };
+    assert!(change_statement(&update, &tables).is_err());
+
+    // A table without primary key needs the replica identity of all its columns.
+    tables.insert(
+        String::from("logs"),
+        SyncTable {
+            label: String::from("LOGS"),
+            properties: HashMap::from([(String::from("message"), property("STRING"))]),
+            ..Default::default()
+        },
+    );
+    let mut delete = Change {
+        kind: ChangeKind::Delete,
+        table: String::from("logs"),
+        values: Vec::new(),
+        identity: Vec::new(),
+    };
+    assert!(change_statement(&delete, &tables).is_err());
+    delete.identity = vec![(String::from("message"), Some(String::from("started")))];
+    assert_eq!(
+        change_statement(&delete, &tables).unwrap(),
+        "MATCH (n:`LOGS` {`message`: 'started'}) DETACH DELETE n;\n"
+    );
+    assert!(change_statement(&Change { kind: ChangeKind::Insert, identity: Vec::new(), ..delete }, &tables).is_ok());
+}
@@ -0,0 +1,130 @@
+//! This module resolve the custom types of PostgreSQL : the domains are migrated with their base type and
+//! their CHECK constraints, the composite types are converted into JSON objects to be flattened into
+//! properties or exploded into child nodes like the JSON columns.
+
+use serde_json::{Map, Number, Value};
+
+/// Replace the keyword ```VALUE``` of the CHECK constraint of a domain by the name of the column,
+/// the string literals are kept as is.
+pub fn domain_expression(expression: &str, column_name: &str) -> String {
+    let mut result = String::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    for c in expression.chars().chain(std::iter::once(' ')) {
+        if !quoted && (c.is_alphanumeric() || c == '_') {
+            word.push(c);
+            continue;
+        }
+        if !word.is_empty() {
+            if word.eq_ignore_ascii_case("VALUE") {
+                result.push_str(&format!("\"{}\"", column_name));
+            } else {
+                result.push_str(&word);
+            }
+            word.clear();
+        }
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        result.push(c);
+    }
+    result.pop();
+    result
+}
+
+/// Parse the composite value written by psql, like ```(1,"a b",)```, return its fields (```None``` for NULL).
+pub fn parse_composite_literal(value: &str) -> Option<Vec<Option<String>>> {
+    let mut chars = value.trim().strip_prefix('(')?.strip_suffix(')')?.chars().peekable();
+    let mut fields = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    loop {
+        match chars.next() {
+            Some('"') => {
+                quoted = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => field.extend(chars.next()),
+                        // A double quote is escaped by another double quote.
+                        '"' if chars.peek() == Some(&'"') => {
+                            chars.next();
+                            field.push('"');
+                        }
+                        '"' => break,
+                        _ => field.push(c),
+                    }
+                }
+            }
+            Some('\\') => field.extend(chars.next()),
+            Some(c) if c != ',' => field.push(c),
+            separator => {
+                if quoted || !field.is_empty() {
+                    fields.push(Some(std::mem::take(&mut field)));
+                } else {
+                    fields.push(None);
+                }
+                quoted = false;
+                if separator.is_none() {
+                    break;
+                }
+            }
+        }
+    }
+    Some(fields)
+}
+
+/// Convert the field into a JSON value of its Neo4j type, to keep the types when the JSON is flattened.
+fn field_value(field: &str, data_type: &str) -> Value {
+    match data_type {
+        "LONG" => field.parse::<i64>().map(Value::from).unwrap_or_else(|_| Value::from(field)),
+        "DOUBLE" => field
+            .parse::<f64>()
+            .ok()
+            .and_then(Number::from_f64)
+            .map(Value::Number)
+            .unwrap_or_else(|| Value::from(field)),
+        "BOOLEAN" => match field {
+            "t" | "true" => Value::Bool(true),
+            "f" | "false" => Value::Bool(false),
+            _ => Value::from(field),
+        },
+        _ => Value::from(field),
+    }
+}
+
+/// Convert the composite value into a JSON object, with the ```fields``` (their names and Neo4j types) of the composite type.
+pub fn composite_to_json(value: &str, fields: &[(String, String)]) -> Option<String> {
+    let values = parse_composite_literal(value)?;
+    let mut object = Map::new();
+    for ((name, data_type), value) in fields.iter().zip(values) {
+        if let Some(value) = value {
+            object.insert(String::clone(name), field_value(&value, data_type));
+        }
+    }
+    Some(Value::Object(object).to_string())
+}
+
+#[test]
+fn test_domain_expression() {
+    assert_eq!(
+        domain_expression("(VALUE ~ '^.+@.+$'::text) AND value <> 'VALUE'", "email"),
+        "(\"email\" ~ '^.+@.+$'::text) AND \"email\" <> 'VALUE'"
+    );
+}
+
+#[test]
+fn test_composite_to_json() {
+    let fields = vec![
+        (String::from("street"), String::from("STRING")),
+        (String::from("number"), String::from("LONG")),
+        (String::from("zip"), String::from("STRING")),
+        (String::from("verified"), String::from("BOOLEAN")),
+    ];
+    assert_eq!(
+        composite_to_json(r#"("rue de la Paix, ""bis""",12,,t)"#, &fields).unwrap(),
+        r#"{"number":12,"street":"rue de la Paix, \"bis\"","verified":true}"#
+    );
+    assert_eq!(parse_composite_literal("(a,)"), Some(vec![Some(String::from("a")), None]));
+    assert_eq!(parse_composite_literal("(,)"), Some(vec![None, None]));
+    assert_eq!(parse_composite_literal("x"), None);
+}
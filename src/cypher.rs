@@ -0,0 +1,858 @@
+//! This module contains an AST of the Cypher language and its pretty-printer.<br>
+//! The queries generated by Neo4j-Migrator are build with these structures instead of
+//! string fragments, so they are always syntactically valid and consistently formatted.
+
+use std::fmt::{self, Display, Formatter};
+
+/// A complete Cypher query : a sequence of clauses.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Query {
+    pub clauses: Vec<Clause>,
+}
+
+#[allow(unused)]
+/// A Cypher clause.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Clause {
+    Match {
+        optional: bool,
+        patterns: Vec<Pattern>,
+        where_clause: Option<Expr>,
+    },
+    Unwind {
+        expr: Expr,
+        alias: String,
+    },
+    With(Projection),
+    Return(Projection),
+    Create(Vec<Pattern>),
+    Merge(Pattern),
+    Set(Vec<SetItem>),
+    Delete {
+        detach: bool,
+        exprs: Vec<Expr>,
+    },
+    Call {
+        procedure: String,
+        args: Vec<Expr>,
+        yield_items: Vec<String>,
+    },
+}
+
+/// The body of a ```WITH``` or ```RETURN``` clause.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Projection {
+    pub distinct: bool,
+    pub items: Vec<ProjectionItem>,
+    pub order_by: Vec<SortItem>,
+    pub skip: Option<Expr>,
+    pub limit: Option<Expr>,
+    /// Only used by the ```WITH``` clause.
+    pub where_clause: Option<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProjectionItem {
+    /// The ```*``` projection.
+    All,
+    Expr { expr: Expr, alias: Option<String> },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SortItem {
+    pub expr: Expr,
+    pub descending: bool,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum SetItem {
+    /// ```n.property = expr```
+    Property { target: Expr, value: Expr },
+    /// ```n += {map}```
+    Merge { variable: String, value: Expr },
+    /// ```n:LABEL```
+    Labels { variable: String, labels: Vec<String> },
+}
+
+/// A path pattern like ```p = (a:A)-[r:R]->(b)```.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Pattern {
+    pub variable: Option<String>,
+    pub start: NodePattern,
+    pub chain: Vec<(RelationshipPattern, NodePattern)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct NodePattern {
+    pub variable: Option<String>,
+    pub labels: Vec<String>,
+    pub properties: Vec<(String, Expr)>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RelationshipPattern {
+    pub variable: Option<String>,
+    pub types: Vec<String>,
+    pub direction: Direction,
+    pub properties: Vec<(String, Expr)>,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// ```-[]->```
+    Outgoing,
+    /// ```<-[]-```
+    Incoming,
+    /// ```-[]-```
+    Undirected,
+}
+
+#[allow(unused)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Literal(Literal),
+    /// A query parameter like ```$name```.
+    Parameter(String),
+    Variable(String),
+    Property(Box<Expr>, String),
+    List(Vec<Expr>),
+    Map(Vec<(String, Expr)>),
+    Not(Box<Expr>),
+    Negative(Box<Expr>),
+    Binary {
+        left: Box<Expr>,
+        op: BinaryOperator,
+        right: Box<Expr>,
+    },
+    IsNull {
+        expr: Box<Expr>,
+        negated: bool,
+    },
+    FunctionCall {
+        name: String,
+        distinct: bool,
+        args: Vec<Expr>,
+    },
+    /// ```count(*)```
+    CountAll,
+    Case {
+        operand: Option<Box<Expr>>,
+        when_then: Vec<(Expr, Expr)>,
+        else_expr: Option<Box<Expr>>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Literal {
+    Null,
+    Boolean(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOperator {
+    Or,
+    Xor,
+    And,
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+    In,
+    StartsWith,
+    EndsWith,
+    Contains,
+    RegexMatch,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+    Pow,
+}
+
+impl BinaryOperator {
+    fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Or => "or",
+            BinaryOperator::Xor => "xor",
+            BinaryOperator::And => "and",
+            BinaryOperator::Eq => "=",
+            BinaryOperator::NotEq => "<>",
+            BinaryOperator::Lt => "<",
+            BinaryOperator::LtEq => "<=",
+            BinaryOperator::Gt => ">",
+            BinaryOperator::GtEq => ">=",
+            BinaryOperator::In => "in",
+            BinaryOperator::StartsWith => "starts with",
+            BinaryOperator::EndsWith => "ends with",
+            BinaryOperator::Contains => "contains",
+            BinaryOperator::RegexMatch => "=~",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Sub => "-",
+            BinaryOperator::Mul => "*",
+            BinaryOperator::Div => "/",
+            BinaryOperator::Mod => "%",
+            BinaryOperator::Pow => "^",
+        }
+    }
+
+    /// The binding power of the operator, used to add the parenthesis only when they are needed.
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOperator::Or => 1,
+            BinaryOperator::Xor => 2,
+            BinaryOperator::And => 3,
+            BinaryOperator::Eq
+            | BinaryOperator::NotEq
+            | BinaryOperator::Lt
+            | BinaryOperator::LtEq
+            | BinaryOperator::Gt
+            | BinaryOperator::GtEq
+            | BinaryOperator::In
+            | BinaryOperator::StartsWith
+            | BinaryOperator::EndsWith
+            | BinaryOperator::Contains
+            | BinaryOperator::RegexMatch => 5,
+            BinaryOperator::Add | BinaryOperator::Sub => 6,
+            BinaryOperator::Mul | BinaryOperator::Div | BinaryOperator::Mod => 7,
+            BinaryOperator::Pow => 8,
+        }
+    }
+}
+
+#[allow(unused)]
+impl Expr {
+    pub fn var(name: &str) -> Self {
+        Expr::Variable(String::from(name))
+    }
+
+    pub fn prop(variable: &str, property: &str) -> Self {
+        Expr::Property(Box::new(Expr::var(variable)), String::from(property))
+    }
+
+    pub fn string(value: &str) -> Self {
+        Expr::Literal(Literal::String(String::from(value)))
+    }
+
+    pub fn param(name: &str) -> Self {
+        Expr::Parameter(String::from(name))
+    }
+
+    pub fn binary(left: Expr, op: BinaryOperator, right: Expr) -> Self {
+        Expr::Binary {
+            left: Box::new(left),
+            op,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn call(name: &str, args: Vec<Expr>) -> Self {
+        Expr::FunctionCall {
+            name: String::from(name),
+            distinct: false,
+            args,
+        }
+    }
+
+    /// Combine the expressions with ```and```, return ```None``` if the vector is empty.
+    pub fn and_all(exprs: Vec<Expr>) -> Option<Expr> {
+        exprs
+            .into_iter()
+            .reduce(|left, right| Expr::binary(left, BinaryOperator::And, right))
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary { op, .. } => op.precedence(),
+            Expr::Not(_) => 4,
+            Expr::IsNull { .. } => 5,
+            Expr::Negative(_) => 9,
+            _ => 10,
+        }
+    }
+
+    /// Return true if the expression is an aggregating function call.
+    pub fn is_aggregate(&self) -> bool {
+        match self {
+            Expr::CountAll => true,
+            Expr::FunctionCall { name, args, .. } => {
+                AGGREGATE_FUNCTIONS.contains(&name.to_lowercase().as_str())
+                    || args.iter().any(|arg| arg.is_aggregate())
+            }
+            Expr::Binary { left, right, .. } => left.is_aggregate() || right.is_aggregate(),
+            Expr::Not(expr) | Expr::Negative(expr) | Expr::Property(expr, _) => expr.is_aggregate(),
+            Expr::IsNull { expr, .. } => expr.is_aggregate(),
+            Expr::List(exprs) => exprs.iter().any(|expr| expr.is_aggregate()),
+            Expr::Map(entries) => entries.iter().any(|(_, expr)| expr.is_aggregate()),
+            Expr::Case {
+                operand,
+                when_then,
+                else_expr,
+            } => {
+                operand.as_ref().is_some_and(|expr| expr.is_aggregate())
+                    || when_then
+                        .iter()
+                        .any(|(when, then)| when.is_aggregate() || then.is_aggregate())
+                    || else_expr.as_ref().is_some_and(|expr| expr.is_aggregate())
+            }
+            Expr::Literal(_) | Expr::Parameter(_) | Expr::Variable(_) => false,
+        }
+    }
+
+    /// Collect the names of the parameters used in the expression.
+    fn collect_parameters(&self, parameters: &mut Vec<String>) {
+        match self {
+            Expr::Parameter(name) => {
+                if !parameters.contains(name) {
+                    parameters.push(String::clone(name));
+                }
+            }
+            Expr::Property(expr, _) | Expr::Not(expr) | Expr::Negative(expr) => {
+                expr.collect_parameters(parameters)
+            }
+            Expr::IsNull { expr, .. } => expr.collect_parameters(parameters),
+            Expr::Binary { left, right, .. } => {
+                left.collect_parameters(parameters);
+                right.collect_parameters(parameters);
+            }
+            Expr::List(exprs) | Expr::FunctionCall { args: exprs, .. } => {
+                exprs.iter().for_each(|expr| expr.collect_parameters(parameters))
+            }
+            Expr::Map(entries) => entries
+                .iter()
+                .for_each(|(_, expr)| expr.collect_parameters(parameters)),
+            Expr::Case {
+                operand,
+                when_then,
+                else_expr,
+            } => {
+                if let Some(operand) = operand {
+                    operand.collect_parameters(parameters);
+                }
+                for (when, then) in when_then {
+                    when.collect_parameters(parameters);
+                    then.collect_parameters(parameters);
+                }
+                if let Some(else_expr) = else_expr {
+                    else_expr.collect_parameters(parameters);
+                }
+            }
+            Expr::Literal(_) | Expr::Variable(_) | Expr::CountAll => {}
+        }
+    }
+}
+
+const AGGREGATE_FUNCTIONS: [&str; 9] = [
+    "count",
+    "sum",
+    "avg",
+    "min",
+    "max",
+    "collect",
+    "stdev",
+    "stdevp",
+    "percentiledisc",
+];
+
+#[allow(unused)]
+impl Query {
+    pub fn new(clauses: Vec<Clause>) -> Self {
+        Self { clauses }
+    }
+
+    /// Return the names of the parameters (without the ```$```) expected by the query.
+    pub fn parameters(&self) -> Vec<String> {
+        let mut parameters = Vec::new();
+        for clause in &self.clauses {
+            clause.for_each_expr(&mut |expr| expr.collect_parameters(&mut parameters));
+        }
+        parameters
+    }
+}
+
+#[allow(unused)]
+impl Clause {
+    fn for_each_expr(&self, function: &mut impl FnMut(&Expr)) {
+        fn patterns_exprs(patterns: &[Pattern], function: &mut dyn FnMut(&Expr)) {
+            for pattern in patterns {
+                pattern.start.properties.iter().for_each(|(_, e)| function(e));
+                for (relationship, node) in &pattern.chain {
+                    relationship.properties.iter().for_each(|(_, e)| function(e));
+                    node.properties.iter().for_each(|(_, e)| function(e));
+                }
+            }
+        }
+        match self {
+            Clause::Match {
+                patterns,
+                where_clause,
+                ..
+            } => {
+                patterns_exprs(patterns, function);
+                if let Some(expr) = where_clause {
+                    function(expr);
+                }
+            }
+            Clause::Unwind { expr, .. } => function(expr),
+            Clause::With(projection) | Clause::Return(projection) => {
+                for item in &projection.items {
+                    if let ProjectionItem::Expr { expr, .. } = item {
+                        function(expr);
+                    }
+                }
+                projection.order_by.iter().for_each(|item| function(&item.expr));
+                for expr in [&projection.skip, &projection.limit, &projection.where_clause]
+                    .into_iter()
+                    .flatten()
+                {
+                    function(expr);
+                }
+            }
+            Clause::Create(patterns) => patterns_exprs(patterns, function),
+            Clause::Merge(pattern) => patterns_exprs(std::slice::from_ref(pattern), function),
+            Clause::Set(items) => {
+                for item in items {
+                    match item {
+                        SetItem::Property { target, value } => {
+                            function(target);
+                            function(value);
+                        }
+                        SetItem::Merge { value, .. } => function(value),
+                        SetItem::Labels { .. } => {}
+                    }
+                }
+            }
+            Clause::Delete { exprs, .. } | Clause::Call { args: exprs, .. } => {
+                exprs.iter().for_each(function)
+            }
+        }
+    }
+}
+
+/// Escape the identifier with backticks when it isn't a simple identifier.
+pub fn escape_identifier(identifier: &str) -> String {
+    let mut chars = identifier.chars();
+    let is_simple = match chars.next() {
+        Some(first) => {
+            (first.is_ascii_alphabetic() || first == '_')
+                && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        }
+        None => false,
+    };
+    if is_simple {
+        String::from(identifier)
+    } else {
+        format!("`{}`", identifier.replace('`', "``"))
+    }
+}
+
+/// Escape the string literal to be used between single quotes.
+pub fn escape_string(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('\'', "\\'")
+        .replace('\n', "\\n")
+        .replace('\r', "\\r")
+        .replace('\t', "\\t")
+}
+
+fn write_separated<T: Display>(f: &mut Formatter<'_>, items: &[T], separator: &str) -> fmt::Result {
+    for (index, item) in items.iter().enumerate() {
+        if index > 0 {
+            write!(f, "{}", separator)?;
+        }
+        write!(f, "{}", item)?;
+    }
+    Ok(())
+}
+
+fn write_properties(f: &mut Formatter<'_>, properties: &[(String, Expr)]) -> fmt::Result {
+    write!(f, "{{")?;
+    for (index, (key, value)) in properties.iter().enumerate() {
+        if index > 0 {
+            write!(f, ", ")?;
+        }
+        write!(f, "{}: {}", escape_identifier(key), value)?;
+    }
+    write!(f, "}}")
+}
+
+impl Display for Query {
+    /// Each clause is written on its own line and the query is terminated by a ```;```
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write_separated(f, &self.clauses, "\n")?;
+        write!(f, ";")
+    }
+}
+
+impl Display for Clause {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Clause::Match {
+                optional,
+                patterns,
+                where_clause,
+            } => {
+                if *optional {
+                    write!(f, "optional ")?;
+                }
+                write!(f, "match ")?;
+                write_separated(f, patterns, ", ")?;
+                if let Some(expr) = where_clause {
+                    write!(f, "\nwhere {}", expr)?;
+                }
+                Ok(())
+            }
+            Clause::Unwind { expr, alias } => {
+                write!(f, "unwind {} as {}", expr, escape_identifier(alias))
+            }
+            Clause::With(projection) => {
+                write!(f, "with ")?;
+                projection.fmt(f)?;
+                if let Some(expr) = &projection.where_clause {
+                    write!(f, "\nwhere {}", expr)?;
+                }
+                Ok(())
+            }
+            Clause::Return(projection) => {
+                write!(f, "return ")?;
+                projection.fmt(f)
+            }
+            Clause::Create(patterns) => {
+                write!(f, "create ")?;
+                write_separated(f, patterns, ", ")
+            }
+            Clause::Merge(pattern) => write!(f, "merge {}", pattern),
+            Clause::Set(items) => {
+                write!(f, "set ")?;
+                write_separated(f, items, ", ")
+            }
+            Clause::Delete { detach, exprs } => {
+                if *detach {
+                    write!(f, "detach ")?;
+                }
+                write!(f, "delete ")?;
+                write_separated(f, exprs, ", ")
+            }
+            Clause::Call {
+                procedure,
+                args,
+                yield_items,
+            } => {
+                write!(f, "call {}(", procedure)?;
+                write_separated(f, args, ", ")?;
+                write!(f, ")")?;
+                if !yield_items.is_empty() {
+                    let yield_items = yield_items
+                        .iter()
+                        .map(|item| escape_identifier(item))
+                        .collect::<Vec<String>>();
+                    write!(f, " yield {}", yield_items.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Projection {
+    /// Write the projection without the ```where``` clause of the ```WITH```.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.distinct {
+            write!(f, "distinct ")?;
+        }
+        write_separated(f, &self.items, ", ")?;
+        if !self.order_by.is_empty() {
+            write!(f, "\norder by ")?;
+            write_separated(f, &self.order_by, ", ")?;
+        }
+        if let Some(skip) = &self.skip {
+            write!(f, "\nskip {}", skip)?;
+        }
+        if let Some(limit) = &self.limit {
+            write!(f, "\nlimit {}", limit)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for ProjectionItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ProjectionItem::All => write!(f, "*"),
+            ProjectionItem::Expr { expr, alias: None } => write!(f, "{}", expr),
+            ProjectionItem::Expr {
+                expr,
+                alias: Some(alias),
+            } => write!(f, "{} as {}", expr, escape_identifier(alias)),
+        }
+    }
+}
+
+impl Display for SortItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if self.descending {
+            write!(f, "{} desc", self.expr)
+        } else {
+            write!(f, "{}", self.expr)
+        }
+    }
+}
+
+impl Display for SetItem {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            SetItem::Property { target, value } => write!(f, "{} = {}", target, value),
+            SetItem::Merge { variable, value } => {
+                write!(f, "{} += {}", escape_identifier(variable), value)
+            }
+            SetItem::Labels { variable, labels } => {
+                write!(f, "{}", escape_identifier(variable))?;
+                for label in labels {
+                    write!(f, ":{}", escape_identifier(label))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Display for Pattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        if let Some(variable) = &self.variable {
+            write!(f, "{} = ", escape_identifier(variable))?;
+        }
+        write!(f, "{}", self.start)?;
+        for (relationship, node) in &self.chain {
+            write!(f, "{}{}", relationship, node)?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for NodePattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "(")?;
+        if let Some(variable) = &self.variable {
+            write!(f, "{}", escape_identifier(variable))?;
+        }
+        for label in &self.labels {
+            write!(f, ":{}", escape_identifier(label))?;
+        }
+        if !self.properties.is_empty() {
+            if self.variable.is_some() || !self.labels.is_empty() {
+                write!(f, " ")?;
+            }
+            write_properties(f, &self.properties)?;
+        }
+        write!(f, ")")
+    }
+}
+
+impl Display for RelationshipPattern {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let (left, right) = match self.direction {
+            Direction::Outgoing => ("-", "->"),
+            Direction::Incoming => ("<-", "-"),
+            Direction::Undirected => ("-", "-"),
+        };
+        write!(f, "{}[", left)?;
+        if let Some(variable) = &self.variable {
+            write!(f, "{}", escape_identifier(variable))?;
+        }
+        for (index, relationship_type) in self.types.iter().enumerate() {
+            let prefix = if index == 0 { ":" } else { "|" };
+            write!(f, "{}{}", prefix, escape_identifier(relationship_type))?;
+        }
+        if !self.properties.is_empty() {
+            write!(f, " ")?;
+            write_properties(f, &self.properties)?;
+        }
+        write!(f, "]{}", right)
+    }
+}
+
+impl Display for Literal {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Literal::Null => write!(f, "null"),
+            Literal::Boolean(value) => write!(f, "{}", value),
+            Literal::Integer(value) => write!(f, "{}", value),
+            Literal::Float(value) => {
+                if value.fract() == 0.0 && value.is_finite() {
+                    write!(f, "{:.1}", value)
+                } else {
+                    write!(f, "{}", value)
+                }
+            }
+            Literal::String(value) => write!(f, "'{}'", escape_string(value)),
+        }
+    }
+}
+
+impl Expr {
+    /// Write the operand, with parenthesis if its precedence is lower than the parent one.
+    fn fmt_operand(&self, f: &mut Formatter<'_>, parent_precedence: u8) -> fmt::Result {
+        if self.precedence() < parent_precedence {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl Display for Expr {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Literal(literal) => write!(f, "{}", literal),
+            Expr::Parameter(name) => write!(f, "${}", escape_identifier(name)),
+            Expr::Variable(name) => write!(f, "{}", escape_identifier(name)),
+            Expr::Property(expr, property) => {
+                expr.fmt_operand(f, 10)?;
+                write!(f, ".{}", escape_identifier(property))
+            }
+            Expr::List(exprs) => {
+                write!(f, "[")?;
+                write_separated(f, exprs, ", ")?;
+                write!(f, "]")
+            }
+            Expr::Map(entries) => write_properties(f, entries),
+            Expr::Not(expr) => {
+                write!(f, "not ")?;
+                expr.fmt_operand(f, 4)
+            }
+            Expr::Negative(expr) => {
+                write!(f, "-")?;
+                expr.fmt_operand(f, 10)
+            }
+            Expr::Binary { left, op, right } => {
+                let precedence = op.precedence();
+                left.fmt_operand(f, precedence)?;
+                write!(f, " {} ", op.symbol())?;
+                // The operators are left associative, so the right operand need the parenthesis
+                // when its precedence is the same.
+                right.fmt_operand(f, precedence + 1)
+            }
+            Expr::IsNull { expr, negated } => {
+                expr.fmt_operand(f, 6)?;
+                if *negated {
+                    write!(f, " is not null")
+                } else {
+                    write!(f, " is null")
+                }
+            }
+            Expr::FunctionCall {
+                name,
+                distinct,
+                args,
+            } => {
+                write!(f, "{}(", name)?;
+                if *distinct {
+                    write!(f, "distinct ")?;
+                }
+                write_separated(f, args, ", ")?;
+                write!(f, ")")
+            }
+            Expr::CountAll => write!(f, "count(*)"),
+            Expr::Case {
+                operand,
+                when_then,
+                else_expr,
+            } => {
+                write!(f, "case")?;
+                if let Some(operand) = operand {
+                    write!(f, " {}", operand)?;
+                }
+                for (when, then) in when_then {
+                    write!(f, " when {} then {}", when, then)?;
+                }
+                if let Some(else_expr) = else_expr {
+                    write!(f, " else {}", else_expr)?;
+                }
+                write!(f, " end")
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format_query() {
+    let query = Query::new(vec![
+        Clause::Match {
+            optional: false,
+            patterns: vec![Pattern {
+                variable: None,
+                start: NodePattern {
+                    variable: Some(String::from("o")),
+                    labels: vec![String::from("ORDERS")],
+                    properties: vec![],
+                },
+                chain: vec![(
+                    RelationshipPattern {
+                        variable: None,
+                        types: vec![String::from("ORDERS_ref_CUSTOMER_ID")],
+                        direction: Direction::Outgoing,
+                        properties: vec![],
+                    },
+                    NodePattern {
+                        variable: Some(String::from("c")),
+                        labels: vec![String::from("CUSTOMERS")],
+                        properties: vec![(String::from("city"), Expr::param("city"))],
+                    },
+                )],
+            }],
+            where_clause: Some(Expr::binary(
+                Expr::binary(
+                    Expr::prop("o", "freight"),
+                    BinaryOperator::Gt,
+                    Expr::Literal(Literal::Integer(10)),
+                ),
+                BinaryOperator::Or,
+                Expr::Not(Box::new(Expr::binary(
+                    Expr::prop("o", "ship name"),
+                    BinaryOperator::StartsWith,
+                    Expr::string("O'Neil"),
+                ))),
+            )),
+        },
+        Clause::Return(Projection {
+            items: vec![ProjectionItem::Expr {
+                expr: Expr::prop("o", "order_id"),
+                alias: Some(String::from("id")),
+            }],
+            order_by: vec![SortItem {
+                expr: Expr::prop("o", "freight"),
+                descending: true,
+            }],
+            limit: Some(Expr::Literal(Literal::Integer(10))),
+            ..Default::default()
+        }),
+    ]);
+    assert_eq!(
+        query.to_string(),
+        "match (o:ORDERS)-[:ORDERS_ref_CUSTOMER_ID]->(c:CUSTOMERS {city: $city})\n\
+         where o.freight > 10 or not o.`ship name` starts with 'O\\'Neil'\n\
+         return o.order_id as id\norder by o.freight desc\nlimit 10;"
+    );
+    assert_eq!(query.parameters(), vec![String::from("city")]);
+}
+
+#[test]
+fn test_format_precedence() {
+    let sum = Expr::binary(
+        Expr::prop("n", "a"),
+        BinaryOperator::Add,
+        Expr::prop("n", "b"),
+    );
+    let expr = Expr::binary(sum.clone(), BinaryOperator::Mul, Expr::prop("n", "c"));
+    assert_eq!(expr.to_string(), "(n.a + n.b) * n.c");
+    let expr = Expr::binary(Expr::prop("n", "c"), BinaryOperator::Sub, sum);
+    assert_eq!(expr.to_string(), "n.c - (n.a + n.b)");
+}
@@ -0,0 +1,366 @@
+//! This module draw the graph model of a migration from the meta-data and the mapping : the labels with their
+//! properties and keys, the relationships with their direction and their cardinality. The diagram is exported
+//! in Mermaid, Graphviz DOT or in the JSON of arrows.app, whose relationship types can be edited and imported
+//! back into the mapping.
+
+use std::collections::HashMap;
+use std::fs;
+
+use serde_json::{json, Map, Value};
+
+use crate::format_to_neo4j::plan_meta_data;
+use crate::mapping::Mapping;
+use crate::plan::{header_properties, plan_relationships};
+
+/// A label of the graph model and its properties (name, type, key).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramLabel {
+    pub label: String,
+    pub extra_labels: Vec<String>,
+    pub properties: Vec<(String, String, Option<&'static str>)>,
+}
+
+/// A relationship of the graph model, generated from the foreign key ```table.column```.<br>
+/// Each end node has ```start_cardinality``` start nodes, each start node has ```end_cardinality``` end nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiagramRelationship {
+    pub relationship_type: String,
+    pub start: String,
+    pub end: String,
+    pub table: String,
+    pub column: String,
+    pub start_cardinality: &'static str,
+    pub end_cardinality: &'static str,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Diagram {
+    pub labels: Vec<DiagramLabel>,
+    pub relationships: Vec<DiagramRelationship>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiagramFormat {
+    Mermaid,
+    Dot,
+    Arrows,
+}
+
+/// Build the diagram of the graph model from the meta-data exported from PostgreSQL and the mapping.
+pub fn build_diagram(meta_data_path: &str, mapping: &Mapping) -> Result<Diagram, String> {
+    let files = plan_meta_data(meta_data_path, mapping)?;
+    let content = fs::read_to_string(meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    let tables = json_object
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|table| Some((table["table_name"].as_str()?.to_uppercase(), table)))
+        .collect::<HashMap<String, &Value>>();
+    let column = |label: &str, column_name: &str| {
+        tables
+            .get(label)
+            .and_then(|table| table["columns"].as_array())
+            .into_iter()
+            .flatten()
+            .find(|column| column["column_name"].as_str() == Some(column_name))
+    };
+    // A primary key made of several columns doesn't make one of them unique.
+    let is_unique = |label: &str, column_name: &str| {
+        let primary_keys = tables
+            .get(label)
+            .and_then(|table| table["columns"].as_array())
+            .into_iter()
+            .flatten()
+            .filter(|column| column["primary_key"].as_bool() == Some(true))
+            .count();
+        column(label, column_name).is_some_and(|column| {
+            (column["primary_key"].as_bool() == Some(true) && primary_keys == 1) || column["is_unique"].as_bool() == Some(true)
+        })
+    };
+
+    let labels = files
+        .headers
+        .iter()
+        .map(|(label, headers)| DiagramLabel {
+            label: String::clone(label),
+            extra_labels: files.parent_labels.get(label).cloned().unwrap_or_default(),
+            properties: header_properties(headers)
+                .into_iter()
+                .map(|(name, data_type)| {
+                    let key = match column(label, &name) {
+                        Some(column) if column["primary_key"].as_bool() == Some(true) => Some("PK"),
+                        Some(column) if column["is_unique"].as_bool() == Some(true) => Some("UK"),
+                        _ => None,
+                    };
+                    (name, data_type, key)
+                })
+                .collect(),
+        })
+        .collect();
+    let relationships = plan_relationships(&files.foreign_keys)
+        .into_iter()
+        .map(|(relationship_type, start, end, column_name)| {
+            let table = tables
+                .get(&start)
+                .and_then(|table| table["table_name"].as_str())
+                .map(String::from)
+                .unwrap_or_else(|| start.to_lowercase());
+            let not_null = column(&start, &column_name).is_some_and(|column| column["is_nullable"].as_str() == Some("NO"));
+            DiagramRelationship {
+                start_cardinality: if is_unique(&start, &column_name) { "0..1" } else { "0..*" },
+                end_cardinality: if not_null { "1" } else { "0..1" },
+                relationship_type,
+                start,
+                end,
+                table,
+                column: column_name,
+            }
+        })
+        .collect();
+    Ok(Diagram { labels, relationships })
+}
+
+/// Export the diagram as a Mermaid ER diagram.
+pub fn to_mermaid(diagram: &Diagram) -> String {
+    let mut content = String::from("erDiagram\n");
+    for label in &diagram.labels {
+        content.push_str(&format!("    {} {{\n", label.label));
+        for (name, data_type, key) in &label.properties {
+            match key {
+                Some(key) => content.push_str(&format!("        {} {} {}\n", data_type, name, key)),
+                None => content.push_str(&format!("        {} {}\n", data_type, name)),
+            }
+        }
+        content.push_str("    }\n");
+    }
+    for relationship in &diagram.relationships {
+        let start = if relationship.start_cardinality == "0..1" { "|o" } else { "}o" };
+        let end = if relationship.end_cardinality == "1" { "||" } else { "o|" };
+        content.push_str(&format!(
+            "    {} {}--{} {} : \"{}\"\n",
+            relationship.start, start, end, relationship.end, relationship.relationship_type
+        ));
+    }
+    content
+}
+
+/// Escape the text of a Graphviz string.
+fn escape_dot(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Export the diagram as a Graphviz DOT graph.
+pub fn to_dot(diagram: &Diagram) -> String {
+    let mut content = String::from("digraph schema {\n    rankdir=LR;\n    node [shape=box, fontname=\"Helvetica\"];\n");
+    for label in &diagram.labels {
+        let mut text = format!(
+            "{}{}\\n\\n",
+            label.label,
+            label.extra_labels.iter().map(|extra_label| format!(":{}", extra_label)).collect::<String>()
+        );
+        for (name, data_type, key) in &label.properties {
+            let key = key.map(|key| format!(" ({})", key)).unwrap_or_default();
+            text.push_str(&format!("{} : {}{}\\l", escape_dot(name), escape_dot(data_type), key));
+        }
+        content.push_str(&format!("    \"{}\" [label=\"{}\"];\n", escape_dot(&label.label), text));
+    }
+    for relationship in &diagram.relationships {
+        content.push_str(&format!(
+            "    \"{}\" -> \"{}\" [label=\"{}\", taillabel=\"{}\", headlabel=\"{}\"];\n",
+            escape_dot(&relationship.start),
+            escape_dot(&relationship.end),
+            escape_dot(&relationship.relationship_type),
+            relationship.start_cardinality,
+            relationship.end_cardinality
+        ));
+    }
+    content.push_str("}\n");
+    content
+}
+
+/// Export the diagram in the JSON of arrows.app, the nodes are placed on a grid.<br>
+/// The property ```foreign_key``` of the relationships is used to import them back into the mapping.
+pub fn to_arrows(diagram: &Diagram) -> Value {
+    let ids = diagram
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| (label.label.as_str(), format!("n{}", index)))
+        .collect::<HashMap<&str, String>>();
+    let nodes = diagram
+        .labels
+        .iter()
+        .enumerate()
+        .map(|(index, label)| {
+            let properties = label
+                .properties
+                .iter()
+                .map(|(name, data_type, key)| {
+                    let value = key.map(|key| format!("{} {}", data_type, key)).unwrap_or_else(|| String::clone(data_type));
+                    (String::clone(name), Value::from(value))
+                })
+                .collect::<Map<String, Value>>();
+            json!({
+                "id": ids[label.label.as_str()],
+                "position": { "x": (index % 4) * 300, "y": (index / 4) * 300 },
+                "caption": "",
+                "labels": std::iter::once(&label.label).chain(&label.extra_labels).collect::<Vec<&String>>(),
+                "properties": properties,
+                "style": {},
+            })
+        })
+        .collect::<Vec<Value>>();
+    let relationships = diagram
+        .relationships
+        .iter()
+        .enumerate()
+        .filter_map(|(index, relationship)| {
+            Some(json!({
+                "id": format!("r{}", index),
+                "fromId": ids.get(relationship.start.as_str())?,
+                "toId": ids.get(relationship.end.as_str())?,
+                "type": relationship.relationship_type,
+                "properties": {
+                    "foreign_key": format!("{}.{}", relationship.table, relationship.column),
+                    "cardinality": format!("{} -> {}", relationship.start_cardinality, relationship.end_cardinality),
+                },
+                "style": {},
+            }))
+        })
+        .collect::<Vec<Value>>();
+    json!({
+        "graph": { "nodes": nodes, "relationships": relationships, "style": {} },
+        "diagramName": "Neo4j-Migrator",
+    })
+}
+
+/// Export the diagram in the ```format```.
+pub fn export_diagram(diagram: &Diagram, format: DiagramFormat) -> Result<String, String> {
+    match format {
+        DiagramFormat::Mermaid => Ok(to_mermaid(diagram)),
+        DiagramFormat::Dot => Ok(to_dot(diagram)),
+        DiagramFormat::Arrows => serde_json::to_string_pretty(&to_arrows(diagram)).map_err(|error| format!("{}", error)),
+    }
+}
+
+/// Import the relationship types of a diagram edited in arrows.app into the mapping, with the column option
+/// ```"relationship"``` of the foreign keys. Return the changes and the edits that are ignored : the relationships
+/// that don't come from a foreign key, and the nodes whose labels or properties are edited, added or removed
+/// (they come from the tables and can't be changed by the mapping).
+pub fn import_arrows(arrows: &Value, diagram: &Diagram, mapping: &mut Mapping) -> Result<(Vec<String>, Vec<String>), String> {
+    let graph = arrows.get("graph").unwrap_or(arrows);
+    let mut changes = Vec::new();
+    let mut ignored = Vec::new();
+
+    let exported = to_arrows(diagram);
+    let exported_nodes = exported["graph"]["nodes"].as_array().cloned().unwrap_or_default();
+    let nodes = graph["nodes"].as_array().cloned().unwrap_or_default();
+    for node in &nodes {
+        let label = node["labels"].as_array().and_then(|labels| labels.first()).and_then(|label| label.as_str());
+        match exported_nodes.iter().find(|exported_node| exported_node["id"] == node["id"]) {
+            None => ignored.push(format!("node {} ({}) : added", label.unwrap_or_default(), node["id"])),
+            Some(exported_node) => {
+                let name = exported_node["labels"][0].as_str().unwrap_or_default();
+                if exported_node["labels"] != node["labels"] {
+                    ignored.push(format!("node {} ({}) : labels edited to {}", name, node["id"], node["labels"]));
+                }
+                if exported_node["properties"] != node["properties"] {
+                    ignored.push(format!("node {} ({}) : properties edited", name, node["id"]));
+                }
+            }
+        }
+    }
+    for exported_node in &exported_nodes {
+        if !nodes.iter().any(|node| node["id"] == exported_node["id"]) {
+            ignored.push(format!(
+                "node {} ({}) : removed",
+                exported_node["labels"][0].as_str().unwrap_or_default(),
+                exported_node["id"]
+            ));
+        }
+    }
+
+    for relationship in graph["relationships"].as_array().into_iter().flatten() {
+        let relationship_type = relationship["type"].as_str().unwrap_or_default();
+        let current = relationship["properties"]["foreign_key"]
+            .as_str()
+            .and_then(|foreign_key| foreign_key.split_once('.'))
+            .and_then(|(table, column)| {
+                diagram.relationships.iter().find(|current| current.table == table && current.column == column)
+            });
+        let Some(current) = current else {
+            ignored.push(format!("relationship {} ({}) : not a foreign key", relationship_type, relationship["id"]));
+            continue;
+        };
+        if current.relationship_type != relationship_type {
+            mapping.set_column_option(&current.table, &current.column, "relationship", Value::from(relationship_type));
+            mapping.relationship_type(&current.table, &current.column)?;
+            changes.push(format!(
+                "{}.{} : {} -> {}",
+                current.table, current.column, current.relationship_type, relationship_type
+            ));
+        }
+    }
+    Ok((changes, ignored))
+}
+
+#[test]
+fn test_diagram() {
+    let folder = format!("{}/diagram_test", std::env::temp_dir().display());
+    fs::create_dir_all(&folder).unwrap();
+    let meta_data_path = format!("{}/meta_data.json", folder);
+    let meta_data = json!([
+        {
+            "table_name": "customers",
+            "columns": [
+                { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+                { "column_name": "email", "data_type": "text", "is_unique": true, "foreign_key": null }
+            ]
+        },
+        {
+            "table_name": "orders",
+            "columns": [
+                { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+                { "column_name": "customer_id", "data_type": "integer", "is_nullable": "NO",
+                  "foreign_key": [{ "referenced_table": "customers", "referenced_column": "id" }] }
+            ]
+        }
+    ]);
+    fs::write(&meta_data_path, meta_data.to_string()).unwrap();
+    let diagram = build_diagram(&meta_data_path, &Mapping::default()).unwrap();
+    let _ = fs::remove_dir_all(&folder);
+
+    assert_eq!(
+        to_mermaid(&diagram),
+        "erDiagram\n    CUSTOMERS {\n        LONG id PK\n        STRING email UK\n    }\n    ORDERS {\n        LONG id PK\n    }\n    \
+         ORDERS }o--|| CUSTOMERS : \"ORDERS_ref_CUSTOMER_ID\"\n"
+    );
+    assert!(to_dot(&diagram).contains("\"ORDERS\" -> \"CUSTOMERS\" [label=\"ORDERS_ref_CUSTOMER_ID\", taillabel=\"0..*\", headlabel=\"1\"];"));
+
+    // The relationship type edited in arrows.app is imported into the mapping.
+    let mut arrows = to_arrows(&diagram);
+    assert_eq!(arrows["graph"]["relationships"][0]["properties"]["foreign_key"], "orders.customer_id");
+    arrows["graph"]["relationships"][0]["type"] = Value::from("PLACED_BY");
+    let mut mapping = Mapping::default();
+    let (changes, ignored) = import_arrows(&arrows, &diagram, &mut mapping).unwrap();
+    assert_eq!(changes, vec!["orders.customer_id : ORDERS_ref_CUSTOMER_ID -> PLACED_BY"]);
+    assert!(ignored.is_empty());
+    assert_eq!(mapping.relationship_type("ORDERS", "customer_id").unwrap(), Some(String::from("PLACED_BY")));
+    arrows["graph"]["relationships"][0]["type"] = Value::from("PLACED BY");
+    assert!(import_arrows(&arrows, &diagram, &mut mapping).is_err());
+
+    // The edits of the labels and the properties can't be imported, they are reported as ignored.
+    arrows["graph"]["relationships"][0]["type"] = Value::from("PLACED_BY");
+    arrows["graph"]["nodes"][0]["labels"] = json!(["CLIENTS"]);
+    arrows["graph"]["nodes"][1]["properties"]["total"] = Value::from("FLOAT");
+    let (_, ignored) = import_arrows(&arrows, &diagram, &mut mapping).unwrap();
+    assert_eq!(
+        ignored,
+        vec![
+            "node CUSTOMERS (\"n0\") : labels edited to [\"CLIENTS\"]",
+            "node ORDERS (\"n1\") : properties edited"
+        ]
+    );
+}
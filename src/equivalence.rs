@@ -0,0 +1,579 @@
+//! This module contains the equivalence testing harness : it checks that the graph database
+//! answers the same questions than the relationnal database.<br>
+//! Each SQL query of the corpus is run on PostgreSQL, translated into Cypher and run on Neo4j,
+//! then the two result sets are compared without considering the order of the rows.
+
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::mapping::Mapping;
+use crate::neo4j::Neo4j;
+use crate::postgresql::PostgreSQL;
+use crate::translate::*;
+use crate::utils::*;
+
+/// The prefix added by ```Neo4j::execute_query()``` to the output of *cypher-shell*.
+const CYPHER_RESULT_PREFIX: &str = "Result of the cypher query :";
+
+/// Where the result sets of the queries come from.
+pub enum ResultSource<'a> {
+    /// Run the queries on the databases. When a folder is given, the outputs are recorded in it
+    /// to be replayed later with ```ResultSource::Fixtures```.
+    Live {
+        db_postgresql: &'a PostgreSQL,
+        db_neo4j: &'a Neo4j,
+        record_folder: Option<String>,
+    },
+    /// Read the outputs recorded in the folder : ```query_<N>.sql.csv``` and ```query_<N>.cypher.txt```.
+    Fixtures(String),
+}
+
+/// A normalized value of a result set.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Null,
+    Boolean(bool),
+    Number(f64),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<Cell>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Status {
+    Equivalent,
+    Mismatch(String),
+    Untranslatable(String),
+    ExecutionError(String),
+}
+
+/// The result of the comparison of one query of the corpus.
+#[derive(Debug)]
+pub struct QueryReport {
+    pub index: usize,
+    pub line: usize,
+    pub sql: String,
+    pub cypher: Option<String>,
+    pub status: Status,
+    pub sql_duration: Option<Duration>,
+    pub cypher_duration: Option<Duration>,
+}
+
+impl Cell {
+    /// Normalize the text of a value : booleans, numbers and timestamps are detected.
+    fn from_text(text: &str) -> Self {
+        let text = text.trim();
+        match text {
+            "t" | "true" | "TRUE" => return Cell::Boolean(true),
+            "f" | "false" | "FALSE" => return Cell::Boolean(false),
+            _ => {}
+        }
+        if let Ok(number) = text.parse::<f64>() {
+            if number.is_finite() {
+                return Cell::Number(number);
+            }
+        }
+        Cell::Text(normalize_temporal(text))
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Cell::Null => 0,
+            Cell::Boolean(_) => 1,
+            Cell::Number(_) => 2,
+            Cell::Text(_) => 3,
+        }
+    }
+
+    fn cmp(&self, other: &Cell) -> Ordering {
+        match (self, other) {
+            (Cell::Boolean(a), Cell::Boolean(b)) => a.cmp(b),
+            (Cell::Number(a), Cell::Number(b)) => a.total_cmp(b),
+            (Cell::Text(a), Cell::Text(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+
+    /// The numbers are equals if their relative difference is lower than the ```tolerance```.
+    fn equals(&self, other: &Cell, tolerance: f64) -> bool {
+        match (self, other) {
+            (Cell::Number(a), Cell::Number(b)) => {
+                (a - b).abs() <= tolerance * a.abs().max(b.abs()).max(1.0)
+            }
+            _ => self == other,
+        }
+    }
+}
+
+/// PostgreSQL write the timestamps with a space, Neo4j with a ```T```.
+fn normalize_temporal(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let is_date = bytes.len() > 11
+        && bytes[..10]
+            .iter()
+            .enumerate()
+            .all(|(index, c)| if index == 4 || index == 7 { *c == b'-' } else { c.is_ascii_digit() });
+    if is_date && bytes[10] == b' ' && bytes[11].is_ascii_digit() {
+        format!("{}T{}", &text[..10], &text[11..])
+    } else {
+        String::from(text)
+    }
+}
+
+/// Parse the CSV output of ```psql --csv```.<br>
+/// The unquoted empty values are the NULL values, the quoted ones are empty strings.
+pub fn parse_psql_csv(content: &str) -> Result<ResultSet, String> {
+    let (columns, records) = parse_psql_records(content)?;
+    let rows = records
+        .into_iter()
+        .map(|record| {
+            record
+                .into_iter()
+                .map(|value| psql_cell(value.as_deref()))
+                .collect()
+        })
+        .collect();
+    Ok(ResultSet { columns, rows })
+}
+
+/// Normalize a raw value of a PostgreSQL result.
+pub fn psql_cell(value: Option<&str>) -> Cell {
+    match value {
+        None => Cell::Null,
+        Some(value) => Cell::from_text(value),
+    }
+}
+
+/// The columns of a PostgreSQL result and its raw values (```None``` for NULL).
+pub type PsqlRecords = (Vec<String>, Vec<Vec<Option<String>>>);
+
+/// Parse the CSV output of ```psql --csv``` into its columns and its raw values.
+pub fn parse_psql_records(content: &str) -> Result<PsqlRecords, String> {
+    let mut records: Vec<Vec<Option<String>>> = Vec::new();
+    let mut record: Vec<Option<String>> = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut in_quotes = false;
+    let mut chars = content.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                _ => field.push(c),
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_quotes = true;
+                quoted = true;
+            }
+            ',' => {
+                record.push(take_field(&mut field, &mut quoted));
+            }
+            '\r' => {}
+            '\n' => {
+                record.push(take_field(&mut field, &mut quoted));
+                records.push(std::mem::take(&mut record));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || quoted || !record.is_empty() {
+        record.push(take_field(&mut field, &mut quoted));
+        records.push(record);
+    }
+    if in_quotes {
+        return Err(String::from("ERROR : Unterminated quoted value in the PostgreSQL result."));
+    }
+
+    let mut records = records.into_iter();
+    let columns = records
+        .next()
+        .ok_or_else(|| String::from("ERROR : The PostgreSQL result is empty."))?
+        .into_iter()
+        .map(|column| column.unwrap_or_default())
+        .collect::<Vec<String>>();
+    Ok((columns, records.collect()))
+}
+
+fn take_field(field: &mut String, quoted: &mut bool) -> Option<String> {
+    let value = std::mem::take(field);
+    let was_quoted = std::mem::replace(quoted, false);
+    if value.is_empty() && !was_quoted {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+/// Split a line of the *plain* output format of *cypher-shell* into its values.
+fn split_cypher_line(line: &str) -> Vec<String> {
+    let mut values = Vec::new();
+    let mut value = String::new();
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_string {
+            value.push(c);
+            match c {
+                '\\' => {
+                    if let Some(escaped) = chars.next() {
+                        value.push(escaped);
+                    }
+                }
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => {
+                in_string = true;
+                value.push(c);
+            }
+            '[' | '{' | '(' => {
+                depth += 1;
+                value.push(c);
+            }
+            ']' | '}' | ')' => {
+                depth -= 1;
+                value.push(c);
+            }
+            ',' if depth == 0 && chars.peek() == Some(&' ') => {
+                chars.next();
+                values.push(std::mem::take(&mut value));
+            }
+            _ => value.push(c),
+        }
+    }
+    values.push(value);
+    values
+}
+
+/// Parse the *plain* output of *cypher-shell* : the first line contains the columns, the strings are quoted.
+pub fn parse_cypher_plain(content: &str) -> Result<ResultSet, String> {
+    let (columns, records) = parse_cypher_records(content)?;
+    let rows = records
+        .into_iter()
+        .map(|record| record.iter().map(|value| cypher_cell(value)).collect::<Vec<Cell>>())
+        .collect();
+    Ok(ResultSet { columns, rows })
+}
+
+/// The columns of a Neo4j result and its raw values, written like Cypher literals (the strings are quoted).
+pub type CypherRecords = (Vec<String>, Vec<Vec<String>>);
+
+/// Parse the *plain* output of *cypher-shell* into its columns and its raw values.
+pub fn parse_cypher_records(content: &str) -> Result<CypherRecords, String> {
+    let content = content.trim_start();
+    let content = content.strip_prefix(CYPHER_RESULT_PREFIX).unwrap_or(content);
+    let mut lines = content.lines().filter(|line| !line.trim().is_empty());
+    let columns = match lines.next() {
+        Some(header) => split_cypher_line(header),
+        None => return Ok((vec![], vec![])),
+    };
+    Ok((columns, lines.map(split_cypher_line).collect()))
+}
+
+/// Normalize a raw value of a Neo4j result.
+pub fn cypher_cell(value: &str) -> Cell {
+    if value == "NULL" {
+        Cell::Null
+    } else if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+        // PostgreSQL doesn't type its CSV output, so the strings are normalized the same way.
+        Cell::from_text(&value[1..value.len() - 1].replace("\\\"", "\"").replace("\\\\", "\\"))
+    } else {
+        Cell::from_text(value)
+    }
+}
+
+/// Compare the two result sets without considering the order of the rows and the names of the columns.
+pub fn compare_result_sets(sql_result: &ResultSet, cypher_result: &ResultSet, tolerance: f64) -> Status {
+    if sql_result.columns.len() != cypher_result.columns.len() {
+        return Status::Mismatch(format!(
+            "The number of columns is different : {} in SQL {:?}, {} in Cypher {:?}",
+            sql_result.columns.len(),
+            sql_result.columns,
+            cypher_result.columns.len(),
+            cypher_result.columns
+        ));
+    }
+    if sql_result.rows.len() != cypher_result.rows.len() {
+        return Status::Mismatch(format!(
+            "The number of rows is different : {} in SQL, {} in Cypher",
+            sql_result.rows.len(),
+            cypher_result.rows.len()
+        ));
+    }
+
+    let sort = |rows: &[Vec<Cell>]| {
+        let mut rows = rows.to_vec();
+        rows.sort_by(|a, b| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(x, y)| x.cmp(y))
+                .find(|ordering| *ordering != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        });
+        rows
+    };
+    let sql_rows = sort(&sql_result.rows);
+    let cypher_rows = sort(&cypher_result.rows);
+
+    let mut differences = Vec::new();
+    for (sql_row, cypher_row) in sql_rows.iter().zip(cypher_rows.iter()) {
+        let is_equal = sql_row.len() == cypher_row.len()
+            && sql_row
+                .iter()
+                .zip(cypher_row.iter())
+                .all(|(a, b)| a.equals(b, tolerance));
+        if !is_equal {
+            differences.push(format!("SQL {:?} <> Cypher {:?}", sql_row, cypher_row));
+        }
+    }
+    if differences.is_empty() {
+        Status::Equivalent
+    } else {
+        let count = differences.len();
+        differences.truncate(5);
+        Status::Mismatch(format!("{} rows are different, for example :\n{}", count, differences.join("\n")))
+    }
+}
+
+/// Read the corpus of SQL queries : the ```SQL``` code blocks of a Markdown file (like ***example_queries.md***)
+/// or the statements of a SQL file.
+pub fn read_corpus(corpus_path: &str) -> Result<Vec<(usize, String)>, String> {
+    let content = fs::read_to_string(corpus_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", corpus_path, error))?;
+    if !corpus_path.ends_with(".md") {
+        return Ok(split_sql_statements(&content));
+    }
+
+    let mut queries = Vec::new();
+    let mut block: Option<(usize, String)> = None;
+    for (index, line) in content.lines().enumerate() {
+        let trimmed = line.trim();
+        match &mut block {
+            None if trimmed.eq_ignore_ascii_case("```sql") => block = Some((index + 2, String::new())),
+            None => {}
+            Some(_) if trimmed.starts_with("```") => {
+                if let Some((line, sql)) = block.take() {
+                    queries.extend(
+                        split_sql_statements(&sql)
+                            .into_iter()
+                            .map(|(offset, query)| (line + offset - 1, query)),
+                    );
+                }
+            }
+            Some((_, sql)) => {
+                sql.push_str(line);
+                sql.push('\n');
+            }
+        }
+    }
+    Ok(queries)
+}
+
+impl ResultSource<'_> {
+    fn run(&self, index: usize, sql: &str, cypher: &str) -> Result<(String, Duration, String, Duration), String> {
+        match self {
+            ResultSource::Live {
+                db_postgresql,
+                db_neo4j,
+                record_folder,
+            } => {
+                let start = Instant::now();
+                let sql_output = db_postgresql.execute_query(sql, true)?;
+                let sql_duration = start.elapsed();
+                let start = Instant::now();
+                let cypher_output = db_neo4j.execute_query(cypher)?;
+                let cypher_duration = start.elapsed();
+                if let Some(folder) = record_folder {
+                    write_file(String::clone(&sql_output), &fixture_path(folder, index, "sql.csv"))?;
+                    write_file(String::clone(&cypher_output), &fixture_path(folder, index, "cypher.txt"))?;
+                }
+                Ok((sql_output, sql_duration, cypher_output, cypher_duration))
+            }
+            ResultSource::Fixtures(folder) => {
+                let read = |extension: &str| {
+                    let path = fixture_path(folder, index, extension);
+                    fs::read_to_string(&path)
+                        .map_err(|error| format!("ERROR : when try to read the fixture : {}\n{}", path, error))
+                };
+                Ok((read("sql.csv")?, Duration::ZERO, read("cypher.txt")?, Duration::ZERO))
+            }
+        }
+    }
+}
+
+fn fixture_path(folder: &str, index: usize, extension: &str) -> String {
+    Path::new(folder)
+        .join(format!("query_{}.{}", index, extension))
+        .display()
+        .to_string()
+}
+
+/// Run the equivalence tests of all the queries of the corpus.
+pub fn run_equivalence(
+    corpus: &[(usize, String)],
+    source: &ResultSource,
+    tolerance: f64,
+    mapping: &Mapping,
+) -> Vec<QueryReport> {
+    let mut reports = Vec::new();
+    for (index, (line, sql)) in corpus.iter().enumerate() {
+        let index = index + 1;
+        let mut report = QueryReport {
+            index,
+            line: *line,
+            sql: String::clone(sql),
+            cypher: None,
+            status: Status::Equivalent,
+            sql_duration: None,
+            cypher_duration: None,
+        };
+        let cypher = match generate_cypher_query(sql, mapping) {
+            Ok(cypher) => cypher,
+            Err(error) => {
+                report.status = Status::Untranslatable(error);
+                reports.push(report);
+                continue;
+            }
+        };
+        report.cypher = Some(String::clone(&cypher));
+        report.status = match source.run(index, sql, &cypher) {
+            Ok((sql_output, sql_duration, cypher_output, cypher_duration)) => {
+                if !sql_duration.is_zero() || !cypher_duration.is_zero() {
+                    report.sql_duration = Some(sql_duration);
+                    report.cypher_duration = Some(cypher_duration);
+                }
+                match (parse_psql_csv(&sql_output), parse_cypher_plain(&cypher_output)) {
+                    (Ok(sql_result), Ok(cypher_result)) => {
+                        compare_result_sets(&sql_result, &cypher_result, tolerance)
+                    }
+                    (Err(error), _) | (_, Err(error)) => Status::ExecutionError(error),
+                }
+            }
+            Err(error) => Status::ExecutionError(error),
+        };
+        reports.push(report);
+    }
+    reports
+}
+
+/// Generate the report of the equivalence tests in a human readable form and in JSON.
+pub fn format_report(reports: &[QueryReport]) -> (String, Value) {
+    let count = |expected: fn(&Status) -> bool| reports.iter().filter(|r| expected(&r.status)).count();
+    let equivalent = count(|status| matches!(status, Status::Equivalent));
+    let mismatches = count(|status| matches!(status, Status::Mismatch(_)));
+    let untranslatable = count(|status| matches!(status, Status::Untranslatable(_)));
+    let errors = count(|status| matches!(status, Status::ExecutionError(_)));
+
+    let mut text = format!(
+        "Equivalence report : {} queries\n  Equivalent : {}\n  Mismatches : {}\n  Untranslatable : {}\n  Execution errors : {}\n",
+        reports.len(),
+        equivalent,
+        mismatches,
+        untranslatable,
+        errors
+    );
+    let mut queries = Vec::new();
+    for report in reports {
+        let (status, details) = match &report.status {
+            Status::Equivalent => ("equivalent", None),
+            Status::Mismatch(details) => ("mismatch", Some(details)),
+            Status::Untranslatable(details) => ("untranslatable", Some(details)),
+            Status::ExecutionError(details) => ("execution_error", Some(details)),
+        };
+        text.push_str(&format!("\nQuery {} (line {}) : {}\n", report.index, report.line, status.to_uppercase()));
+        if let (Some(sql_duration), Some(cypher_duration)) = (report.sql_duration, report.cypher_duration) {
+            text.push_str(&format!(
+                "  Timing : SQL {:.3} s | Cypher {:.3} s\n",
+                sql_duration.as_secs_f64(),
+                cypher_duration.as_secs_f64()
+            ));
+        }
+        if let Some(details) = details {
+            for line in details.lines() {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+        queries.push(json!({
+            "index": report.index,
+            "line": report.line,
+            "sql": report.sql,
+            "cypher": report.cypher,
+            "status": status,
+            "details": details,
+            "sql_seconds": report.sql_duration.map(|d| d.as_secs_f64()),
+            "cypher_seconds": report.cypher_duration.map(|d| d.as_secs_f64()),
+        }));
+    }
+    let json_report = json!({
+        "summary": {
+            "queries": reports.len(),
+            "equivalent": equivalent,
+            "mismatches": mismatches,
+            "untranslatable": untranslatable,
+            "execution_errors": errors,
+        },
+        "queries": queries,
+    });
+    (text, json_report)
+}
+
+#[test]
+fn test_compare_result_sets() {
+    let sql_result = parse_psql_csv("order_id,freight,shipped,name\n2,10.50,t,\"a, b\"\n1,3,f,\n3,0.1,t,\"\"\n").unwrap();
+    let cypher_result = parse_cypher_plain(
+        "\nResult of the cypher query :\no.order_id, o.freight, o.shipped, o.name\n1, 3.0, FALSE, NULL\n3, 0.1000000001, TRUE, \"\"\n2, 10.5, TRUE, \"a, b\"\n",
+    )
+    .unwrap();
+    assert_eq!(sql_result.rows[1][3], Cell::Null);
+    assert_eq!(sql_result.rows[2][3], Cell::Text(String::new()));
+    assert_eq!(compare_result_sets(&sql_result, &cypher_result, 1e-6), Status::Equivalent);
+
+    let cypher_result = parse_cypher_plain("id, total\n1, 3.5\n").unwrap();
+    let sql_result = parse_psql_csv("id,total\n1,3.6\n").unwrap();
+    assert!(matches!(
+        compare_result_sets(&sql_result, &cypher_result, 1e-6),
+        Status::Mismatch(_)
+    ));
+}
+
+#[test]
+fn test_run_equivalence_with_fixtures() {
+    let folder = std::env::temp_dir().join("neo4j_migrator_equivalence_fixtures");
+    fs::create_dir_all(&folder).unwrap();
+    let folder = folder.display().to_string();
+    write_file(String::from("name\nAlice\nBob\n"), &fixture_path(&folder, 1, "sql.csv")).unwrap();
+    write_file(
+        String::from("customers.name\n\"Bob\"\n\"Alice\"\n"),
+        &fixture_path(&folder, 1, "cypher.txt"),
+    )
+    .unwrap();
+
+    let corpus = vec![
+        (1, String::from("SELECT name FROM customers")),
+        (2, String::from("SELECT name FROM a, b")),
+    ];
+    let reports = run_equivalence(&corpus, &ResultSource::Fixtures(folder), 1e-6, &Mapping::default());
+    assert_eq!(reports[0].status, Status::Equivalent);
+    assert!(matches!(reports[1].status, Status::Untranslatable(_)));
+}
@@ -1,22 +1,204 @@
 //! This module contains the logic to transform the data from the relationnal database to neo4j data
 
+use std::collections::HashMap;
 use std::env;
 use std::fs::OpenOptions;
 use std::fs::{self, DirEntry};
-use std::io::Write;
 use std::path::Path;
+use std::sync::Mutex;
+use std::time::Instant;
 
-use polars::prelude::{CsvReadOptions, CsvWriter, Series, Column, DataFrame, StringChunked, IntoColumn, SerWriter, SerReader, NamedFrom, DataFrameJoinOps};
+use polars::prelude::{DataType, CsvWriter, Series, DataFrame, StringChunked, IntoColumn, SerWriter, NamedFrom};
 use serde_json::Value;
 
+use crate::binary::*;
+use crate::categories::*;
+use crate::custom_types::*;
+use crate::hierarchy::*;
+use crate::integrity::{ForeignKeyIntegrity, ReferencedKeys};
+use crate::json_columns::process_json_columns;
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+use crate::mapping::{CategoryStrategy, JsonStrategy, Mapping};
 use crate::neo4j::*;
+use crate::report::MigrationReport;
+use crate::spatial::*;
+use crate::translate::translate_expression;
 use crate::utils::*;
+use crate::workers::run_pool;
 
-const HEADERS_FK: &str = ":START_ID;:END_ID;:TYPE\n";
+pub const HEADERS_FK: &str = ":START_ID;:END_ID;:TYPE\n";
+
+/// The conversion of the values of a column that can't be deduced from its Neo4j type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ColumnConversion {
+    Spatial(SpatialColumn),
+    Category(CategoryColumn),
+    /// The fields of the composite type, with their Neo4j types.
+    Composite(Vec<(String, String)>),
+    Binary(BinaryColumn),
+    Hierarchy(HierarchyColumn),
+}
+
+impl ColumnConversion {
+    /// Describe the conversion, for the migration plan.
+    fn describe(&self) -> String {
+        match self {
+            ColumnConversion::Spatial(SpatialColumn::Point { .. }) => String::from("point"),
+            ColumnConversion::Spatial(SpatialColumn::Geometry { as_points: true, .. }) => String::from("geometry as a list of points"),
+            ColumnConversion::Spatial(SpatialColumn::Geometry { .. }) => String::from("geometry in WKT"),
+            ColumnConversion::Category(CategoryColumn::Labels) => String::from("enum as extra labels"),
+            ColumnConversion::Category(CategoryColumn::Nodes { label, relationship_type, .. }) => {
+                format!("enum as :{} nodes with the relationships {}", label, relationship_type)
+            }
+            ColumnConversion::Category(CategoryColumn::Lookup { table, value_column, property: Some(property), .. }) => {
+                format!("lookup of {}.{} in the property {}", table, value_column, property)
+            }
+            ColumnConversion::Category(CategoryColumn::Lookup { table, value_column, property: None, .. }) => {
+                format!("lookup of {}.{} as extra labels", table, value_column)
+            }
+            ColumnConversion::Composite(fields) => format!("composite type with {} fields", fields.len()),
+            ColumnConversion::Binary(binary) => format!(
+                "{} stored in {:?}{}",
+                if binary.large_object { "large object" } else { "bytea" },
+                binary.storage.format,
+                binary.storage.threshold.map(|threshold| format!(", in files above {} bytes", threshold)).unwrap_or_default()
+            ),
+            ColumnConversion::Hierarchy(hierarchy) => format!(
+                "hierarchy on {}{}",
+                hierarchy.key_column,
+                if hierarchy.depth { " with depth and root" } else { "" }
+            ),
+        }
+    }
+}
+
+/// The columns that need a conversion, by label.
+pub type ColumnConversions = HashMap<String, Vec<(String, ColumnConversion)>>;
+
+/// Return the labels of the ancestors of the tables that inherit other tables (```INHERITS```), the nearest
+/// first : the nodes of a child table also get the labels of its parents.
+pub fn inheritance_labels(tables: &[Value]) -> HashMap<String, Vec<String>> {
+    let parents = tables
+        .iter()
+        .filter_map(|table| {
+            let label = table["table_name"].as_str()?.to_uppercase();
+            let parents = table["parent_tables"]
+                .as_array()?
+                .iter()
+                .filter_map(|parent| parent.as_str())
+                .map(|parent| parent.to_uppercase())
+                .collect::<Vec<String>>();
+            Some((label, parents))
+        })
+        .collect::<HashMap<String, Vec<String>>>();
+    let mut labels = HashMap::new();
+    for label in parents.keys() {
+        let mut ancestors = parents[label].clone();
+        let mut index = 0;
+        while index < ancestors.len() {
+            for parent in parents.get(&ancestors[index]).into_iter().flatten() {
+                if parent != label && !ancestors.contains(parent) {
+                    ancestors.push(String::clone(parent));
+                }
+            }
+            index += 1;
+        }
+        if !ancestors.is_empty() {
+            labels.insert(String::clone(label), ancestors);
+        }
+    }
+    labels
+}
+
+/// The files generated from the meta-data, before they are written.
+#[derive(Debug, Default)]
+pub struct MetaDataFiles {
+    /// The headers of the nodes files, by label.
+    pub headers: Vec<(String, String)>,
+    /// The relationships files, they only contain ```HEADERS_FK```.
+    pub relationship_files: Vec<String>,
+    pub constraints: String,
+    pub triggers: String,
+    /// The content of the foreign keys file, read by ```extract_relationships```.
+    pub foreign_keys: String,
+    /// The CHECK constraints that can't be translated.
+    pub checks_report: Vec<String>,
+    /// The columns stored as ```STRING``` because their type can't be converted.
+    pub unsupported_columns: Vec<String>,
+    /// The conversions of the columns (label, column, conversion).
+    pub conversions: Vec<(String, String, String)>,
+    /// The labels of the parents of the inherited tables.
+    pub parent_labels: HashMap<String, Vec<String>>,
+}
+
+/// Translate the meta-data into the files needed to import the data in Neo4j, without writing them.<br>
+/// The columns whose values need a conversion are added in ```conversions```.
+fn translate_meta_data(json_object: Value, mapping: &Mapping, conversions: &mut ColumnConversions) -> Result<MetaDataFiles, String> {
+    let Value::Array(vector) = json_object else {
+        return Err(format!("Expected a Value::Object(Map<_,_>) but found :\n{}",json_object));
+    };
+    let mut files = MetaDataFiles {
+        parent_labels: inheritance_labels(&vector),
+        ..Default::default()
+    };
+    for table in vector {
+        let label = String::from(table["table_name"].as_str().ok_or_else(|| {
+            format!("Error when try to get the 'table_name' field in {}", table)
+        })?)
+        .to_uppercase();
+        let mut headers = String::from(":ID;");
+        let mut foreign_keys: Vec<String> = Vec::new();
+
+        let columns = table["columns"].as_array().ok_or_else(|| {
+            format!("Error when try to get the 'columns' field in {}", table)
+        })?;
+
+        let mut label_conversions = Vec::new();
+        process_columns(columns,label.as_str(),&mut files.constraints, &mut files.triggers, &mut headers, &mut foreign_keys, &mut files.foreign_keys, mapping, &mut label_conversions, &mut files.unsupported_columns)?;
+        for (column_name, conversion) in &label_conversions {
+            files.conversions.push((String::clone(&label), String::clone(column_name), conversion.describe()));
+        }
+        if !label_conversions.is_empty() {
+            conversions.insert(String::clone(&label), label_conversions);
+        }
+        process_check_constraints(&table, columns, label.as_str(), &mut files.triggers, &mut files.checks_report)?;
+
+        headers.push_str(":LABEL\n");
+        files.headers.push((label, headers));
+        files.relationship_files.extend(foreign_keys);
+    }
+    Ok(files)
+}
+
+/// Translate the meta-data exported from PostgreSQL without writing any file or running any script, to
+/// preview the migration.
+pub fn plan_meta_data(meta_data_path: &str, mapping: &Mapping) -> Result<MetaDataFiles, String> {
+    let content = fs::read_to_string(meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    translate_meta_data(json_object, mapping, &mut ColumnConversions::new())
+}
+
+/// Return the conversions of the columns chosen by the meta-data and the ```mapping```, by label.
+pub fn meta_data_conversions(meta_data: &Value, mapping: &Mapping) -> Result<ColumnConversions, String> {
+    let mut conversions = ColumnConversions::new();
+    translate_meta_data(meta_data.clone(), mapping, &mut conversions)?;
+    Ok(conversions)
+}
 
 /// Generate **CSV** files who contains the **HEADERS** needed to generate and organise the
-/// data to be imported to Neo4j.
-fn process_meta_data(db_neo4j: &Neo4j,meta_data_path: &str,foreign_key_path: &str) -> Result<String, String> {
+/// data to be imported to Neo4j.<br>
+/// The columns whose values need a conversion are added in ```conversions```, the labels of the parents of
+/// the inherited tables in ```parent_labels```.
+fn process_meta_data(
+    db_neo4j: &Neo4j,
+    meta_data_path: &str,
+    foreign_key_path: &str,
+    mapping: &Mapping,
+    conversions: &mut ColumnConversions,
+    parent_labels: &mut HashMap<String, Vec<String>>,
+    report: &mut MigrationReport
+) -> Result<String, String> {
     if let Err(error) = clean_directory(&db_neo4j.get_import_folder()) {
         return Err(error);
     }
@@ -40,80 +222,77 @@ fn process_meta_data(db_neo4j: &Neo4j,meta_data_path: &str,foreign_key_path: &st
             .display()
     );
 
-    let mut constraints_content = String::new();
-    let mut triggers_content = String::new();
-    let mut fk_content = String::new();
-
-    match json_object {
-        Value::Array(vector) => {
-            for table in vector {
-                let label = String::from(table["table_name"].as_str().ok_or_else(|| {
-                    format!("Error when try to get the 'table_name' field in {}", table)
-                })?)
-                .to_uppercase();
-                let mut headers = String::from(":ID;");
-                let mut foreign_keys: Vec<String> = Vec::new();
-
-                let columns = table["columns"].as_array().ok_or_else(|| {
-                    format!("Error when try to get the 'columns' field in {}", table)
-                })?;
-
-                process_columns(columns,label.as_str(),&mut constraints_content, &mut triggers_content, &mut headers, &mut foreign_keys, &mut fk_content)?;
+    let files = translate_meta_data(json_object.clone(), mapping, conversions)?;
+    report.record_meta_data(&json_object, &files);
+    *parent_labels = files.parent_labels;
 
-                headers.push_str(":LABEL\n");
-                let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), label);
-                write_file(headers, &file_path)?;
-                println!("\nSuccessfully write the headers in {}\n", file_path);
+    for (label, headers) in files.headers {
+        let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), label);
+        write_file(headers, &file_path)?;
+        println!("\nSuccessfully write the headers in {}\n", file_path);
+    }
 
-                for fk in foreign_keys {
-                    let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), fk);
-                    write_file(String::from(HEADERS_FK), &file_path)?;
-                    println!("\nSuccessfully write the fk headers in {}\n", file_path);
-                }
-            }
+    for fk in files.relationship_files {
+        let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), fk);
+        write_file(String::from(HEADERS_FK), &file_path)?;
+        println!("\nSuccessfully write the fk headers in {}\n", file_path);
+    }
 
-            match write_file(constraints_content, &constraints_path) {
-                Ok(_) => match db_neo4j.execute_script(&constraints_path) {
-                    Ok(_) => println!(
-                        "\nSuccessfully create and run the Cypher script : {}\n",
-                        constraints_path
-                    ),
-                    Err(error) => {
-                        return Err(format!("{}", error));
-                    }
-                },
-                Err(error) => {
-                    return Err(format!("{}", error));
-                }
+    match write_file(files.constraints, &constraints_path) {
+        Ok(_) => match db_neo4j.execute_script(&constraints_path) {
+            Ok(_) => println!(
+                "\nSuccessfully create and run the Cypher script : {}\n",
+                constraints_path
+            ),
+            Err(error) => {
+                return Err(format!("{}", error));
             }
+        },
+        Err(error) => {
+            return Err(format!("{}", error));
+        }
+    }
 
-            match write_file(triggers_content, &triggers_path) {
-                Ok(_) => match db_neo4j.execute_script(&triggers_path) {
-                    Ok(_) => println!(
-                        "\nSuccessfully create and run the Cypher script : {}\n",
-                        triggers_path
-                    ),
-                    Err(error) => {
-                        return Err(format!("{}", error));
-                    }
-                },
-                Err(error) => {
-                    return Err(format!("{}", error));
-                }
+    match write_file(files.triggers, &triggers_path) {
+        Ok(_) => match db_neo4j.execute_script(&triggers_path) {
+            Ok(_) => println!(
+                "\nSuccessfully create and run the Cypher script : {}\n",
+                triggers_path
+            ),
+            Err(error) => {
+                return Err(format!("{}", error));
             }
+        },
+        Err(error) => {
+            return Err(format!("{}", error));
+        }
+    }
 
-            write_file(fk_content, foreign_key_path)?;
-            println!("\nSuccessfully write the {} file", foreign_key_path);
+    write_file(files.foreign_keys, foreign_key_path)?;
+    println!("\nSuccessfully write the {} file", foreign_key_path);
 
-            Ok(String::from("\nSuccessfully create and write the Headers for the Neo4j import."))
-        }
-        _ => {
-            Err(format!("Expected a Value::Object(Map<_,_>) but found :\n{}",json_object))
-        }
+    let checks_report_path = format!(
+        "{}/Neo4j/check_constraints_report.txt",
+        env::current_dir()
+            .map_err(|error| format!("{}", error))?
+            .display()
+    );
+    if files.checks_report.is_empty() {
+        write_file(String::from("All the CHECK constraints are translated.\n"), &checks_report_path)?;
+    } else {
+        warning(format!(
+            "{} CHECK constraints can't be translated, see the report {}",
+            files.checks_report.len(),
+            checks_report_path
+        ));
+        write_file(format!("{}\n", files.checks_report.join("\n")), &checks_report_path)?;
     }
+
+    Ok(String::from("\nSuccessfully create and write the Headers for the Neo4j import."))
 }
 
 /// Process on the meta-data for each column.
+#[allow(clippy::too_many_arguments)]
 fn process_columns(
     columns: &Vec<Value>,
     label: &str,
@@ -121,7 +300,10 @@ fn process_columns(
     triggers_content: &mut String,
     headers: &mut String,
     foreign_keys: &mut Vec<String>,
-    fk_content: &mut String
+    fk_content: &mut String,
+    mapping: &Mapping,
+    conversions: &mut Vec<(String, ColumnConversion)>,
+    unsupported_columns: &mut Vec<String>
 ) -> Result<(), String> {
     for column in columns {
         let column_name =
@@ -137,24 +319,116 @@ fn process_columns(
                 let pg_data_type = column["data_type"].as_str().ok_or_else(|| {
                     format!("Error when try to get the 'data_type' field in {}", column)
                 })?;
-                let data_type = Neo4j::convert_postgresql_type(pg_data_type)
-                    .map_err(|error| format!("{}", error))?;
+                // information_schema name all the arrays 'ARRAY', their elements type is in the udt_name.
+                let pg_data_type = match (pg_data_type, column["udt_name"].as_str()) {
+                    ("ARRAY", Some(udt_name)) => udt_name,
+                    // The definition of the PostGIS types contains the geometry type and the SRID.
+                    ("USER-DEFINED", Some(udt_name)) => column["type_definition"].as_str().unwrap_or(udt_name),
+                    _ => pg_data_type,
+                };
+                // The domains are migrated with their base type, their CHECK constraints are translated with the
+                // CHECK constraints of the table.
+                let pg_data_type = column["domain"]["base_type"].as_str().unwrap_or(pg_data_type);
+                // The values of the enums are resolved with pg_enum in the meta-data.
+                let enum_values = column["enum_values"].as_array().map(|values| {
+                    values
+                        .iter()
+                        .filter_map(|value| value.as_str())
+                        .map(String::from)
+                        .collect::<Vec<String>>()
+                });
+                if let Some(values) = &enum_values {
+                    match mapping.enum_strategy(label, &column_name)? {
+                        CategoryStrategy::Property => {
+                            triggers_content.push_str(&enum_trigger(&function_name, label, &column_name, values));
+                        }
+                        CategoryStrategy::Labels => {
+                            conversions.push((column_name, ColumnConversion::Category(CategoryColumn::Labels)));
+                            continue;
+                        }
+                        CategoryStrategy::Nodes => {
+                            let category = category_label(pg_data_type);
+                            constraints_content.push_str(&format!("create constraint unique_{}_value if not exists for (n:{}) require n.value is unique;\n",
+                            category.to_lowercase(),category));
+                            let relationship_type = format!("{}_ref_{}", label, column_name.to_uppercase());
+                            conversions.push((column_name, ColumnConversion::Category(CategoryColumn::Nodes {
+                                label: category,
+                                values: values.clone(),
+                                relationship_type,
+                            })));
+                            continue;
+                        }
+                    }
+                }
+                let mut data_type = match (enum_values, column["composite_fields"].as_array()) {
+                    (Some(_), _) => String::from("STRING"),
+                    (None, Some(fields)) => {
+                        let mut composite_fields = Vec::new();
+                        for field in fields {
+                            let field_name = field["name"].as_str().ok_or_else(|| {
+                                format!("Error when try to get the 'name' field in {}", field)
+                            })?;
+                            let field_type = field["type"].as_str().ok_or_else(|| {
+                                format!("Error when try to get the 'type' field in {}", field)
+                            })?;
+                            let field_type = convert_type_or_string(field_type, label, &format!("{}.{}", column_name, field_name), unsupported_columns);
+                            composite_fields.push((String::from(field_name), field_type));
+                        }
+                        conversions.push((String::clone(&column_name), ColumnConversion::Composite(composite_fields)));
+                        // The JSON of the fields, flattened or exploded in extract_nodes
+                        String::from("STRING")
+                    }
+                    (None, None) => convert_type_or_string(pg_data_type, label, &column_name, unsupported_columns),
+                };
 
-                if let Some(value) = column["primary_key"].as_bool() {
-                    if value == true {
-                        constraints_content.push_str(&format!("create constraint unique_{} if not exists for (n:{}) require n.{} is unique;\n",
-                        function_name,label,column_name));
+                // The binary values are stored in the node or in external files, with their path and SHA-256.
+                let mut binary_headers = String::new();
+                let large_object = is_large_object(column, label, mapping);
+                if large_object || pg_data_type.eq_ignore_ascii_case("bytea") {
+                    let storage = mapping.binary_storage(label, &column_name)?;
+                    data_type = String::from(storage.data_type());
+                    if storage.is_external() {
+                        binary_headers = format!("{}_path:STRING;{}_sha256:STRING;", column_name, column_name);
+                    }
+                    conversions.push((String::clone(&column_name), ColumnConversion::Binary(BinaryColumn { storage, large_object })));
+                }
+
+                let as_points = mapping.geometry_as_points(label, &column_name)?;
+                if let Some(spatial) = spatial_column(pg_data_type, as_points) {
+                    match spatial {
+                        SpatialColumn::Point { .. } => {
+                            constraints_content.push_str(&format!("create point index point_{} if not exists for (n:{}) on (n.{});\n",
+                            function_name,label,column_name));
+                        }
+                        SpatialColumn::Geometry { as_points: true, .. } => data_type = String::from("POINT[]"),
+                        SpatialColumn::Geometry { .. } => {}
                     }
+                    conversions.push((String::clone(&column_name), ColumnConversion::Spatial(spatial)));
                 }
-                if let Some(value) = column["is_nullable"].as_str() {
-                    if value == "NO" {
-                        constraints_content.push_str(&format!("create constraint nonull_{} if not exists for (n:{}) require n.{} is not null;\n",
-                        function_name,label,column_name));
+
+                // The JSON and composite columns flattened or moved to child nodes aren't properties of the nodes.
+                let strategy = match column["composite_fields"].is_array() {
+                    true => mapping.composite_strategy(label, &column_name)?,
+                    false => mapping.json_strategy(label, &column_name)?,
+                };
+                if strategy == JsonStrategy::String {
+                    if let Some(value) = column["primary_key"].as_bool() {
+                        if value == true {
+                            constraints_content.push_str(&format!("create constraint unique_{} if not exists for (n:{}) require n.{} is unique;\n",
+                            function_name,label,column_name));
+                        }
+                    }
+                    if let Some(value) = column["is_nullable"].as_str() {
+                        if value == "NO" {
+                            constraints_content.push_str(&format!("create constraint nonull_{} if not exists for (n:{}) require n.{} is not null;\n",
+                            function_name,label,column_name));
+                        }
                     }
+                    triggers_content.push_str(&format!(r#"CALL apoc.trigger.add('type_{}',"MATCH (m:{}) WHERE m.{} IS NOT NULL AND NOT valueType(m.{}) = '{}' CALL apoc.util.validate(true, 'ERROR : The type of the field {} need to be a {} .', []) RETURN m",{{phase: 'before'}});{}"#
+                        ,function_name,label,column_name,column_name,data_type,column_name,data_type,"\n"));
                 }
-                triggers_content.push_str(&format!(r#"CALL apoc.trigger.add('type_{}',"MATCH (m:{}) WHERE m.{} IS NOT NULL AND NOT valueType(m.{}) = '{}' CALL apoc.util.validate(true, 'ERROR : The type of the field {} need to be a {} .', []) RETURN m",{{phase: 'before'}});{}"#
-                    ,function_name,label,column_name,column_name,data_type,column_name,data_type,"\n"));
                 headers.push_str(&format!("{}:{};", column_name, data_type));
+                headers.push_str(&binary_headers);
             }
             Value::Array(vector) => {
                 let key = String::from(
@@ -174,14 +448,69 @@ fn process_columns(
                         )
                     })?,
                 );
+                if let Some((strategy, value_column)) = mapping.lookup_strategy(&key)? {
+                    let property = match strategy {
+                        CategoryStrategy::Nodes => None,
+                        CategoryStrategy::Labels => Some(None),
+                        CategoryStrategy::Property => {
+                            // 'status_id' become 'status', if it isn't already a column.
+                            let property = column_name
+                                .strip_suffix("_id")
+                                .filter(|property| {
+                                    !property.is_empty()
+                                        && !columns.iter().any(|column| column["column_name"].as_str() == Some(property))
+                                })
+                                .unwrap_or(&column_name);
+                            triggers_content.push_str(&lookup_trigger(&function_name, label, property, &key, &value_column));
+                            headers.push_str(&format!("{}:STRING;", property));
+                            Some(Some(String::from(property)))
+                        }
+                    };
+                    if let Some(property) = property {
+                        conversions.push((String::clone(&column_name), ColumnConversion::Category(CategoryColumn::Lookup {
+                            table: String::clone(&key),
+                            key_column: column_ref_name,
+                            value_column,
+                            property,
+                        })));
+                        continue;
+                    }
+                }
                 foreign_keys.push(format!(
                     "{}_ref_{}",
                     label,
                     column_name.to_uppercase()
                 ));
+                // The type of the relationships can be chosen in the mapping, like REPORTS_TO.
+                let relationship_type = match mapping.relationship_type(label, &column_name)? {
+                    Some(relationship_type) => format!(";{}", relationship_type),
+                    None => String::new(),
+                };
+                // The self-referencing foreign keys are hierarchies, their relationships go from the child to its parent.
+                if key == label {
+                    let (_, depth) = mapping.hierarchy(label, &column_name)?;
+                    if depth {
+                        // The properties depth and root can only be written once per node.
+                        let other = conversions.iter().find_map(|(other, conversion)| match conversion {
+                            ColumnConversion::Hierarchy(HierarchyColumn { depth: true, .. }) => Some(other),
+                            _ => None,
+                        });
+                        if let Some(other) = other {
+                            return Err(format!(
+                                "ERROR : The hierarchies {}.{} and {}.{} both have a depth, only one hierarchy of a table can have \"depth\": true.",
+                                label, other, label, column_name
+                            ));
+                        }
+                        headers.push_str("depth:LONG;root:BOOLEAN;");
+                    }
+                    conversions.push((String::clone(&column_name), ColumnConversion::Hierarchy(HierarchyColumn {
+                        key_column: String::clone(&column_ref_name),
+                        depth,
+                    })));
+                }
                 fk_content.push_str(&format!(
-                    "{}_ref_{};{};{}\n",
-                    label, key, column_name, column_ref_name
+                    "{}_ref_{};{};{}{}\n",
+                    label, key, column_name, column_ref_name, relationship_type
                 ));
             }
             _ => {
@@ -195,259 +524,1040 @@ fn process_columns(
     Ok(())
 }
 
-/// This simple function write the ```content``` in the ```file_path```<br>
-/// It use the ```OpenOptions``` struct with the following args :<br>
-/// write = true ; create = true ; truncate = true
-fn write_file(content: String, file_path: &str) -> Result<(), String> {
-    let mut file = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .truncate(true)
-        .open(file_path)
-        .map_err(|error| format!("ERROR : when try to open the follosing file : {}\n {}", file_path, error))?;
-    match file.write_all(&content.as_bytes()) {
-        Ok(_) => Ok(()),
-        Err(error) => Err(format!("ERROR : when try to write in {}\n {}", file_path, error))
+/// Convert the PostgreSQL type into a Neo4j type, the unknown types are stored as ```STRING``` with a warning
+/// and added to the ```unsupported_columns```.
+fn convert_type_or_string(pg_data_type: &str, label: &str, column_name: &str, unsupported_columns: &mut Vec<String>) -> String {
+    Neo4j::convert_postgresql_type(pg_data_type).unwrap_or_else(|error| {
+        warning(format!("{}.{} - {}\nThe values are stored as STRING.", label, column_name, error));
+        unsupported_columns.push(format!("{}.{} ({})", label, column_name, pg_data_type));
+        String::from("STRING")
+    })
+}
+
+/// Translate the CHECK constraints of the table and of the domains of its columns into APOC triggers that
+/// validate the nodes of the ```label```.<br>
+/// The constraints that can't be translated are added to the ```checks_report```.
+fn process_check_constraints(
+    table: &Value,
+    columns: &[Value],
+    label: &str,
+    triggers_content: &mut String,
+    checks_report: &mut Vec<String>
+) -> Result<(), String> {
+    let check_constraints = match &table["check_constraints"] {
+        Value::Array(vector) => vector.as_slice(),
+        Value::Null => &[],
+        _ => {
+            return Err(format!(
+                "Error when try to match the 'check_constraints' field in {}",
+                table
+            ))
+        }
+    };
+    let foreign_keys = columns
+        .iter()
+        .filter(|column| column["foreign_key"].is_array())
+        .filter_map(|column| column["column_name"].as_str())
+        .collect::<Vec<&str>>();
+
+    // The name of the trigger, the name and the definition of the constraint, its SQL expression and its columns.
+    let mut checks: Vec<(String, String, String, String, Vec<String>)> = Vec::new();
+    for check in check_constraints {
+        let constraint_name = check["constraint_name"].as_str().ok_or_else(|| {
+            format!("Error when try to get the 'constraint_name' field in {}", check)
+        })?;
+        let definition = check["definition"].as_str().ok_or_else(|| {
+            format!("Error when try to get the 'definition' field in {}", check)
+        })?;
+        let expression = definition.trim().trim_start_matches("CHECK").trim();
+        let expression = expression.strip_suffix("NOT VALID").unwrap_or(expression).trim();
+        let check_columns = check["columns"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|column| column.as_str())
+            .map(String::from)
+            .collect::<Vec<String>>();
+        checks.push((
+            format!("check_{}_{}", label.to_lowercase(), constraint_name),
+            String::from(constraint_name),
+            String::from(definition),
+            String::from(expression),
+            check_columns,
+        ));
+    }
+    for column in columns {
+        let (Some(column_name), Some(domain_checks)) = (
+            column["column_name"].as_str(),
+            column["domain"]["check_constraints"].as_array(),
+        ) else {
+            continue;
+        };
+        for check in domain_checks {
+            let constraint_name = check["constraint_name"].as_str().ok_or_else(|| {
+                format!("Error when try to get the 'constraint_name' field in {}", check)
+            })?;
+            let definition = check["definition"].as_str().ok_or_else(|| {
+                format!("Error when try to get the 'definition' field in {}", check)
+            })?;
+            let expression = definition.trim().trim_start_matches("CHECK").trim();
+            let expression = expression.strip_suffix("NOT VALID").unwrap_or(expression).trim();
+            checks.push((
+                format!("check_{}_{}_{}", label.to_lowercase(), column_name, constraint_name),
+                format!("{} (domain of {})", constraint_name, column_name),
+                String::from(definition),
+                domain_expression(expression, column_name),
+                vec![String::from(column_name)],
+            ));
+        }
+    }
+
+    for (trigger_name, constraint_name, definition, expression, check_columns) in checks {
+        let referenced_fk = check_columns
+            .iter()
+            .find(|column| foreign_keys.contains(&column.as_str()));
+        if let Some(column) = referenced_fk {
+            checks_report.push(format!(
+                "{} - {} : {}\n  The foreign key '{}' is stored as a relationship in the graph.",
+                label, constraint_name, definition, column
+            ));
+            continue;
+        }
+
+        match translate_expression(&expression, label, "m") {
+            Ok(cypher_expression) => {
+                // The statement of the trigger is a Cypher string between double quotes.
+                let condition = cypher_expression.to_string().replace('\\', "\\\\").replace('"', "\\\"");
+                triggers_content.push_str(&format!(r#"CALL apoc.trigger.add('{}',"MATCH (m:{}) WHERE NOT ({}) CALL apoc.util.validate(true, 'ERROR : The CHECK constraint {} of {} is violated.', []) RETURN m",{{phase: 'before'}});{}"#
+                    ,trigger_name,label,condition,constraint_name,label,"\n"));
+            }
+            Err(error) => {
+                checks_report.push(format!(
+                    "{} - {} : {}\n  {}",
+                    label,
+                    constraint_name,
+                    definition,
+                    error.replace('\n', "\n  ")
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The Neo4j types of the temporal values written by psql.
+const TEMPORAL_TYPES: [&str; 5] = ["LOCALTIME", "TIME", "LOCALDATETIME", "DATETIME", "DURATION"];
+
+/// Rewrite the temporal values written by psql (like ```2024-01-05 10:00:00+02``` or ```1 day 02:00:00```)
+/// into the ISO-8601 format expected by neo4j-admin, for each column with a temporal type.
+fn normalize_temporal_columns(mut df: DataFrame, header_types: &[(&str, &str)]) -> Result<DataFrame, String> {
+    for (column_name, data_type) in header_types {
+        if !TEMPORAL_TYPES.contains(data_type) {
+            continue;
+        }
+        let column = df
+            .column(column_name)
+            .map_err(|e| format!("{}", e))?
+            .cast(&DataType::String)
+            .map_err(|e| format!("{}", e))?;
+        let values = column
+            .str()
+            .map_err(|e| format!("{}", e))?
+            .into_iter()
+            .map(|value| value.and_then(|value| normalize_temporal(value, data_type)))
+            .collect::<StringChunked>()
+            .with_name((*column_name).into());
+        df.with_column(values.into_column()).map_err(|e| format!("{}", e))?;
+    }
+    Ok(df)
+}
+
+/// Convert the temporal value written by psql into ISO-8601, return ```None``` for the values
+/// that Neo4j can't represent (like ```infinity```).
+pub fn normalize_temporal(value: &str, data_type: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value.ends_with("infinity") {
+        return None;
+    }
+    match data_type {
+        "LOCALDATETIME" | "DATETIME" => {
+            let value = value.replacen(' ', "T", 1);
+            Some(complete_offset(&value))
+        }
+        "TIME" => Some(complete_offset(value)),
+        "DURATION" => Some(interval_to_iso(value)),
+        _ => Some(String::from(value)),
+    }
+}
+
+/// psql write the offsets without their minutes (```+02```), ISO-8601 need them (```+02:00```).
+fn complete_offset(value: &str) -> String {
+    let time_start = value.find('T').map(|index| index + 1).unwrap_or(0);
+    match value[time_start..].rfind(['+', '-']) {
+        Some(index) => {
+            let offset = &value[time_start + index + 1..];
+            if offset.len() == 2 && offset.chars().all(|c| c.is_ascii_digit()) {
+                format!("{}:00", value)
+            } else {
+                String::from(value)
+            }
+        }
+        None => String::from(value),
+    }
+}
+
+/// Convert an interval written with the PostgreSQL style (```1 year 2 mons -3 days +04:05:06.5```)
+/// into an ISO-8601 duration (```P1Y2M-3DT4H5M6.5S```).
+fn interval_to_iso(value: &str) -> String {
+    let (mut years, mut months, mut days) = (0_i64, 0_i64, 0_i64);
+    let mut time = String::new();
+    let tokens = value.split_whitespace().collect::<Vec<&str>>();
+    let mut index = 0;
+    while index < tokens.len() {
+        let token = tokens[index];
+        if token.contains(':') {
+            time = interval_time_to_iso(token);
+            index += 1;
+            continue;
+        }
+        let number = token.parse::<i64>().unwrap_or(0);
+        let unit = tokens.get(index + 1).copied().unwrap_or_default();
+        if unit.starts_with("year") {
+            years += number;
+        } else if unit.starts_with("mon") {
+            months += number;
+        } else if unit.starts_with("day") {
+            days += number;
+        }
+        index += 2;
+    }
+
+    let mut result = String::from("P");
+    for (number, unit) in [(years, 'Y'), (months, 'M'), (days, 'D')] {
+        if number != 0 {
+            result.push_str(&format!("{}{}", number, unit));
+        }
+    }
+    if !time.is_empty() {
+        result.push('T');
+        result.push_str(&time);
+    }
+    if result == "P" {
+        result.push_str("T0S");
     }
+    result
+}
+
+/// Convert the time part of an interval (```-04:05:06.5```) into ISO-8601 (```-4H-5M-6.5S```).
+fn interval_time_to_iso(token: &str) -> String {
+    let (sign, token) = match token.strip_prefix('-') {
+        Some(token) => ("-", token),
+        None => ("", token.trim_start_matches('+')),
+    };
+    let parts = token.split(':').collect::<Vec<&str>>();
+    let mut result = String::new();
+    for (part, unit) in parts.iter().zip(['H', 'M', 'S']) {
+        let part = part.trim_start_matches('0');
+        let part = part.strip_suffix(".000000").unwrap_or(part);
+        if !part.is_empty() && part.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            let part = if part.starts_with('.') { format!("0{}", part) } else { String::from(part) };
+            result.push_str(&format!("{}{}{}", sign, part, unit));
+        }
+    }
+    result
+}
+
+/// Rewrite the arrays written by psql (like ```{a,b,"c d"}```) into the format expected by neo4j-admin :
+/// the elements separated by the ```ARRAY_DELIMITER```.
+fn normalize_array_columns(mut df: DataFrame, header_types: &[(&str, &str)], label: &str) -> Result<DataFrame, String> {
+    for (column_name, data_type) in header_types {
+        let element_type = match data_type.strip_suffix("[]") {
+            Some(element_type) => element_type,
+            None => continue,
+        };
+        let column = df
+            .column(column_name)
+            .map_err(|e| format!("{}", e))?
+            .cast(&DataType::String)
+            .map_err(|e| format!("{}", e))?;
+        let mut lossy_values = 0;
+        let mut split_values = 0;
+        let values = column
+            .str()
+            .map_err(|e| format!("{}", e))?
+            .into_iter()
+            .map(|value| {
+                value.and_then(|value| {
+                    let (array, is_lossy, is_split) = normalize_array(value, element_type);
+                    if is_lossy {
+                        lossy_values += 1;
+                    }
+                    if is_split {
+                        split_values += 1;
+                    }
+                    array
+                })
+            })
+            .collect::<StringChunked>()
+            .with_name((*column_name).into());
+        df.with_column(values.into_column()).map_err(|e| format!("{}", e))?;
+        if lossy_values > 0 {
+            warning(format!(
+                "{} arrays of {}.{} contain NULL elements, nested arrays or invalid values that Neo4j can't store as is.",
+                lossy_values, label, column_name
+            ));
+        }
+        if split_values > 0 {
+            warning(format!(
+                "Lossy conversion : {} arrays of {}.{} contain elements with the delimiter '{}', neo4j-admin split them into several elements.",
+                split_values, label, column_name, ARRAY_DELIMITER
+            ));
+        }
+    }
+    Ok(df)
+}
+
+/// Convert the array literal written by psql into the neo4j-admin format.<br>
+/// Return the array, true if it was altered (the NULL elements are removed, the nested arrays are flattened)
+/// and true if some elements contain the ```ARRAY_DELIMITER``` : neo4j-admin split them.
+fn normalize_array(value: &str, element_type: &str) -> (Option<String>, bool, bool) {
+    let value = value.trim();
+    // Remove the dimensions decoration like '[1:2]={1,2}'
+    let value = match value.find("={") {
+        Some(index) if value.starts_with('[') => &value[index + 1..],
+        _ => value,
+    };
+    let (elements, mut is_lossy) = match parse_array_literal(value) {
+        Some(result) => result,
+        None => return (Some(String::from(value)), false, false),
+    };
+    let mut is_split = false;
+    let mut result = Vec::new();
+    for element in elements {
+        match element {
+            None => is_lossy = true,
+            Some(element) => {
+                let element = if TEMPORAL_TYPES.contains(&element_type) {
+                    match normalize_temporal(&element, element_type) {
+                        Some(element) => element,
+                        None => {
+                            is_lossy = true;
+                            continue;
+                        }
+                    }
+                } else if element_type == "BOOLEAN" {
+                    // psql write the booleans 't' and 'f', neo4j-admin only read 'true' and 'false'.
+                    match element.as_str() {
+                        "t" | "true" => String::from("true"),
+                        "f" | "false" => String::from("false"),
+                        _ => {
+                            is_lossy = true;
+                            continue;
+                        }
+                    }
+                } else {
+                    element
+                };
+                if element.contains(ARRAY_DELIMITER) {
+                    is_split = true;
+                }
+                result.push(element);
+            }
+        }
+    }
+    if result.is_empty() {
+        (None, is_lossy, is_split)
+    } else {
+        (Some(result.join(&ARRAY_DELIMITER.to_string())), is_lossy, is_split)
+    }
+}
+
+/// Parse the PostgreSQL array literal, return its elements (```None``` for NULL) and true if the array is nested.
+pub fn parse_array_literal(value: &str) -> Option<(Vec<Option<String>>, bool)> {
+    let mut chars = value.strip_prefix('{')?.chars().peekable();
+    let mut elements = Vec::new();
+    let mut depth = 1;
+    let mut is_nested = false;
+    let mut element = String::new();
+    let mut quoted = false;
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => {
+                quoted = true;
+                while let Some(c) = chars.next() {
+                    match c {
+                        '\\' => element.extend(chars.next()),
+                        '"' => break,
+                        _ => element.push(c),
+                    }
+                }
+            }
+            '{' => {
+                depth += 1;
+                is_nested = true;
+            }
+            ',' | '}' => {
+                if quoted || !element.trim().is_empty() {
+                    let trimmed = element.trim();
+                    if !quoted && trimmed.eq_ignore_ascii_case("NULL") {
+                        elements.push(None);
+                    } else if quoted {
+                        elements.push(Some(std::mem::take(&mut element)));
+                    } else {
+                        elements.push(Some(String::from(trimmed)));
+                    }
+                }
+                element.clear();
+                quoted = false;
+                if c == '}' {
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+            }
+            '\\' => element.extend(chars.next()),
+            _ => element.push(c),
+        }
+    }
+    Some((elements, is_nested))
+}
+
+/// Rewrite the spatial values exported by PostgreSQL (EWKB hex, WKT or ```(x,y)```) into Neo4j points or WKT.
+fn normalize_spatial_columns(
+    mut df: DataFrame,
+    conversions: &[(String, ColumnConversion)],
+    label: &str
+) -> Result<DataFrame, String> {
+    for (column_name, conversion) in conversions {
+        let ColumnConversion::Spatial(spatial) = conversion else {
+            continue;
+        };
+        let column = df
+            .column(column_name)
+            .map_err(|e| format!("{}", e))?
+            .cast(&DataType::String)
+            .map_err(|e| format!("{}", e))?;
+        let mut lossy_values = 0;
+        let values = column
+            .str()
+            .map_err(|e| format!("{}", e))?
+            .into_iter()
+            .map(|value| {
+                value.and_then(|value| {
+                    let (value, is_lossy) = normalize_spatial(value, spatial);
+                    if is_lossy {
+                        lossy_values += 1;
+                    }
+                    value
+                })
+            })
+            .collect::<StringChunked>()
+            .with_name(column_name.as_str().into());
+        df.with_column(values.into_column()).map_err(|e| format!("{}", e))?;
+        if lossy_values > 0 {
+            warning(format!(
+                "{} spatial values of {}.{} can't be stored exactly in Neo4j (not a point, unsupported SRID or geometry stored as a list of points).",
+                lossy_values, label, column_name
+            ));
+        }
+    }
+    Ok(df)
+}
+
+/// Convert the composite values written by psql (like ```(12,"rue de la Paix")```) into JSON objects,
+/// they are flattened or exploded by ```process_json_columns```.
+fn normalize_composite_columns(mut df: DataFrame, conversions: &[(String, ColumnConversion)]) -> Result<DataFrame, String> {
+    for (column_name, conversion) in conversions {
+        let ColumnConversion::Composite(fields) = conversion else {
+            continue;
+        };
+        let column = df
+            .column(column_name)
+            .map_err(|e| format!("{}", e))?
+            .cast(&DataType::String)
+            .map_err(|e| format!("{}", e))?;
+        let values = column
+            .str()
+            .map_err(|e| format!("{}", e))?
+            .into_iter()
+            .map(|value| value.and_then(|value| composite_to_json(value, fields)))
+            .collect::<StringChunked>()
+            .with_name(column_name.as_str().into());
+        df.with_column(values.into_column()).map_err(|e| format!("{}", e))?;
+    }
+    Ok(df)
+}
+
+/// Return the size in memory of the batches read by each worker : the transformations copy a batch several
+/// times, the workers share the memory limit of the mapping.
+fn batch_bytes(mapping: &Mapping, workers: usize) -> Result<usize, String> {
+    Ok(mapping.memory_limit()? / workers.max(1) / 4)
+}
+
+/// Transform the CSV file of a table into the nodes of its label, appended to the file of the headers in the
+/// import folder. The file is read by batches of about ```batch_bytes``` bytes, except the tables whose
+/// conversions need all the rows. Return the label, the number of nodes, the problems found in the hierarchies
+/// and the parts of the files shared with other tables.
+#[allow(clippy::too_many_arguments)]
+fn extract_table_nodes(
+    db_neo4j: &Neo4j,
+    tables_folder: &str,
+    mapping: &Mapping,
+    conversions: &ColumnConversions,
+    parent_labels: &HashMap<String, Vec<String>>,
+    file_name: &str,
+    file_path: &str,
+    batch_bytes: usize,
+    categories_lock: &Mutex<()>,
+) -> Result<(String, usize, Vec<String>, Vec<FilePart>), String> {
+    let mut label = file_name.to_uppercase();
+    label.truncate(label.len() - 4);
+    let headers = fs::read_to_string(format!(
+        "{}{}.csv",
+        db_neo4j.get_import_folder(),
+        label
+    ))
+    .map_err(|error| {
+        format!(
+            "ERROR : when try to read the file : {}{}.csv\n{}",
+            db_neo4j.get_import_folder(),
+            label,
+            error
+        )
+    })?;
+    let header_types = headers
+        .split(";")
+        .filter_map(|c| c.trim().split_once(":"))
+        .collect::<Vec<(&str, &str)>>();
+    let headers = headers
+        .split(";")
+        .map(|c| c.split(":").collect::<Vec<&str>>()[0])
+        .collect::<Vec<&str>>();
+    let headers = headers
+        .iter()
+        .skip(1)
+        .take(headers.len() - 2)
+        .cloned()
+        .collect::<Vec<&str>>();
+
+    // The category columns aren't properties, they need all the columns of the table.
+    let import_folder = db_neo4j.get_import_folder();
+    let categories = conversions
+        .get(&label)
+        .into_iter()
+        .flatten()
+        .filter_map(|(column_name, conversion)| match conversion {
+            ColumnConversion::Category(category) => Some((String::clone(column_name), category.clone())),
+            _ => None,
+        })
+        .collect::<Vec<(String, CategoryColumn)>>();
+    // The binary columns add the columns of the paths and SHA-256 of the external files.
+    let binaries = conversions
+        .get(&label)
+        .into_iter()
+        .flatten()
+        .filter_map(|(column_name, conversion)| match conversion {
+            ColumnConversion::Binary(binary) => Some((String::clone(column_name), *binary)),
+            _ => None,
+        })
+        .collect::<Vec<(String, BinaryColumn)>>();
+    let blob_folder = mapping
+        .option("blob_folder")
+        .and_then(|folder| folder.as_str())
+        .map(|folder| format!("{}/", folder.trim_end_matches('/')))
+        .unwrap_or_else(|| format!("{}Blobs/", tables_folder));
+    let hierarchies = conversions
+        .get(&label)
+        .into_iter()
+        .flatten()
+        .filter_map(|(column_name, conversion)| match conversion {
+            ColumnConversion::Hierarchy(hierarchy) => Some((String::clone(column_name), hierarchy.clone())),
+            _ => None,
+        })
+        .collect::<Vec<(String, HierarchyColumn)>>();
+    let json_header_types = header_types
+        .iter()
+        .filter(|(name, _)| !name.is_empty())
+        .map(|(name, data_type)| (String::from(*name), String::from(data_type.trim())))
+        .collect::<Vec<(String, String)>>();
+    let composite_columns = conversions
+        .get(&label)
+        .into_iter()
+        .flatten()
+        .filter(|(_, conversion)| matches!(conversion, ColumnConversion::Composite(_)))
+        .map(|(column_name, _)| String::clone(column_name))
+        .collect::<Vec<String>>();
+
+    // The hierarchies are checked on all the rows, the flattened JSON columns need all the keys to write the
+    // headers and the large objects are read once.
+    let mut whole_table = !hierarchies.is_empty() || binaries.iter().any(|(_, binary)| binary.large_object);
+    for (column_name, _) in &json_header_types {
+        let strategy = if composite_columns.contains(column_name) {
+            mapping.composite_strategy(&label, column_name)?
+        } else {
+            mapping.json_strategy(&label, column_name)?
+        };
+        whole_table |= strategy != JsonStrategy::String;
+    }
+    let batch_bytes = if whole_table {
+        let file_size = fs::metadata(file_path).map(|metadata| metadata.len()).unwrap_or_default();
+        if file_size > batch_bytes as u64 {
+            warning(format!(
+                "The table {} ({} MB) is transformed at once for its hierarchies, JSON or large objects, it can exceed the memory limit.",
+                label,
+                file_size >> 20
+            ));
+        }
+        usize::MAX
+    } else {
+        batch_bytes
+    };
+
+    let path_destination = format!("{}{}.csv", import_folder, label);
+    let mut hierarchy_report: Vec<String> = Vec::new();
+    // The lookup tables are read once for all the batches.
+    let mut category_state = CategoryState::new(&categories, tables_folder)
+        .map_err(|e| format!("ERROR : when try to read the lookup tables of {}\n{}", file_name, e))?;
+    let height = read_csv_batches(file_path, None, batch_bytes, |mut df, offset| {
+        // The files of the category nodes and of their relationships can be shared by several tables.
+        let mut extra_labels = {
+            let _lock = categories_lock.lock().map_err(|e| format!("{}", e))?;
+            apply_category_columns(&mut df, &categories, &label, offset, import_folder, &mut category_state)
+                .map_err(|e| format!("ERROR : when try to process the categories of {}\n{}", file_name, e))?
+        };
+        // The rows of a child table are also nodes of its parents, PostgreSQL exported them once.
+        if let Some(parents) = parent_labels.get(&label) {
+            for labels in extra_labels.iter_mut() {
+                labels.splice(0..0, parents.iter().cloned());
+            }
+        }
+        apply_binary_columns(&mut df, &binaries, &label, tables_folder, &blob_folder)
+            .map_err(|e| format!("ERROR : when try to process the binary values of {}\n{}", file_name, e))?;
+        apply_hierarchy_columns(&mut df, &hierarchies, &label, &mut hierarchy_report)
+            .map_err(|e| format!("ERROR : when try to check the hierarchies of {}\n{}", file_name, e))?;
+
+        let df = df.select(headers.clone())
+                .map_err(|e| format!("ERROR : when try to filter the Dataframe with the columns '{:#?}' from the file {}\n{:?}",
+                headers,file_name,e))?;
+
+        let df = normalize_temporal_columns(df, &header_types)
+            .map_err(|e| format!("ERROR : when try to normalize the temporal values of {}\n{}", file_name, e))?;
+        let df = normalize_array_columns(df, &header_types, &label)
+            .map_err(|e| format!("ERROR : when try to normalize the arrays of {}\n{}", file_name, e))?;
+        // After the arrays : the geometries stored as POINT[] aren't PostgreSQL arrays.
+        let mut df = match conversions.get(&label) {
+            Some(label_conversions) => {
+                let df = normalize_spatial_columns(df, label_conversions, &label)
+                    .map_err(|e| format!("ERROR : when try to normalize the spatial values of {}\n{}", file_name, e))?;
+                normalize_composite_columns(df, label_conversions)
+                    .map_err(|e| format!("ERROR : when try to normalize the composite values of {}\n{}", file_name, e))?
+            }
+            None => df,
+        };
+
+        // The identifiers continue from a batch to the next, they are the same as if the table was read at once.
+        let index_series = Series::new(
+            "neo4j_id_for_import".into(),
+            (offset..offset + df.height())
+                .map(|x| format!("{}{}", label, x))
+                .collect::<Vec<String>>(),
+        );
+        let df = df.insert_column(0, index_series).map_err(|e| {
+            format!(
+                "ERROR : when try to insert the index column in {}\n{}",
+                file_name, e
+            )
+        })?;
+
+        let json_result = process_json_columns(df, &json_header_types, &label, "neo4j_id_for_import", mapping, &composite_columns, import_folder)
+            .map_err(|e| format!("ERROR : when try to process the JSON columns of {}\n{}", file_name, e))?;
+        // The flattened columns change the headers written by process_meta_data
+        if let Some(new_header_types) = json_result {
+            let mut headers = String::from(":ID;");
+            for (column_name, data_type) in new_header_types {
+                headers.push_str(&format!("{}:{};", column_name, data_type));
+            }
+            headers.push_str(":LABEL\n");
+            write_file(headers, &path_destination)?;
+        }
+
+        let label_series = Series::new(
+            "line_number".into(),
+            extra_labels
+                .iter()
+                .map(|extra_labels| labels_value(&label, extra_labels))
+                .collect::<Vec<String>>(),
+        );
+        let df = df.with_column(label_series).map_err(|e| {
+            format!(
+                "ERROR : when try to insert the label column in {}\n{}",
+                file_name, e
+            )
+        })?;
+
+        let mut file = OpenOptions::new()
+            .create(false)
+            .append(true)
+            .truncate(false)
+            .open(&path_destination)
+            .map_err(|error| format!("{}", error))?;
+
+        if let Err(error) = CsvWriter::new(&mut file)
+            .include_header(false)
+            .with_separator(b';')
+            .finish(df)
+        {
+            return Err(format!(
+                "ERROR : when try to write the Dataframe of {}\n{}",
+                file_name, error
+            ));
+        }
+        Ok(())
+    })?;
+    category_state.warn_missing_values(&label);
+    Ok((label, height, hierarchy_report, category_state.parts))
 }
 
 /// Scan the folder that contains the CSV files that contains the tables imported from the PostgreSQL database<br>
 /// and save them in the CSV files in the the import folder. <br><br>
 /// **WARNING** : This method need to be used after ```&self.extract_csv_headers(...)```
-fn extract_nodes(db_neo4j: &Neo4j, tables_folder: &str) -> Result<String, String> {
+fn extract_nodes(
+    db_neo4j: &Neo4j,
+    tables_folder: &str,
+    mapping: &Mapping,
+    conversions: &ColumnConversions,
+    parent_labels: &HashMap<String, Vec<String>>,
+    report: &mut MigrationReport
+) -> Result<String, String> {
+    let mut hierarchy_report: Vec<String> = Vec::new();
     let path = Path::new(tables_folder);
     match fs::read_dir(path) {
         Ok(entries) => {
-            let entries = entries
+            let mut entries = entries
                 .filter(|e| e.is_ok())
                 .map(|x| x.unwrap())
                 .collect::<Vec<DirEntry>>();
-            for entry in entries {
-                let file_name = entry.file_name().into_string().unwrap_or_default();
-                if file_name.ends_with(".csv") {
-                    let mut label = file_name.to_uppercase();
-                    label.truncate(label.len() - 4);
-                    let headers = fs::read_to_string(format!(
-                        "{}{}.csv",
-                        db_neo4j.get_import_folder(),
-                        label
-                    ))
-                    .map_err(|error| {
-                        format!(
-                            "ERROR : when try to read the file : {}{}.csv\n{}",
-                            db_neo4j.get_import_folder(),
-                            label,
-                            error
-                        )
-                    })?;
-                    let headers = headers
-                        .split(";")
-                        .map(|c| c.split(":").collect::<Vec<&str>>()[0])
-                        .collect::<Vec<&str>>();
-                    let headers = headers
-                        .iter()
-                        .skip(1)
-                        .take(headers.len() - 2)
-                        .cloned()
-                        .collect::<Vec<&str>>();
-
-                    let df = CsvReadOptions::default()
-                        .with_has_header(true)
-                        .try_into_reader_with_file_path(Some(entry.path()))
-                        .map_err(|e| format!("{}", e))?
-                        .finish()
-                        .map_err(|e| format!("{}", e))?;
-
-                    let mut df = df.select(headers.clone())
-                            .map_err(|e| format!("ERROR : when try to filter the Dataframe with the columns '{:#?}' from the file {}\n{:?}",
-                            headers,file_name,e))?;
-
-                    let index_series = Series::new(
-                        "neo4j_id_for_import".into(),
-                        (0..df.height() as u64)
-                            .map(|x| format!("{}{}", label, x))
-                            .collect::<Vec<String>>(),
-                    );
-                    let df = df.insert_column(0, index_series).map_err(|e| {
-                        format!(
-                            "ERROR : when try to insert the index column in {}\n{}",
-                            file_name, e
-                        )
-                    })?;
-
-                    let label_series = Series::new(
-                        "line_number".into(),
-                        (0..df.height())
-                            .map(|_| String::clone(&label))
-                            .collect::<Vec<String>>(),
-                    );
-                    let mut df = df.with_column(label_series).map_err(|e| {
-                        format!(
-                            "ERROR : when try to insert the label column in {}\n{}",
-                            file_name, e
-                        )
-                    })?;
-
-                    let path_destination = format!("{}{}.csv", db_neo4j.get_import_folder(), label);
-
-                    let mut file = OpenOptions::new()
-                        .create(false)
-                        .append(true)
-                        .truncate(false)
-                        .open(&path_destination)
-                        .map_err(|error| format!("{}", error))?;
-
-                    if let Err(error) = CsvWriter::new(&mut file)
-                        .include_header(false)
-                        .with_separator(b';')
-                        .finish(&mut df)
-                    {
-                        return Err(format!(
-                            "ERROR : when try to write the Dataframe of {}\n{}",
-                            file_name, error
-                        ));
-                    }
-                }
+            // The nodes are extracted at the same time, the report keeps the order of the files.
+            entries.sort_by_key(|entry| entry.file_name());
+            let files = entries
+                .iter()
+                .map(|entry| entry.file_name().into_string().unwrap_or_default())
+                .filter(|file_name| file_name.ends_with(".csv"))
+                .map(|file_name| {
+                    let file_path = format!("{}", path.join(&file_name).display());
+                    (String::clone(&file_name), (file_name, file_path))
+                })
+                .collect::<Vec<(String, (String, String))>>();
+            let workers = mapping.workers()?;
+            let batch_bytes = batch_bytes(mapping, workers)?;
+            let categories_lock = Mutex::new(());
+            let results = run_pool("Extract the nodes", &files, workers, |(file_name, file_path)| {
+                extract_table_nodes(db_neo4j, tables_folder, mapping, conversions, parent_labels, file_name, file_path, batch_bytes, &categories_lock)
+            })?;
+            // The relationships of the categories shared by several tables are written in the order of the tables.
+            for (label, height, table_hierarchy_report, parts) in results {
+                report.nodes.push((label, height));
+                hierarchy_report.extend(table_hierarchy_report);
+                merge_parts(&parts)?;
             }
         }
         Err(error) => {
             return Err(format!("{}", error));
         }
     }
+
+    let hierarchy_report_path = format!(
+        "{}/Neo4j/hierarchy_report.txt",
+        env::current_dir()
+            .map_err(|error| format!("{}", error))?
+            .display()
+    );
+    if hierarchy_report.is_empty() {
+        write_file(String::from("The hierarchies don't contain cycles or orphans.\n"), &hierarchy_report_path)?;
+    } else {
+        warning(format!(
+            "{} problems found in the hierarchies, see the report {}",
+            hierarchy_report.len(),
+            hierarchy_report_path
+        ));
+        write_file(format!("{}\n", hierarchy_report.join("\n")), &hierarchy_report_path)?;
+    }
+    report.hierarchies = hierarchy_report;
     Ok(String::from(
         "\nSuccessfully extract the nodes and store them in the CSV files !",
     ))
 }
 
+/// Generate the relationships of the line ```index``` of the foreign keys file, buffered in a part of the file of
+/// the relationships in the import folder. Only the key columns are read : the rows of the referenced column are
+/// kept by value, the foreign key is read by batches of about ```batch_bytes``` bytes and joined to them. The keys
+/// can use half of the memory of the worker, twice ```batch_bytes```, the foreign key fails beyond.<br>
+/// Return the foreign key, the type and the number of its relationships, its integrity and its part.
+fn extract_foreign_key(
+    db_neo4j: &Neo4j,
+    tables_folder: &str,
+    index: usize,
+    line: &str,
+    batch_bytes: usize,
+) -> Result<(String, String, usize, ForeignKeyIntegrity, FilePart), String> {
+    let elements = line.split(";").collect::<Vec<&str>>();
+    let tables = elements[0].split("_ref_").collect::<Vec<&str>>();
+    let table1 = tables[0];
+    let table2 = tables[1];
+    let column1 = elements[1];
+    let column2 = elements[2];
+    let label = format!("{}_ref_{}", table1, column1.to_uppercase());
+    // The type of the relationships can be chosen in the mapping.
+    let relationship_type = String::from(elements.get(3).map(|value| value.trim()).unwrap_or(&label));
+
+    let mut targets = ReferencedKeys::default();
+    read_csv_batches(
+        &format!("{}{}.csv", tables_folder, table2.to_lowercase()),
+        Some(&[column2]),
+        batch_bytes,
+        |df, offset| {
+            targets.extend(string_values(&df, column2)?, offset);
+            if targets.bytes() > batch_bytes.saturating_mul(2) {
+                return Err(format!(
+                    "ERROR : The keys of {}.{} need more than {} MB in memory, raise the \"memory_limit\" or reduce the \"workers\" of the mapping.",
+                    table2,
+                    column2,
+                    batch_bytes.saturating_mul(2) >> 20
+                ));
+            }
+            Ok(())
+        },
+    )?;
+    let mut integrity = ForeignKeyIntegrity {
+        duplicate_targets: targets.duplicate_targets(),
+        ..Default::default()
+    };
+    if integrity.duplicate_targets > 0 {
+        warning(format!(
+            "{} keys of {}.{} exist more than once, the foreign key {} create several relationships for them.",
+            integrity.duplicate_targets, table2, column2, label
+        ));
+    }
+
+    // A column in several foreign keys shares the file of its relationships.
+    let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), label);
+    let part_path = part_path(&file_path, &index.to_string());
+    write_file(String::new(), &part_path)?;
+    let mut count = 0;
+    read_csv_batches(
+        &format!("{}{}.csv", tables_folder, table1.to_lowercase()),
+        Some(&[column1]),
+        batch_bytes,
+        |df, offset| {
+            // The NULL values and the orphans don't have relationships, they are counted before.
+            let values = string_values(&df, column1)?;
+            targets.check(&values, &mut integrity);
+            let mut relationships = (Vec::new(), Vec::new());
+            for (index, value) in values.iter().enumerate() {
+                for row in value.as_deref().map(|value| targets.rows(value)).unwrap_or_default() {
+                    relationships.0.push(format!("{}{}", table1, offset + index));
+                    relationships.1.push(format!("{}{}", table2, row));
+                }
+            }
+            count += relationships.0.len();
+            let df = DataFrame::new(vec![
+                string_column("row_id1", relationships.0.iter().map(|id| id.as_str())),
+                string_column("row_id2", relationships.1.iter().map(|id| id.as_str())),
+                string_column("line_number", relationships.0.iter().map(|_| relationship_type.as_str())),
+            ])
+            .map_err(|e| format!("ERROR : when try to build the relationships of {}\n{}", label, e))?;
+            append_dataframe(df, &part_path)
+        },
+    )?;
+    Ok((label, relationship_type, count, integrity, (file_path, part_path)))
+}
+
 /// Read the JSON file that contains all the couple of foreign keys of the PostgreSQL database <br>
 /// and save them in the CSV files in the the import folder. <br><br>
 /// **WARNING** this method need to be used after ```&self.extract_csv_headers(...)```
-fn extract_relationships(db_neo4j: &Neo4j, tables_folder: &str, foreign_key_path: &str) -> Result<String, String> {
+fn extract_relationships(
+    db_neo4j: &Neo4j,
+    tables_folder: &str,
+    foreign_key_path: &str,
+    mapping: &Mapping,
+    report: &mut MigrationReport
+) -> Result<String, String> {
     let lines = fs::read_to_string(foreign_key_path).map_err(|error| format!("{}", error))?;
     let lines = lines.split("\n").collect::<Vec<&str>>();
+    let mut integrity_report: Vec<String> = Vec::new();
+    let mut orphan_foreign_keys: Vec<String> = Vec::new();
 
-    for line in lines {
-        if line != "" {
-            let elements = line.split(";").collect::<Vec<&str>>();
-            let tables = elements[0].split("_ref_").collect::<Vec<&str>>();
-            let table1 = tables[0];
-            let table2 = tables[1];
-            let column1 = elements[1];
-            let column2 = elements[2];
-            let label = format!("{}_ref_{}", table1, column1.to_uppercase());
-
-            let mut df1 = CsvReadOptions::default()
-                .with_has_header(true)
-                .try_into_reader_with_file_path(Some(
-                    format!("{}{}.csv", tables_folder, table1.to_lowercase()).into(),
-                ))
-                .map_err(|e| format!("{}", e))?
-                .finish()
-                .map_err(|e| format!("{}", e))?;
-
-            let mut df1_id =
-                generate_id_column(&df1, table1, "row_id1").map_err(|e| format!("{}", e))?;
-            df1_id.rename("row_id1".into());
-
-            let df1 = df1.insert_column(0, df1_id).map_err(|e| format!("{}", e))?;
-
-            let mut df2 = CsvReadOptions::default()
-                .with_has_header(true)
-                .try_into_reader_with_file_path(Some(
-                    format!("{}{}.csv", tables_folder, table2.to_lowercase()).into(),
-                ))
-                .map_err(|e| format!("{}", e))?
-                .finish()
-                .map_err(|e| format!("{}", e))?;
-
-            let mut df2_id =
-                generate_id_column(&df2, table2, "row_id2").map_err(|e| format!("{}", e))?;
-            df2_id.rename("row_id2".into());
-
-            let df2 = df2.insert_column(0, df2_id).map_err(|e| format!("{}", e))?;
-
-            let mut df = df1
-                .inner_join(&df2, [column1], [column2])
-                .map_err(|e| format!("{}", e))?
-                .select(["row_id1", "row_id2"])
-                .map_err(|e| format!("{}", e))?;
-
-            let mut df = df
-                .with_column(Series::new(
-                    "line_number".into(),
-                    (0..df.height())
-                        .map(|_| String::clone(&label))
-                        .collect::<Vec<String>>(),
-                ))
-                .map_err(|e| {
-                    format!(
-                        "ERROR : when try to insert the label column in {}\n{}",
-                        label, e
-                    )
-                })?;
+    // The foreign keys are extracted at the same time, the report keeps the order of the file.
+    let foreign_keys = lines
+        .into_iter()
+        .filter(|line| !line.is_empty())
+        .enumerate()
+        .map(|(index, line)| (String::from(line.split(';').next().unwrap_or_default()), (index, line)))
+        .collect::<Vec<(String, (usize, &str))>>();
+    let workers = mapping.workers()?;
+    let batch_bytes = batch_bytes(mapping, workers)?;
+    let results = run_pool("Extract the relationships", &foreign_keys, workers, |(index, line)| {
+        extract_foreign_key(db_neo4j, tables_folder, *index, line, batch_bytes)
+    })?;
+    // The relationships of the foreign keys that share a file are written in the order of the file.
+    for (label, relationship_type, count, integrity, part) in results {
+        merge_parts(&[part])?;
+        if integrity.orphans > 0 {
+            orphan_foreign_keys.push(String::clone(&label));
+        }
+        integrity_report.push(integrity.report_line(&label));
+        report.relationships.push((relationship_type, count));
+    }
 
-            let file_path = format!("{}{}.csv", db_neo4j.get_import_folder(), label);
-            let mut file = OpenOptions::new()
-                .write(true)
-                .create(false)
-                .append(true)
-                .truncate(false)
-                .open(&file_path)
-                .map_err(|error| format!("{}", error))?;
-
-            if let Err(error) = CsvWriter::new(&mut file)
-                .include_header(false)
-                .with_separator(b';')
-                .finish(&mut df)
-            {
-                return Err(format!(
-                    "ERROR : when try to write the Dataframe of {}\n{}",
-                    file_path, error
-                ));
-            }
+    let integrity_report_path = format!(
+        "{}/Neo4j/integrity_report.txt",
+        env::current_dir()
+            .map_err(|error| format!("{}", error))?
+            .display()
+    );
+    write_file(format!("{}\n", integrity_report.join("\n")), &integrity_report_path)?;
+    report.integrity = integrity_report;
+    if !orphan_foreign_keys.is_empty() {
+        let message = format!(
+            "The foreign keys {} have orphan values, see the report {}",
+            orphan_foreign_keys.join(", "),
+            integrity_report_path
+        );
+        if mapping.is_strict() {
+            return Err(format!("ERROR : {}", message));
         }
+        warning(message);
     }
     Ok(String::from(
         "\nSuccessfully extract the edges and store them in the CSV files !",
     ))
 }
 
-/// This function generate the files needed to do the import to Neo4J. These files store the database in CSV files in the import folder of the Neo4j object.
-pub fn generate_import_files(db_neo4j: &Neo4j,meta_data_path: &str,tables_folder: &str,foreign_key_path: &str) -> Result<String, String> {
-    match process_meta_data(db_neo4j, meta_data_path, foreign_key_path) {
-        Ok(res) => {
-            println!("{}", res);
-            match extract_nodes(db_neo4j, tables_folder) {
-                Ok(res) => {
-                    println!("{}", res);
-                    match extract_relationships(db_neo4j, tables_folder, foreign_key_path) {
-                        Ok(res) => {
-                            println!("{}\n\nThe files to do the import are ready. You can stop your neo4j database and use the function 'load_with_admin()'.",res);
-                            Ok(res)
-                        }
-                        Err(error) => Err(error),
-                    }
-                }
-                Err(error) => Err(error),
-            }
-        }
-        Err(error) => Err(error),
+/// This function generate the files needed to do the import to Neo4J. These files store the database in CSV files in the import folder of the Neo4j object.<br>
+/// The ```mapping``` choose how some columns are migrated, like the JSON columns.
+pub fn generate_import_files(
+    db_neo4j: &Neo4j,
+    meta_data_path: &str,
+    tables_folder: &str,
+    foreign_key_path: &str,
+    mapping: &Mapping,
+    report: &mut MigrationReport
+) -> Result<String, String> {
+    let mut conversions = ColumnConversions::new();
+    let mut parent_labels = HashMap::new();
+    let start = Instant::now();
+    let result = process_meta_data(db_neo4j, meta_data_path, foreign_key_path, mapping, &mut conversions, &mut parent_labels, report);
+    report.stage("Translate the meta-data", start);
+    println!("{}", result?);
+
+    let start = Instant::now();
+    let result = extract_nodes(db_neo4j, tables_folder, mapping, &conversions, &parent_labels, report);
+    report.stage("Extract the nodes", start);
+    println!("{}", result?);
+
+    let start = Instant::now();
+    let result = extract_relationships(db_neo4j, tables_folder, foreign_key_path, mapping, report);
+    report.stage("Extract the relationships", start);
+    let res = result?;
+    println!("{}\n\nThe files to do the import are ready. You can stop your neo4j database and use the function 'load_with_admin()'.",res);
+    Ok(res)
+}
+
+#[test]
+fn test_normalize_temporal() {
+    let expected = [
+        ("2024-01-05 10:00:00+02", "DATETIME", "2024-01-05T10:00:00+02:00"),
+        ("2024-01-05 10:00:00.25-05:30", "DATETIME", "2024-01-05T10:00:00.25-05:30"),
+        ("2024-01-05 10:00:00", "LOCALDATETIME", "2024-01-05T10:00:00"),
+        ("10:00:00+02", "TIME", "10:00:00+02:00"),
+        ("10:00:00", "LOCALTIME", "10:00:00"),
+        ("1 day 02:00:00", "DURATION", "P1DT2H"),
+        ("1 year 2 mons -3 days +04:05:06.5", "DURATION", "P1Y2M-3DT4H5M6.5S"),
+        ("-00:30:00", "DURATION", "PT-30M"),
+        ("00:00:00", "DURATION", "PT0S"),
+    ];
+    for (value, data_type, iso) in expected {
+        assert_eq!(normalize_temporal(value, data_type).unwrap(), iso);
     }
+    assert_eq!(normalize_temporal("infinity", "DATETIME"), None);
 }
 
-/// Generate an 'Id column' necessary for the import of the relationships.<br>
-/// It's basically to match the alias names called ID in the format of the csv files<br>
-/// for the import to neo4j.
-fn generate_id_column(df: &DataFrame, label: &str, column_name: &str) -> Result<Column, String> {
-    Ok(df
-        .with_row_index(column_name.into(), None)
-        .map_err(|e| format!("{}", e))?
-        .column(column_name)
-        .map_err(|e| format!("{}", e))?
-        .u32()
-        .map_err(|e| format!("{}", e))?
-        .into_iter()
-        .map(|opt_name: Option<u32>| opt_name.map(|index: u32| format!("{}{}", label, index)))
-        .collect::<StringChunked>()
-        .into_column())
+#[test]
+fn test_normalize_array() {
+    assert_eq!(normalize_array("{1,2,3}", "LONG"), (Some(String::from("1|2|3")), false, false));
+    assert_eq!(
+        normalize_array(r#"{a,"c d","say \"hi\"",NULL,"NULL"}"#, "STRING"),
+        (Some(String::from("a|c d|say \"hi\"|NULL")), true, false)
+    );
+    assert_eq!(normalize_array("{{1,2},{3,4}}", "LONG"), (Some(String::from("1|2|3|4")), true, false));
+    assert_eq!(
+        normalize_array(r#"{"2024-01-05 10:00:00+02"}"#, "DATETIME"),
+        (Some(String::from("2024-01-05T10:00:00+02:00")), false, false)
+    );
+    assert_eq!(normalize_array("[0:1]={t,f}", "BOOLEAN"), (Some(String::from("true|false")), false, false));
+    assert_eq!(normalize_array("{}", "LONG"), (None, false, false));
+    assert_eq!(normalize_array(r#"{"a|b"}"#, "STRING"), (Some(String::from("a|b")), false, true));
+}
+
+#[test]
+fn test_inheritance_labels() {
+    let tables = serde_json::json!([
+        { "table_name": "vehicles", "parent_tables": null },
+        { "table_name": "cars", "parent_tables": ["vehicles"] },
+        { "table_name": "electric_cars", "parent_tables": ["cars", "vehicles"] }
+    ]);
+    let labels = inheritance_labels(tables.as_array().unwrap());
+    assert_eq!(labels.get("VEHICLES"), None);
+    assert_eq!(labels["CARS"], vec!["VEHICLES"]);
+    assert_eq!(labels["ELECTRIC_CARS"], vec!["CARS", "VEHICLES"]);
+}
+
+#[test]
+fn test_hierarchies_depth() {
+    let meta_data = serde_json::json!([{
+        "table_name": "employees",
+        "columns": [
+            { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+            { "column_name": "reports_to", "data_type": "integer", "foreign_key": [{ "referenced_table": "employees", "referenced_column": "id" }] },
+            { "column_name": "mentor", "data_type": "integer", "foreign_key": [{ "referenced_table": "employees", "referenced_column": "id" }] }
+        ]
+    }]);
+    let mapping = |mentor_depth: bool| {
+        Mapping::from_value(serde_json::json!({ "tables": { "employees": { "columns": {
+            "reports_to": { "hierarchy": { "depth": true } },
+            "mentor": { "hierarchy": { "depth": mentor_depth } }
+        } } } }))
+    };
+    let files = translate_meta_data(meta_data.clone(), &mapping(false), &mut ColumnConversions::new()).unwrap();
+    assert_eq!(files.headers[0].1.matches("depth:LONG").count(), 1);
+    let error = translate_meta_data(meta_data, &mapping(true), &mut ColumnConversions::new()).unwrap_err();
+    assert!(error.contains("EMPLOYEES.reports_to and EMPLOYEES.mentor"));
+}
+
+#[test]
+fn test_json_constraints() {
+    let meta_data = serde_json::json!([{
+        "table_name": "orders",
+        "columns": [
+            { "column_name": "id", "data_type": "integer", "primary_key": true, "is_nullable": "NO", "foreign_key": null },
+            { "column_name": "address", "data_type": "jsonb", "is_nullable": "NO", "foreign_key": null },
+            { "column_name": "lines", "data_type": "jsonb", "is_nullable": "NO", "foreign_key": null },
+            { "column_name": "note", "data_type": "jsonb", "is_nullable": "NO", "foreign_key": null }
+        ]
+    }]);
+    let mapping = Mapping::from_value(serde_json::json!({ "tables": { "orders": { "columns": {
+        "address": { "json": "flatten" },
+        "lines": { "json": "nodes" }
+    } } } }));
+    let files = translate_meta_data(meta_data, &mapping, &mut ColumnConversions::new()).unwrap();
+    // The flattened and exploded columns don't have constraints or triggers, the JSON kept as a string has.
+    for column in ["address", "lines"] {
+        assert!(!files.constraints.contains(&format!("orders_{} ", column)));
+        assert!(!files.triggers.contains(&format!("type_orders_{}'", column)));
+    }
+    assert!(files.constraints.contains("nonull_orders_note "));
+    assert!(files.triggers.contains("type_orders_note'"));
+    assert!(files.headers[0].1.contains("address:STRING;lines:STRING;"));
 }
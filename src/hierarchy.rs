@@ -0,0 +1,160 @@
+//! This module check the self-referencing foreign keys (like ```employees.reports_to```), migrated as
+//! hierarchies : the relationships go from the child to its parent, the cycles and the orphan parents are
+//! reported and the ```depth``` of the nodes in the tree can be computed.
+
+use std::collections::HashMap;
+
+use polars::prelude::{BooleanChunked, DataFrame, Int64Chunked, IntoColumn};
+
+use crate::utils::string_values;
+
+/// A self-referencing foreign key, that reference the ```key_column``` of the same table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HierarchyColumn {
+    pub key_column: String,
+    /// Add the properties ```depth``` and ```root``` to the nodes.
+    pub depth: bool,
+}
+
+/// The shape of a hierarchy, the rows are identified by their index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hierarchy {
+    /// The depth of each row, ```None``` for the rows in a cycle or under a cycle.
+    pub depths: Vec<Option<i64>>,
+    /// The rows whose parent doesn't exist.
+    pub orphans: Vec<usize>,
+    /// The rows of each cycle.
+    pub cycles: Vec<Vec<usize>>,
+}
+
+/// Analyze the hierarchy of the rows : the ```parents``` are the values of the foreign key, they reference the
+/// ```keys``` of the rows.<br>
+/// The roots (without parent) and the orphans (with a parent that doesn't exist) have the depth 0.
+pub fn analyze_hierarchy(keys: &[Option<String>], parents: &[Option<String>]) -> Hierarchy {
+    let index_of = keys
+        .iter()
+        .enumerate()
+        .filter_map(|(index, key)| Some((key.as_deref()?, index)))
+        .collect::<HashMap<&str, usize>>();
+    let mut orphans = Vec::new();
+    let parent_index = parents
+        .iter()
+        .enumerate()
+        .map(|(index, parent)| {
+            let parent = parent.as_deref()?;
+            let parent_index = index_of.get(parent).copied();
+            if parent_index.is_none() {
+                orphans.push(index);
+            }
+            parent_index
+        })
+        .collect::<Vec<Option<usize>>>();
+
+    // The depth of the visited rows, the rows of the current path are in 'path'.
+    let mut depths: Vec<Option<Option<i64>>> = vec![None; keys.len()];
+    let mut cycles = Vec::new();
+    for start in 0..keys.len() {
+        let mut path = Vec::new();
+        let mut current = Some(start);
+        let mut depth = loop {
+            let Some(index) = current else {
+                break Some(-1);
+            };
+            if let Some(depth) = depths[index] {
+                break depth;
+            }
+            if let Some(position) = path.iter().position(|row| *row == index) {
+                let cycle = path.split_off(position);
+                for row in &cycle {
+                    depths[*row] = Some(None);
+                }
+                cycles.push(cycle);
+                break None;
+            }
+            path.push(index);
+            current = parent_index[index];
+        };
+        for row in path.into_iter().rev() {
+            depth = depth.map(|depth| depth + 1);
+            depths[row] = Some(depth);
+        }
+    }
+    Hierarchy {
+        depths: depths.into_iter().map(|depth| depth.flatten()).collect(),
+        orphans,
+        cycles,
+    }
+}
+
+/// Check the hierarchies of the Dataframe of the table ```label```, read from the CSV file of the table.<br>
+/// The cycles and the orphans are added to the ```report```, the properties ```depth``` and ```root``` are
+/// added to the Dataframe when they are asked.
+pub fn apply_hierarchy_columns(
+    df: &mut DataFrame,
+    hierarchies: &[(String, HierarchyColumn)],
+    label: &str,
+    report: &mut Vec<String>,
+) -> Result<(), String> {
+    for (column_name, hierarchy_column) in hierarchies {
+        let keys = string_values(df, &hierarchy_column.key_column)?;
+        let parents = string_values(df, column_name)?;
+        let hierarchy = analyze_hierarchy(&keys, &parents);
+
+        let key_of = |index: &usize| keys[*index].clone().unwrap_or_default();
+        if !hierarchy.orphans.is_empty() {
+            report.push(format!(
+                "{}.{} : {} orphans, their parent doesn't exist ({} = {})",
+                label,
+                column_name,
+                hierarchy.orphans.len(),
+                hierarchy_column.key_column,
+                hierarchy.orphans.iter().map(key_of).collect::<Vec<String>>().join(", ")
+            ));
+        }
+        for cycle in &hierarchy.cycles {
+            report.push(format!(
+                "{}.{} : cycle {}",
+                label,
+                column_name,
+                cycle.iter().map(key_of).collect::<Vec<String>>().join(" -> ")
+            ));
+        }
+
+        if hierarchy_column.depth {
+            for property in ["depth", "root"] {
+                if df.get_column_index(property).is_some() {
+                    return Err(format!(
+                        "ERROR : The property {} of the hierarchy {}.{} is already a column of the table.",
+                        property, label, column_name
+                    ));
+                }
+            }
+            let roots = parents
+                .iter()
+                .zip(&hierarchy.depths)
+                .map(|(parent, depth)| Some(parent.is_none() && depth.is_some()))
+                .collect::<BooleanChunked>()
+                .with_name("root".into());
+            let depths = hierarchy
+                .depths
+                .into_iter()
+                .collect::<Int64Chunked>()
+                .with_name("depth".into());
+            df.with_column(depths.into_column()).map_err(|e| format!("{}", e))?;
+            df.with_column(roots.into_column()).map_err(|e| format!("{}", e))?;
+        }
+    }
+    Ok(())
+}
+
+#[test]
+fn test_analyze_hierarchy() {
+    let to_values = |values: &[Option<&str>]| values.iter().map(|value| value.map(String::from)).collect::<Vec<Option<String>>>();
+    let keys = to_values(&[Some("1"), Some("2"), Some("3"), Some("4"), Some("5"), Some("6"), Some("7")]);
+    // 1 <- 2 <- 3, 4 reference a missing parent, 5 <-> 6 is a cycle and 7 is under the cycle.
+    let parents = to_values(&[None, Some("1"), Some("2"), Some("9"), Some("6"), Some("5"), Some("6")]);
+    let hierarchy = analyze_hierarchy(&keys, &parents);
+    assert_eq!(hierarchy.depths, vec![Some(0), Some(1), Some(2), Some(0), None, None, None]);
+    assert_eq!(hierarchy.orphans, vec![3]);
+    assert_eq!(hierarchy.cycles, vec![vec![4, 5]]);
+}
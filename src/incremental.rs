@@ -0,0 +1,322 @@
+//! This module synchronize the graph with the tables of a source that can't use logical replication : each
+//! table has a watermark column (like ```updated_at```) chosen in the mapping, and the highest value already
+//! synchronized is saved between the runs. Each run only exports the rows changed since then and upserts them,
+//! the deleted rows are found by comparing the primary keys of the table and of the nodes.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use serde_json::{json, Value};
+
+use crate::cdc::{apply_changes, warn_unsynchronized, Change, ChangeKind, SyncTable};
+use crate::equivalence::{parse_cypher_plain, parse_psql_records, Cell};
+use crate::mapping::Mapping;
+use crate::neo4j::Neo4j;
+use crate::postgresql::PostgreSQL;
+use crate::utils::{warning, write_file};
+
+/// The number of runs between two comparisons of the primary keys, by default.
+pub const DEFAULT_DIFF_EVERY: u64 = 10;
+
+/// The watermarks saved between the runs.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WatermarkState {
+    /// The highest value of the watermark column already synchronized, by table.
+    pub marks: BTreeMap<String, String>,
+    /// The primary keys of the rows whose watermark is the mark, by table : the rows of the mark are exported
+    /// again by the next run to catch the rows committed late with the same mark, these ones are skipped.
+    pub mark_keys: BTreeMap<String, Vec<Vec<String>>>,
+    /// The number of runs, the primary keys are compared every ```diff_every``` runs.
+    pub runs: u64,
+}
+
+impl WatermarkState {
+    /// Read the state saved by the previous run, an empty state if the file doesn't exist.
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).map_err(|error| format!("ERROR : when try to read the file : {}\n{}", path, error))?;
+        let json: Value =
+            serde_json::from_str(&content).map_err(|error| format!("ERROR : when try to parse the watermarks {}\n{}", path, error))?;
+        let marks = json["marks"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(table, mark)| Some((String::clone(table), String::from(mark.as_str()?))))
+            .collect();
+        let mark_keys = json["mark_keys"]
+            .as_object()
+            .into_iter()
+            .flatten()
+            .filter_map(|(table, keys)| Some((String::clone(table), serde_json::from_value(keys.clone()).ok()?)))
+            .collect();
+        Ok(Self {
+            marks,
+            mark_keys,
+            runs: json["runs"].as_u64().unwrap_or_default(),
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = json!({ "marks": self.marks, "mark_keys": self.mark_keys, "runs": self.runs });
+        write_file(serde_json::to_string_pretty(&json).map_err(|error| format!("{}", error))?, path)
+    }
+}
+
+/// Return the watermark column of each table, chosen with the table option ```"watermark": "updated_at"```.
+pub fn watermark_columns(tables: &HashMap<String, SyncTable>, mapping: &Mapping) -> Result<Vec<(String, String)>, String> {
+    let mut columns = Vec::new();
+    for table_name in tables.keys() {
+        let Some(watermark) = mapping.table_option(table_name, "watermark") else {
+            continue;
+        };
+        let column = watermark.as_str().ok_or_else(|| {
+            format!(
+                "ERROR : The watermark of the table {} need to be the name of a column, found {}",
+                table_name, watermark
+            )
+        })?;
+        columns.push((String::clone(table_name), String::from(column)));
+    }
+    columns.sort();
+    Ok(columns)
+}
+
+/// Return the query that exports the rows changed since the watermark, ordered by the watermark column : the
+/// last row contains the new watermark. The rows of the watermark are exported again, a row committed after the
+/// previous run with the same watermark isn't missed.
+pub fn changed_rows_query(table: &str, column: &str, mark: Option<&str>) -> String {
+    match mark {
+        Some(mark) => format!(
+            "SELECT * FROM {} WHERE \"{}\" >= '{}' ORDER BY \"{}\"",
+            table,
+            column,
+            mark.replace('\'', "''"),
+            column
+        ),
+        None => format!("SELECT * FROM {} WHERE \"{}\" IS NOT NULL ORDER BY \"{}\"", table, column, column),
+    }
+}
+
+/// The rows exported since the watermark : the upserts, the new watermark and the primary keys of its rows.
+pub type ChangedRows = (Vec<Change>, Option<String>, Vec<Vec<String>>);
+
+/// Convert the rows exported by psql into upserts, return them with the new watermark and the primary keys of
+/// its rows. The rows of the previous watermark ```mark``` whose primary key is in ```mark_keys``` were already
+/// synchronized, they are skipped.
+pub fn rows_to_changes(
+    table: &str,
+    column: &str,
+    primary_key: &[String],
+    columns: &[String],
+    records: Vec<Vec<Option<String>>>,
+    mark: Option<&str>,
+    mark_keys: &[Vec<String>],
+) -> Result<ChangedRows, String> {
+    let position_of = |column: &str| {
+        columns
+            .iter()
+            .position(|name| name == column)
+            .ok_or_else(|| format!("ERROR : The column {} isn't a column of the table {}", column, table))
+    };
+    let position = position_of(column)?;
+    let key_positions = primary_key.iter().map(|column| position_of(column)).collect::<Result<Vec<usize>, String>>()?;
+    let key_of = |record: &[Option<String>]| {
+        key_positions
+            .iter()
+            .map(|position| record[*position].clone().unwrap_or_default())
+            .collect::<Vec<String>>()
+    };
+    let new_mark = records.last().and_then(|record| record.get(position).cloned().flatten());
+    let new_mark_keys = records
+        .iter()
+        .filter(|record| record[position].is_some() && record[position] == new_mark)
+        .map(|record| key_of(record))
+        .collect::<Vec<Vec<String>>>();
+    let changes = records
+        .into_iter()
+        .filter(|record| record[position].as_deref() != mark || !mark_keys.contains(&key_of(record)))
+        .map(|record| Change {
+            // The inserts are merged on the primary key, they update the existing nodes.
+            kind: ChangeKind::Insert,
+            table: String::from(table),
+            values: columns.iter().cloned().zip(record).collect(),
+            identity: Vec::new(),
+        })
+        .collect();
+    Ok((changes, new_mark, new_mark_keys))
+}
+
+/// Return the deletes of the nodes whose primary key isn't in the table anymore.
+pub fn deleted_rows(table: &str, primary_key: &[String], rows: &HashSet<Vec<String>>, nodes: &[Vec<String>]) -> Vec<Change> {
+    nodes
+        .iter()
+        .filter(|key| !rows.contains(*key))
+        .map(|key| Change {
+            kind: ChangeKind::Delete,
+            table: String::from(table),
+            values: Vec::new(),
+            identity: primary_key.iter().cloned().zip(key.iter().cloned().map(Some)).collect(),
+        })
+        .collect()
+}
+
+/// Compare the primary keys of the table and of the nodes of its label, return the deletes of the missing rows.<br>
+/// The keys are compared in text, only the integer and the string keys are supported.
+fn diff_primary_keys(db_postgresql: &PostgreSQL, db_neo4j: &Neo4j, table_name: &str, table: &SyncTable) -> Result<Vec<Change>, String> {
+    let comparable = table.primary_key.iter().all(|column| {
+        table
+            .properties
+            .get(column)
+            .is_some_and(|property| matches!(property.neo4j_type.as_str(), "LONG" | "INT" | "STRING"))
+    });
+    if table.primary_key.is_empty() || !comparable {
+        warning(format!(
+            "The primary key of {} can't be compared in text, its deleted rows aren't detected.",
+            table_name
+        ));
+        return Ok(Vec::new());
+    }
+    let sql_columns = table
+        .primary_key
+        .iter()
+        .map(|column| format!("\"{}\"::text", column))
+        .collect::<Vec<String>>();
+    let (_, records) =
+        parse_psql_records(&db_postgresql.execute_query(&format!("SELECT {} FROM {}", sql_columns.join(", "), table_name), true)?)?;
+    let rows = records
+        .into_iter()
+        .map(|record| record.into_iter().map(|value| value.unwrap_or_default()).collect::<Vec<String>>())
+        .collect::<HashSet<Vec<String>>>();
+
+    // The keys of a node are returned in a JSON list, their text isn't altered by the parsing of cypher-shell.
+    let cypher_columns = table
+        .primary_key
+        .iter()
+        .map(|column| format!("toString(n.`{}`)", column))
+        .collect::<Vec<String>>();
+    let result = parse_cypher_plain(&db_neo4j.execute_query(&format!(
+        "MATCH (n:`{}`) RETURN apoc.convert.toJson([{}]) AS key",
+        table.label,
+        cypher_columns.join(", ")
+    ))?)?;
+    let mut nodes = Vec::new();
+    for row in result.rows {
+        let Some(Cell::Text(key)) = row.first() else {
+            continue;
+        };
+        let key: Vec<Option<String>> =
+            serde_json::from_str(key).map_err(|error| format!("ERROR : when try to parse the key of a node : {}\n{}", key, error))?;
+        nodes.push(key.into_iter().map(|value| value.unwrap_or_default()).collect::<Vec<String>>());
+    }
+    Ok(deleted_rows(table_name, &table.primary_key, &rows, &nodes))
+}
+
+/// Synchronize the tables that have a watermark, the deleted rows are detected every ```diff_every``` runs
+/// (never with 0).
+pub fn run_incremental(
+    db_postgresql: &PostgreSQL,
+    db_neo4j: &Neo4j,
+    tables: &HashMap<String, SyncTable>,
+    watermarks: &[(String, String)],
+    state_path: &str,
+    diff_every: u64,
+    script_path: &str,
+) -> Result<String, String> {
+    if watermarks.is_empty() {
+        return Err(String::from(
+            "ERROR : No table has a watermark, add the option \"watermark\": \"<column>\" to the tables of the mapping.",
+        ));
+    }
+    warn_unsynchronized(tables);
+    let mut state = WatermarkState::load(state_path)?;
+    state.runs += 1;
+    let diff = diff_every > 0 && state.runs.is_multiple_of(diff_every);
+    let mut summary = Vec::new();
+    for (table_name, column) in watermarks {
+        let Some(table) = tables.get(table_name) else {
+            continue;
+        };
+        if table.primary_key.is_empty() {
+            warning(format!(
+                "The table {} doesn't have a primary key, its rows can't be upserted.",
+                table_name
+            ));
+            continue;
+        }
+        let mark = state.marks.get(table_name).map(|mark| mark.as_str());
+        let query = changed_rows_query(table_name, column, mark);
+        let (columns, records) = parse_psql_records(&db_postgresql.execute_query(&query, true)?)?;
+        let mark_keys = state.mark_keys.get(table_name).map(|keys| keys.as_slice()).unwrap_or_default();
+        let (mut changes, mark, mark_keys) =
+            rows_to_changes(table_name, column, &table.primary_key, &columns, records, mark, mark_keys)?;
+        let upserts = changes.len();
+        if diff {
+            changes.extend(diff_primary_keys(db_postgresql, db_neo4j, table_name, table)?);
+        }
+        let deletes = changes.len() - upserts;
+        apply_changes(db_neo4j, &changes, tables, script_path)?;
+        // The watermark is saved once the changes are applied, they are exported again after an error.
+        if let Some(mark) = mark {
+            state.marks.insert(String::clone(table_name), mark);
+            state.mark_keys.insert(String::clone(table_name), mark_keys);
+            state.save(state_path)?;
+        }
+        summary.push(format!("{} : {} upserted, {} deleted", table_name, upserts, deletes));
+    }
+    state.save(state_path)?;
+    Ok(format!(
+        "Successfully synchronize {} tables !\n{}",
+        summary.len(),
+        summary.join("\n")
+    ))
+}
+
+#[test]
+fn test_incremental_sync() {
+    assert_eq!(
+        changed_rows_query("orders", "updated_at", Some("2024-01-05 10:00:00+02")),
+        "SELECT * FROM orders WHERE \"updated_at\" >= '2024-01-05 10:00:00+02' ORDER BY \"updated_at\""
+    );
+    assert_eq!(
+        changed_rows_query("orders", "updated_at", None),
+        "SELECT * FROM orders WHERE \"updated_at\" IS NOT NULL ORDER BY \"updated_at\""
+    );
+
+    let primary_key = vec![String::from("id")];
+    let (columns, records) = parse_psql_records("id,updated_at\n1,2024-01-05 10:00:00+02\n2,2024-01-06 08:30:00+02\n").unwrap();
+    let (changes, mark, mark_keys) = rows_to_changes("orders", "updated_at", &primary_key, &columns, records, None, &[]).unwrap();
+    assert_eq!(changes.len(), 2);
+    assert_eq!(changes[1].kind, ChangeKind::Insert);
+    assert_eq!(changes[1].values[0], (String::from("id"), Some(String::from("2"))));
+    assert_eq!(mark.as_deref(), Some("2024-01-06 08:30:00+02"));
+    assert_eq!(mark_keys, vec![vec![String::from("2")]]);
+    assert!(rows_to_changes("orders", "modified", &primary_key, &columns, Vec::new(), None, &[]).is_err());
+
+    // The row 3 committed late with the watermark of the previous run is synchronized, the row 2 is skipped.
+    let (columns, records) =
+        parse_psql_records("id,updated_at\n2,2024-01-06 08:30:00+02\n3,2024-01-06 08:30:00+02\n").unwrap();
+    let (changes, mark, mark_keys) =
+        rows_to_changes("orders", "updated_at", &primary_key, &columns, records, mark.as_deref(), &mark_keys).unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].values[0], (String::from("id"), Some(String::from("3"))));
+    assert_eq!(mark.as_deref(), Some("2024-01-06 08:30:00+02"));
+    assert_eq!(mark_keys, vec![vec![String::from("2")], vec![String::from("3")]]);
+
+    let rows = HashSet::from([vec![String::from("1")], vec![String::from("2")]]);
+    let nodes = vec![vec![String::from("1")], vec![String::from("3")]];
+    let deletes = deleted_rows("orders", &primary_key, &rows, &nodes);
+    assert_eq!(deletes.len(), 1);
+    assert_eq!(deletes[0].kind, ChangeKind::Delete);
+    assert_eq!(deletes[0].identity, vec![(String::from("id"), Some(String::from("3")))]);
+
+    let mut state = WatermarkState::default();
+    state.marks.insert(String::from("orders"), String::from("2024-01-06 08:30:00+02"));
+    state.mark_keys.insert(String::from("orders"), mark_keys);
+    state.runs = 3;
+    let path = std::env::temp_dir().join("neo4j_migrator_watermarks.json");
+    state.save(path.to_str().unwrap()).unwrap();
+    assert_eq!(WatermarkState::load(path.to_str().unwrap()).unwrap(), state);
+}
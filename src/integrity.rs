@@ -0,0 +1,121 @@
+//! This module check the referential integrity of the foreign keys before their relationships are generated :
+//! the NULL values, the orphan values that reference nothing and the duplicate target keys that would create
+//! several relationships for a single foreign key value.
+
+use std::collections::HashMap;
+
+/// The number of orphan values written in the report for each foreign key.
+const ORPHAN_SAMPLES: usize = 5;
+
+/// The referential integrity of a foreign key.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ForeignKeyIntegrity {
+    /// The rows whose foreign key is NULL, they don't have a relationship.
+    pub nulls: usize,
+    /// The rows whose foreign key reference a key that doesn't exist.
+    pub orphans: usize,
+    /// Some of the orphan values.
+    pub orphan_samples: Vec<String>,
+    /// The target keys that exist more than once.
+    pub duplicate_targets: usize,
+}
+
+impl ForeignKeyIntegrity {
+    /// Return the line of the report of the foreign key.
+    pub fn report_line(&self, foreign_key: &str) -> String {
+        let mut line = format!(
+            "{} : {} NULL values, {} orphans, {} duplicate target keys",
+            foreign_key, self.nulls, self.orphans, self.duplicate_targets
+        );
+        if !self.orphan_samples.is_empty() {
+            line.push_str(&format!(" (orphan values : {})", self.orphan_samples.join(", ")));
+        }
+        line
+    }
+}
+
+/// The estimated size in memory of a key of the referenced column besides its bytes : the ```String```, the
+/// ```Vec``` of its rows and the entry of the ```HashMap```.
+const KEY_OVERHEAD: usize = 64;
+
+/// The rows of the referenced column by value, the values of the foreign key are checked against them by
+/// batches.
+#[derive(Debug, Clone, Default)]
+pub struct ReferencedKeys {
+    rows: HashMap<String, Vec<usize>>,
+    /// The estimated size in memory of the keys and their rows.
+    bytes: usize,
+}
+
+impl ReferencedKeys {
+    /// Add the values of the referenced column, ```offset``` is the index of the row of the first value.
+    pub fn extend(&mut self, referenced_values: Vec<Option<String>>, offset: usize) {
+        for (index, value) in referenced_values.into_iter().enumerate() {
+            if let Some(value) = value {
+                let bytes = &mut self.bytes;
+                *bytes += std::mem::size_of::<usize>();
+                self.rows
+                    .entry(value)
+                    .or_insert_with_key(|value| {
+                        *bytes += value.len() + KEY_OVERHEAD;
+                        Vec::new()
+                    })
+                    .push(offset + index);
+            }
+        }
+    }
+
+    /// Return the estimated size in memory of the keys and their rows.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Return the rows of the referenced column that contain the value.
+    pub fn rows(&self, value: &str) -> &[usize] {
+        self.rows.get(value).map(|rows| rows.as_slice()).unwrap_or_default()
+    }
+
+    /// Return the number of target keys that exist more than once.
+    pub fn duplicate_targets(&self) -> usize {
+        self.rows.values().filter(|rows| rows.len() > 1).count()
+    }
+
+    /// Count the NULL and the orphan values of a batch of values of the foreign key in the ```integrity```.
+    pub fn check(&self, values: &[Option<String>], integrity: &mut ForeignKeyIntegrity) {
+        for value in values {
+            match value {
+                None => integrity.nulls += 1,
+                Some(value) if !self.rows.contains_key(value.as_str()) => {
+                    integrity.orphans += 1;
+                    if integrity.orphan_samples.len() < ORPHAN_SAMPLES && !integrity.orphan_samples.contains(value) {
+                        integrity.orphan_samples.push(String::clone(value));
+                    }
+                }
+                Some(_) => {}
+            }
+        }
+    }
+}
+
+#[test]
+fn test_referenced_keys() {
+    let to_values = |values: &[Option<&str>]| values.iter().map(|value| value.map(String::from)).collect::<Vec<Option<String>>>();
+    let values = to_values(&[Some("1"), None, Some("7"), Some("7"), Some("2"), None]);
+    let referenced_values = to_values(&[Some("1"), Some("2"), Some("2"), None]);
+    let mut targets = ReferencedKeys::default();
+    targets.extend(referenced_values, 10);
+    let mut integrity = ForeignKeyIntegrity { duplicate_targets: targets.duplicate_targets(), ..Default::default() };
+    targets.check(&values, &mut integrity);
+    assert_eq!(
+        integrity,
+        ForeignKeyIntegrity { nulls: 2, orphans: 2, orphan_samples: vec![String::from("7")], duplicate_targets: 1 }
+    );
+    assert_eq!(
+        integrity.report_line("ORDERS_ref_CUSTOMER_ID"),
+        "ORDERS_ref_CUSTOMER_ID : 2 NULL values, 2 orphans, 1 duplicate target keys (orphan values : 7)"
+    );
+
+    assert_eq!(ReferencedKeys::default().rows("1"), &[] as &[usize]);
+    assert_eq!(targets.rows("2"), &[11, 12]);
+    assert!(targets.bytes() > ReferencedKeys::default().bytes());
+}
@@ -0,0 +1,448 @@
+//! This module transform the JSON/JSONB columns according to the ```JsonStrategy``` chosen in the mapping :
+//! keep the JSON text, flatten the top-level keys into prefixed properties or explode the nested
+//! objects and arrays into child nodes.
+
+use std::path::Path;
+
+use polars::prelude::{Column, DataFrame, DataType, IntoColumn, StringChunked};
+use serde_json::{Map, Value};
+
+use crate::format_to_neo4j::HEADERS_FK;
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+use crate::mapping::{JsonStrategy, Mapping};
+use crate::utils::*;
+
+/// The child nodes exploded from the JSON values, stored in the file ```<label>.csv``` and connected to
+/// their parent with the relationships stored in the file ```<parent_label>_ref_<relationship_type>.csv```.
+#[derive(Debug)]
+struct ChildNodes {
+    label: String,
+    parent_label: String,
+    relationship_type: String,
+    keys: Vec<String>,
+    nodes: Vec<(String, Map<String, Value>)>,
+    relationships: Vec<(String, String)>,
+}
+
+/// Convert a JSON key into a name usable in the headers of neo4j-admin.
+pub fn sanitize_key(key: &str) -> String {
+    key.chars()
+        .map(|c| if c.is_alphanumeric() || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Return the Neo4j type of a JSON value, ```None``` for ```null```.
+fn value_type(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Bool(_) => Some(String::from("BOOLEAN")),
+        Value::Number(number) if number.is_f64() => Some(String::from("DOUBLE")),
+        Value::Number(_) => Some(String::from("LONG")),
+        Value::String(_) => Some(String::from("STRING")),
+        Value::Array(elements) => {
+            let element_type = infer_type(elements.iter());
+            if elements.iter().any(|element| element.is_array() || element.is_object()) {
+                Some(String::from("STRING"))
+            } else {
+                Some(format!("{}[]", element_type))
+            }
+        }
+        Value::Object(_) => Some(String::from("STRING")),
+    }
+}
+
+/// Infer the Neo4j type of a property from all its JSON values : the integers mixed with
+/// decimals are ```DOUBLE``` and the other mixed types are ```STRING```.
+fn infer_type<'a>(values: impl Iterator<Item = &'a Value>) -> String {
+    let mut result: Option<String> = None;
+    for data_type in values.filter_map(value_type) {
+        result = Some(match result {
+            None => data_type,
+            Some(previous) if previous == data_type => previous,
+            Some(previous) => match (previous.as_str(), data_type.as_str()) {
+                ("LONG", "DOUBLE") | ("DOUBLE", "LONG") => String::from("DOUBLE"),
+                ("LONG[]", "DOUBLE[]") | ("DOUBLE[]", "LONG[]") => String::from("DOUBLE[]"),
+                _ => String::from("STRING"),
+            },
+        });
+    }
+    result.unwrap_or_else(|| String::from("STRING"))
+}
+
+/// Write the JSON value in the format expected by neo4j-admin for the ```data_type```.
+fn render_value(value: &Value, data_type: &str) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::String(text) => Some(String::clone(text)),
+        Value::Array(elements) if data_type.ends_with("[]") => {
+            let elements = elements
+                .iter()
+                .filter_map(|element| render_value(element, "STRING"))
+                .collect::<Vec<String>>();
+            if elements.is_empty() {
+                None
+            } else {
+                Some(elements.join(&ARRAY_DELIMITER.to_string()))
+            }
+        }
+        Value::Array(_) | Value::Object(_) => Some(value.to_string()),
+        _ => Some(value.to_string()),
+    }
+}
+
+/// Parse the JSON text of the column, the values that aren't valid JSON are counted in ```invalid_values```.
+fn parse_column(df: &DataFrame, column_name: &str, invalid_values: &mut usize) -> Result<Vec<Value>, String> {
+    let column = df
+        .column(column_name)
+        .map_err(|e| format!("{}", e))?
+        .cast(&DataType::String)
+        .map_err(|e| format!("{}", e))?;
+    let values = column
+        .str()
+        .map_err(|e| format!("{}", e))?
+        .into_iter()
+        .map(|value| match value {
+            None => Value::Null,
+            Some(text) => serde_json::from_str(text).unwrap_or_else(|_| {
+                *invalid_values += 1;
+                Value::Null
+            }),
+        })
+        .collect::<Vec<Value>>();
+    Ok(values)
+}
+
+/// Replace the JSON column by one column for each top-level key of the objects.<br>
+/// Return the names and the types of the new columns.
+fn flatten_column(
+    df: &mut DataFrame,
+    column_name: &str,
+    label: &str,
+    existing_columns: &[String],
+) -> Result<Vec<(String, String)>, String> {
+    let mut invalid_values = 0;
+    let values = parse_column(df, column_name, &mut invalid_values)?;
+    let mut keys: Vec<String> = Vec::new();
+    for value in &values {
+        match value {
+            Value::Object(object) => {
+                for key in object.keys() {
+                    if !keys.contains(key) {
+                        keys.push(String::clone(key));
+                    }
+                }
+            }
+            Value::Null => {}
+            _ => invalid_values += 1,
+        }
+    }
+    if invalid_values > 0 {
+        warning(format!(
+            "{} values of {}.{} aren't JSON objects and can't be flattened, they are not imported.",
+            invalid_values, label, column_name
+        ));
+    }
+
+    let index = df
+        .get_column_index(column_name)
+        .ok_or_else(|| format!("ERROR : The column {} doesn't exist in {}", column_name, label))?;
+    df.drop_in_place(column_name).map_err(|e| format!("{}", e))?;
+
+    let mut new_columns = Vec::new();
+    for (position, key) in keys.iter().enumerate() {
+        let property = format!("{}_{}", column_name, sanitize_key(key));
+        if existing_columns.contains(&property) || new_columns.iter().any(|(name, _)| *name == property) {
+            return Err(format!(
+                "ERROR : The flattened property {} of {}.{} already exists.",
+                property, label, column_name
+            ));
+        }
+        let key_values = values.iter().map(|value| value.get(key).unwrap_or(&Value::Null));
+        let data_type = infer_type(key_values.clone());
+        let column = key_values
+            .map(|value| render_value(value, &data_type))
+            .collect::<StringChunked>()
+            .with_name(property.as_str().into())
+            .into_column();
+        df.insert_column(index + position, column).map_err(|e| format!("{}", e))?;
+        new_columns.push((property, data_type));
+    }
+    Ok(new_columns)
+}
+
+/// Create the child node of the JSON ```value``` connected to the node ```parent_id```, the nested
+/// objects and arrays of objects become child nodes too.
+fn explode_value(value: &Value, parent_id: &str, parent_label: &str, key: &str, children: &mut Vec<ChildNodes>) {
+    match value {
+        Value::Null => return,
+        Value::Array(elements) => {
+            for element in elements {
+                match element {
+                    Value::Array(_) => {
+                        let mut properties = Map::new();
+                        properties.insert(String::from("value"), element.clone());
+                        add_child(properties, parent_id, parent_label, key, children);
+                    }
+                    _ => explode_value(element, parent_id, parent_label, key, children),
+                }
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let mut properties = Map::new();
+    let mut nested = Vec::new();
+    match value {
+        Value::Object(object) => {
+            for (child_key, child_value) in object {
+                let has_objects = match child_value {
+                    Value::Object(_) => true,
+                    Value::Array(elements) => elements.iter().any(|element| element.is_object()),
+                    _ => false,
+                };
+                if has_objects {
+                    nested.push((child_key, child_value));
+                } else {
+                    properties.insert(sanitize_key(child_key), child_value.clone());
+                }
+            }
+        }
+        _ => {
+            properties.insert(String::from("value"), value.clone());
+        }
+    }
+    let (child_id, child_label) = add_child(properties, parent_id, parent_label, key, children);
+    for (child_key, child_value) in nested {
+        explode_value(child_value, &child_id, &child_label, child_key, children);
+    }
+}
+
+/// Add the child node and its relationship, return the ID and the label of the child.
+fn add_child(
+    properties: Map<String, Value>,
+    parent_id: &str,
+    parent_label: &str,
+    key: &str,
+    children: &mut Vec<ChildNodes>,
+) -> (String, String) {
+    let key = sanitize_key(key).to_uppercase();
+    let label = format!("{}_{}", parent_label, key);
+    let position = match children.iter().position(|child| child.label == label) {
+        Some(position) => position,
+        None => {
+            children.push(ChildNodes {
+                label: String::clone(&label),
+                parent_label: String::from(parent_label),
+                relationship_type: format!("HAS_{}", key),
+                keys: Vec::new(),
+                nodes: Vec::new(),
+                relationships: Vec::new(),
+            });
+            children.len() - 1
+        }
+    };
+    let child = &mut children[position];
+    for property in properties.keys() {
+        if !child.keys.contains(property) {
+            child.keys.push(String::clone(property));
+        }
+    }
+    let child_id = format!("{}{}", label, child.nodes.len());
+    child.nodes.push((String::clone(&child_id), properties));
+    child.relationships.push((String::from(parent_id), String::clone(&child_id)));
+    (child_id, label)
+}
+
+/// Write the CSV files of the child nodes and of their relationships in the import folder.
+fn write_children(children: &[ChildNodes], import_folder: &str) -> Result<(), String> {
+    for child in children {
+        let nodes_path = format!("{}{}.csv", import_folder, child.label);
+        let relationships_path = format!(
+            "{}{}_ref_{}.csv",
+            import_folder, child.parent_label, child.relationship_type
+        );
+        for file_path in [&nodes_path, &relationships_path] {
+            if Path::new(file_path).exists() {
+                return Err(format!(
+                    "ERROR : The file {} of the JSON child nodes already exists, rename the key or change the JSON strategy.",
+                    file_path
+                ));
+            }
+        }
+
+        let mut headers = String::from(":ID;");
+        let mut columns: Vec<Column> = vec![string_column("id", child.nodes.iter().map(|(id, _)| id.as_str()))];
+        for key in &child.keys {
+            let key_values = child
+                .nodes
+                .iter()
+                .map(|(_, properties)| properties.get(key).unwrap_or(&Value::Null));
+            let data_type = infer_type(key_values.clone());
+            headers.push_str(&format!("{}:{};", key, data_type));
+            columns.push(
+                key_values
+                    .map(|value| render_value(value, &data_type))
+                    .collect::<StringChunked>()
+                    .with_name(key.as_str().into())
+                    .into_column(),
+            );
+        }
+        headers.push_str(":LABEL\n");
+        columns.push(string_column("label", child.nodes.iter().map(|_| child.label.as_str())));
+        write_file(headers, &nodes_path)?;
+        append_dataframe(DataFrame::new(columns).map_err(|e| format!("{}", e))?, &nodes_path)?;
+
+        let relationships = DataFrame::new(vec![
+            string_column("start", child.relationships.iter().map(|(start, _)| start.as_str())),
+            string_column("end", child.relationships.iter().map(|(_, end)| end.as_str())),
+            string_column("type", child.relationships.iter().map(|_| child.relationship_type.as_str())),
+        ])
+        .map_err(|e| format!("{}", e))?;
+        write_file(String::from(HEADERS_FK), &relationships_path)?;
+        append_dataframe(relationships, &relationships_path)?;
+        println!(
+            "\nSuccessfully write the {} JSON child nodes in {}",
+            child.nodes.len(),
+            nodes_path
+        );
+    }
+    Ok(())
+}
+
+/// Apply the JSON strategies of the mapping to the columns of the table ```label```, the ```composite_columns```
+/// contain the values of a composite type converted into JSON and use the composite strategies.<br>
+/// The Dataframe need to contain the ID column ```id_column```. Return the names and the types of the
+/// columns after the transformation, or ```None``` if no column was transformed.
+pub fn process_json_columns(
+    df: &mut DataFrame,
+    header_types: &[(String, String)],
+    label: &str,
+    id_column: &str,
+    mapping: &Mapping,
+    composite_columns: &[String],
+    import_folder: &str,
+) -> Result<Option<Vec<(String, String)>>, String> {
+    let existing_columns = header_types
+        .iter()
+        .map(|(name, _)| String::clone(name))
+        .collect::<Vec<String>>();
+    let mut new_header_types = Vec::new();
+    let mut is_transformed = false;
+    let mut children = Vec::new();
+
+    for (column_name, data_type) in header_types {
+        let strategy = if composite_columns.contains(column_name) {
+            mapping.composite_strategy(label, column_name)?
+        } else {
+            mapping.json_strategy(label, column_name)?
+        };
+        match strategy {
+            JsonStrategy::String => new_header_types.push((String::clone(column_name), String::clone(data_type))),
+            JsonStrategy::Flatten => {
+                is_transformed = true;
+                new_header_types.extend(flatten_column(df, column_name, label, &existing_columns)?);
+            }
+            JsonStrategy::Nodes => {
+                is_transformed = true;
+                let mut invalid_values = 0;
+                let values = parse_column(df, column_name, &mut invalid_values)?;
+                if invalid_values > 0 {
+                    warning(format!(
+                        "{} values of {}.{} aren't valid JSON, they are not imported.",
+                        invalid_values, label, column_name
+                    ));
+                }
+                let ids = df
+                    .column(id_column)
+                    .map_err(|e| format!("{}", e))?
+                    .str()
+                    .map_err(|e| format!("{}", e))?
+                    .into_iter()
+                    .map(|id| String::from(id.unwrap_or_default()))
+                    .collect::<Vec<String>>();
+                for (id, value) in ids.iter().zip(&values) {
+                    explode_value(value, id, label, column_name, &mut children);
+                }
+                df.drop_in_place(column_name).map_err(|e| format!("{}", e))?;
+            }
+        }
+    }
+    write_children(&children, import_folder)?;
+    Ok(if is_transformed { Some(new_header_types) } else { None })
+}
+
+#[test]
+fn test_json_strategies() {
+    use polars::prelude::{NamedFrom, Series};
+    use serde_json::json;
+
+    let mapping = Mapping::from_value(json!({
+        "tables": {
+            "customers": {
+                "columns": {
+                    "address": { "json": "flatten" },
+                    "orders": { "json": "nodes" }
+                }
+            }
+        }
+    }));
+    let import_folder = format!("{}/neo4j_migrator_test_json/", std::env::temp_dir().display());
+    let _ = std::fs::remove_dir_all(&import_folder);
+    std::fs::create_dir_all(&import_folder).unwrap();
+
+    let mut df = DataFrame::new(vec![
+        Series::new("neo4j_id_for_import".into(), ["CUSTOMERS0", "CUSTOMERS1"]).into_column(),
+        Series::new(
+            "address".into(),
+            [
+                Some(r#"{"city": "Paris", "zip": 75001, "tags": ["a", "b"]}"#),
+                Some(r#"{"city": "Lyon", "zip": 69001.5}"#),
+            ],
+        )
+        .into_column(),
+        Series::new(
+            "orders".into(),
+            [
+                Some(r#"[{"sku": "A", "lines": [{"qty": 2}]}, {"sku": "B"}]"#),
+                None,
+            ],
+        )
+        .into_column(),
+    ])
+    .unwrap();
+    let header_types = vec![
+        (String::from("address"), String::from("STRING")),
+        (String::from("orders"), String::from("STRING")),
+    ];
+
+    let new_header_types =
+        process_json_columns(&mut df, &header_types, "CUSTOMERS", "neo4j_id_for_import", &mapping, &[], &import_folder)
+            .unwrap()
+            .unwrap();
+    assert_eq!(
+        new_header_types,
+        vec![
+            (String::from("address_city"), String::from("STRING")),
+            (String::from("address_tags"), String::from("STRING[]")),
+            (String::from("address_zip"), String::from("DOUBLE")),
+        ]
+    );
+    assert_eq!(
+        df.get_column_names_str(),
+        vec!["neo4j_id_for_import", "address_city", "address_tags", "address_zip"]
+    );
+
+    let read = |name: &str| std::fs::read_to_string(format!("{}{}.csv", import_folder, name)).unwrap();
+    assert_eq!(read("CUSTOMERS_ORDERS"), ":ID;sku:STRING;:LABEL\nCUSTOMERS_ORDERS0;A;CUSTOMERS_ORDERS\nCUSTOMERS_ORDERS1;B;CUSTOMERS_ORDERS\n");
+    assert_eq!(
+        read("CUSTOMERS_ref_HAS_ORDERS"),
+        ":START_ID;:END_ID;:TYPE\nCUSTOMERS0;CUSTOMERS_ORDERS0;HAS_ORDERS\nCUSTOMERS0;CUSTOMERS_ORDERS1;HAS_ORDERS\n"
+    );
+    assert_eq!(read("CUSTOMERS_ORDERS_LINES"), ":ID;qty:LONG;:LABEL\nCUSTOMERS_ORDERS_LINES0;2;CUSTOMERS_ORDERS_LINES\n");
+    assert_eq!(
+        read("CUSTOMERS_ORDERS_ref_HAS_LINES"),
+        ":START_ID;:END_ID;:TYPE\nCUSTOMERS_ORDERS0;CUSTOMERS_ORDERS_LINES0;HAS_LINES\n"
+    );
+    std::fs::remove_dir_all(&import_folder).unwrap();
+}
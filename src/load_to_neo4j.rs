@@ -8,6 +8,11 @@ use std::process::Command;
 
 use crate::neo4j::Neo4j;
 
+/// The delimiter of the elements of the arrays in the CSV files imported with neo4j-admin.<br>
+/// It isn't the default ```,``` of neo4j-admin : the values of the ```POINT[]``` (```{crs:cartesian,x:0,y:0}```)
+/// contain commas, so the arrays of all the types use ```|```.
+pub const ARRAY_DELIMITER: char = '|';
+
 /// This method perform the 'neo4j-admin import' from the ```&self.import_folder```<br><br>
 /// **WARNING** : This method construct the command 'neo4j-admin import' by detecting <br>
 /// the **CSV** files in the folder, you need to assert that there isn't other CSV files <br>
@@ -54,10 +59,10 @@ pub fn load_with_admin(db_neo4j: &Neo4j) -> Result<String, String> {
                 command.arg(format!("--relationships={}", relationship));
             }
             command.args([
-                "--delimiter=;",
-                "--array-delimiter=,",
-                "--overwrite-destination",
-                "--verbose",
+                String::from("--delimiter=;"),
+                format!("--array-delimiter={}", ARRAY_DELIMITER),
+                String::from("--overwrite-destination"),
+                String::from("--verbose"),
             ]);
 
             let output = command.output();
@@ -114,11 +119,11 @@ pub fn recovery_database(db_neo4j: &Neo4j) -> Result<String, String> {
     let _ = file.write_all(":ID;:LABEL".as_bytes());
 
     command.args([
-        "--nodes=RECOVERY.csv",
-        "--delimiter=;",
-        "--array-delimiter=,",
-        "--overwrite-destination",
-        "--verbose",
+        String::from("--nodes=RECOVERY.csv"),
+        String::from("--delimiter=;"),
+        format!("--array-delimiter={}", ARRAY_DELIMITER),
+        String::from("--overwrite-destination"),
+        String::from("--verbose"),
     ]);
 
     let output = command.output();
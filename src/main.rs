@@ -1,51 +1,564 @@
+mod binary;
+mod categories;
+mod cdc;
+mod cypher;
+mod custom_types;
+mod diagram;
+mod equivalence;
 mod format_to_neo4j;
+mod hierarchy;
+mod incremental;
+mod integrity;
+mod json_columns;
 mod load_to_neo4j;
+mod mapping;
 mod neo4j;
+mod plan;
 mod postgresql;
+mod report;
+mod reverse;
+mod spatial;
 mod translate;
+mod translate_cli;
 mod utils;
+mod verify;
+mod views;
+mod workers;
+
+use neo4j::Neo4j;
+use postgresql::PostgreSQL;
+
+const USAGE: &str = "Usage :
+  neo4j-migrator                                  Migrate the PostgreSQL database to Neo4j
+  neo4j-migrator translate [--execute]            Interactive SQL to Cypher translation
+  neo4j-migrator translate <file.sql> [file.cql]  Translate the SQL queries of the file
+  neo4j-migrator equivalence [corpus] [--record <folder> | --fixtures <folder>] [--tolerance <number>]
+                                                  Compare the results of the SQL queries and their Cypher translation
+  neo4j-migrator views [--procedures]             Translate the PostgreSQL views into Cypher queries or APOC procedures
+  neo4j-migrator diagram [mermaid|dot|arrows] [file]
+                                                  Draw the graph model from the exported meta-data
+  neo4j-migrator diagram --import <arrows.json>   Import the relationship types edited in arrows.app into the mapping
+  neo4j-migrator plan [--json <file>] [--meta-data <file>]
+                                                  Preview the migration from the meta-data of PostgreSQL, without any change
+  neo4j-migrator verify [--sample <number>]       Verify the counts, the sampled rows and the checksums after the import
+  neo4j-migrator reverse [--from-graph | --mapping <file>] [--load]
+                                                  Migrate the Neo4j graph back into PostgreSQL
+  neo4j-migrator cdc --replay <file>              Apply the changes recorded from wal2json or pgoutput on the graph
+  neo4j-migrator cdc --slot <name> [--publication <name>] [--create-slot] [--from-snapshot] [--interval <seconds>] [--once]
+                                                  Keep the graph in sync with a logical replication slot
+  neo4j-migrator incremental [--diff | --diff-every <runs>]
+                                                  Upsert the rows changed since the watermarks, detect the deleted rows";
 
 fn main() {
-    match demo() {
-        Ok(_) => println!("\n\nSuccessfully migrate the database to Neo4j !"),
-        Err(error) => println!("{}", error),
+    let args = std::env::args().skip(1).collect::<Vec<String>>();
+    match args.first().map(|arg| arg.as_str()) {
+        None => match demo() {
+            Ok(_) => println!("\n\nSuccessfully migrate the database to Neo4j !"),
+            Err(error) => println!("{}", error),
+        },
+        Some("translate") => match translate(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("equivalence") => match equivalence(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("views") => match export_views(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("diagram") => match diagram(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("plan") => match plan(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("verify") => match verify(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("reverse") => match reverse(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("cdc") => match cdc(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some("incremental") => match incremental(&args[1..]) {
+            Ok(result) => println!("{}", result),
+            Err(error) => println!("{}", error),
+        },
+        Some(_) => println!("{}", USAGE),
     }
 }
 
-fn demo() -> Result<(), String> {
-    use format_to_neo4j::*;
-    use load_to_neo4j::*;
-    use neo4j::Neo4j;
-    use postgresql::PostgreSQL;
-    use std::env;
+/// Read the connections informations of the databases stored in the file ***env.txt***
+fn connections() -> Result<(PostgreSQL, Neo4j), String> {
     use std::fs;
-    use std::io;
-
-    let current_dir = format!("{}", env::current_dir().unwrap().display());
 
     // Your personnal informations about the connections of the databases
     let infos = fs::read_to_string("env.txt").map_err(|error| format!("{}", error))?;
     let infos = infos.split("\n").collect::<Vec<&str>>();
+    if infos.len() < 9 {
+        return Err(String::from("ERROR : The file 'env.txt' need to contains 9 lines."));
+    }
 
     let db_postgresql = PostgreSQL::new(infos[0], infos[1], infos[2], infos[3], infos[4]);
+    let db_neo4j = Neo4j::new(infos[5], infos[6], infos[7], infos[8], "");
+    Ok((db_postgresql, db_neo4j))
+}
+
+fn translate(args: &[String]) -> Result<String, String> {
+    // The relationship types of the foreign keys can be renamed in the mapping.
+    let current_dir = format!("{}", std::env::current_dir().unwrap().display());
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    match args {
+        [] => translate_cli::interactive(None, &mapping),
+        [flag] if flag == "--execute" => {
+            let (db_postgresql, db_neo4j) = connections()?;
+            translate_cli::interactive(Some((&db_postgresql, &db_neo4j)), &mapping)
+        }
+        [sql_path] => {
+            let cypher_path = match sql_path.strip_suffix(".sql") {
+                Some(stem) => format!("{}.cql", stem),
+                None => format!("{}.cql", sql_path),
+            };
+            translate_cli::translate_file(sql_path, &cypher_path, &mapping)
+        }
+        [sql_path, cypher_path] => translate_cli::translate_file(sql_path, cypher_path, &mapping),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+fn equivalence(args: &[String]) -> Result<String, String> {
+    use equivalence::*;
+    use std::env;
+
+    let mut corpus_path = String::from("example_queries.md");
+    let mut record_folder: Option<String> = None;
+    let mut fixtures_folder: Option<String> = None;
+    let mut tolerance = 1e-6;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--record" => record_folder = args.next().cloned(),
+            "--fixtures" => fixtures_folder = args.next().cloned(),
+            "--tolerance" => {
+                tolerance = args
+                    .next()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .ok_or_else(|| String::from("ERROR : '--tolerance' expects a number."))?
+            }
+            _ => corpus_path = String::clone(arg),
+        }
+    }
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    let corpus = read_corpus(&corpus_path)?;
+    let reports = match fixtures_folder {
+        Some(folder) => run_equivalence(&corpus, &ResultSource::Fixtures(folder), tolerance, &mapping),
+        None => {
+            let (db_postgresql, db_neo4j) = connections()?;
+            if let Some(folder) = &record_folder {
+                std::fs::create_dir_all(folder).map_err(|error| format!("{}", error))?;
+            }
+            let source = ResultSource::Live {
+                db_postgresql: &db_postgresql,
+                db_neo4j: &db_neo4j,
+                record_folder,
+            };
+            run_equivalence(&corpus, &source, tolerance, &mapping)
+        }
+    };
+
+    let (text, json_report) = format_report(&reports);
+    let save_report = format!("{}/Data/equivalence_report", current_dir);
+    utils::write_file(String::clone(&text), &format!("{}.txt", save_report))?;
+    utils::write_file(
+        serde_json::to_string_pretty(&json_report).map_err(|error| format!("{}", error))?,
+        &format!("{}.json", save_report),
+    )?;
+    Ok(format!("{}
+The report is saved in {}.txt and {}.json", text, save_report, save_report))
+}
+
+/// Export the definitions of the PostgreSQL views and translate them into ***Neo4j/views.cql***
+fn export_views(args: &[String]) -> Result<String, String> {
+    use std::env;
+    use views::*;
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let (db_postgresql, db_neo4j) = connections()?;
+
+    let script_meta_data = format!("{}/PostgreSQL/meta_data.sql", current_dir);
+    let save_views = format!("{}/Data/postgresql_views.json", current_dir);
+    let save_cypher = format!("{}/Neo4j/views.cql", current_dir);
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    db_postgresql.export_from_sql(&script_meta_data, "export_views_metadata", &save_views)?;
 
-    let mut db_neo4j = Neo4j::new(infos[5], infos[6], infos[7], infos[8], "");
+    match args {
+        [] => generate_views_file(&save_views, &save_cypher, ViewsMode::Catalog, &mapping),
+        [flag] if flag == "--procedures" => declare_views(&db_neo4j, &save_views, &save_cypher, &mapping),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+/// Draw the graph model from the meta-data of ***Data/postgresql_meta_data.json*** and the mapping, or import
+/// the relationship types of a diagram edited in arrows.app into ***mapping.json***.
+fn diagram(args: &[String]) -> Result<String, String> {
+    use diagram::*;
+    use std::env;
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let mapping_path = format!("{}/mapping.json", current_dir);
+    let mut mapping = mapping::Mapping::load(&mapping_path)?;
+    let diagram = build_diagram(&format!("{}/Data/postgresql_meta_data.json", current_dir), &mapping)?;
+
+    if let [flag, arrows_path] = args {
+        if flag == "--import" {
+            let content = std::fs::read_to_string(arrows_path)
+                .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", arrows_path, error))?;
+            let arrows: serde_json::Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+            let (changes, ignored) = import_arrows(&arrows, &diagram, &mut mapping)?;
+            for edit in &ignored {
+                println!("WARNING : The edit can't be imported into the mapping, it's ignored : {}", edit);
+            }
+            if changes.is_empty() {
+                return Ok(String::from("The mapping is already up to date."));
+            }
+            mapping.save(&mapping_path)?;
+            return Ok(format!("{}\nThe mapping is saved in {}", changes.join("\n"), mapping_path));
+        }
+    }
+
+    let (format, output_path) = match args {
+        [] => (DiagramFormat::Mermaid, None),
+        [format] => (diagram_format(format)?, None),
+        [format, path] => (diagram_format(format)?, Some(path)),
+        _ => return Err(String::from(USAGE)),
+    };
+    let content = export_diagram(&diagram, format)?;
+    match output_path {
+        Some(path) => {
+            utils::write_file(content, path)?;
+            Ok(format!("The diagram is saved in {}", path))
+        }
+        None => Ok(content),
+    }
+}
+
+fn diagram_format(format: &str) -> Result<diagram::DiagramFormat, String> {
+    match format {
+        "mermaid" => Ok(diagram::DiagramFormat::Mermaid),
+        "dot" => Ok(diagram::DiagramFormat::Dot),
+        "arrows" => Ok(diagram::DiagramFormat::Arrows),
+        _ => Err(String::from(USAGE)),
+    }
+}
+
+/// Preview the migration from the meta-data of PostgreSQL, or of a file exported before with
+/// ```--meta-data <file>```. The files and the databases aren't modified, the plan can be saved in JSON with
+/// ```--json <file>```.
+fn plan(args: &[String]) -> Result<String, String> {
+    use std::env;
+
+    let mut json_path = None;
+    let mut meta_data_path = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => json_path = Some(args.next().ok_or_else(|| String::from(USAGE))?),
+            "--meta-data" => meta_data_path = Some(String::clone(args.next().ok_or_else(|| String::from(USAGE))?)),
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    let (text, json_plan) = match meta_data_path {
+        Some(path) => plan::generate_plan(&path, &mapping)?,
+        // By default the meta-data are read from PostgreSQL, in a transaction rolled back.
+        None => {
+            let (db_postgresql, _) = connections()?;
+            let path = format!("{}", env::temp_dir().join(format!("neo4j_migrator_plan_{}.json", std::process::id())).display());
+            db_postgresql.read_from_sql(
+                &format!("{}/PostgreSQL/meta_data.sql", current_dir),
+                "export_tables_metadata",
+                &path,
+            )?;
+            let plan = plan::generate_plan(&path, &mapping);
+            let _ = std::fs::remove_file(&path);
+            plan?
+        }
+    };
+    match json_path {
+        Some(path) => {
+            utils::write_file(
+                serde_json::to_string_pretty(&json_plan).map_err(|error| format!("{}", error))?,
+                path,
+            )?;
+            Ok(format!("{}\nThe plan is saved in {}", text, path))
+        }
+        None => Ok(text),
+    }
+}
+
+/// Verify the migration : compare the counts, the sampled rows and the checksums of both databases.
+fn verify(args: &[String]) -> Result<String, String> {
+    use std::env;
+    use verify::*;
+
+    let mut sample_size = 20;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--sample" => {
+                sample_size = args
+                    .next()
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .ok_or_else(|| String::from("ERROR : '--sample' expects a number of rows."))?
+            }
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let (db_postgresql, db_neo4j) = connections()?;
+    let verifications = read_verifications(
+        &format!("{}/Data/postgresql_meta_data.json", current_dir),
+        &format!("{}/Neo4j/FK.csv", current_dir),
+    )?;
+    let checks = run_verify(&db_postgresql, &db_neo4j, &verifications, sample_size);
+
+    let (text, json_report) = format_verify_report(&checks);
+    let save_report = format!("{}/Data/verify_report", current_dir);
+    utils::write_file(String::clone(&text), &format!("{}.txt", save_report))?;
+    utils::write_file(
+        serde_json::to_string_pretty(&json_report).map_err(|error| format!("{}", error))?,
+        &format!("{}.json", save_report),
+    )?;
+    Ok(format!("{}
+The report is saved in {}.txt and {}.json", text, save_report, save_report))
+}
+
+/// Migrate the Neo4j graph back into PostgreSQL. The schema is read from the meta-data of the migration, from the
+/// graph with ```--from-graph``` or from an edited reverse mapping with ```--mapping```.
+fn reverse(args: &[String]) -> Result<String, String> {
+    use reverse::*;
+    use std::env;
+    use std::fs;
+    use std::path::Path;
+
+    let mut from_graph = false;
+    let mut load = false;
+    let mut mapping_path: Option<String> = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--from-graph" => from_graph = true,
+            "--load" => load = true,
+            "--mapping" => {
+                mapping_path = Some(
+                    args.next()
+                        .cloned()
+                        .ok_or_else(|| String::from("ERROR : '--mapping' expects the path of a reverse mapping."))?,
+                )
+            }
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let (db_postgresql, mut db_neo4j) = connections()?;
+    println!("{}", db_neo4j.configure_db_on_linux()?);
+
+    let meta_data_path = format!("{}/Data/postgresql_meta_data.json", current_dir);
+    let read_json = |path: &str| -> Result<serde_json::Value, String> {
+        let content = fs::read_to_string(path).map_err(|error| format!("ERROR : when try to read the file : {}\n{}", path, error))?;
+        serde_json::from_str(&content).map_err(|error| format!("ERROR : when try to parse the file : {}\n{}", path, error))
+    };
+    let schema = match mapping_path {
+        Some(path) => ReverseSchema::from_json(&read_json(&path)?)?,
+        None if !from_graph && Path::new(&meta_data_path).exists() => {
+            let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+            schema_from_meta_data(&read_json(&meta_data_path)?, &mapping)?
+        }
+        None => introspect_graph(&db_neo4j)?,
+    };
+
+    let save_mapping = format!("{}/Data/reverse_mapping.json", current_dir);
+    utils::write_file(
+        serde_json::to_string_pretty(&schema.to_json()).map_err(|error| format!("{}", error))?,
+        &save_mapping,
+    )?;
+    println!("{}", export_graph(&db_neo4j, &schema)?);
+    let save_script = format!("{}/Data/reverse_migration.sql", current_dir);
+    utils::write_file(reverse_script(&schema, db_neo4j.get_import_folder()), &save_script)?;
+    if load {
+        db_postgresql.execute_script(&save_script)?;
+        return Ok(format!("Successfully load {} tables into PostgreSQL !\nThe reverse mapping is saved in {}", schema.tables.len(), save_mapping));
+    }
+    Ok(format!(
+        "The reverse mapping is saved in {} and the script in {}.\nUse '--load' to run it on PostgreSQL.",
+        save_mapping, save_script
+    ))
+}
+
+/// Apply the changes of a logical replication slot on the graph, or replay the changes recorded in a file.<br>
+/// The slot use wal2json, or pgoutput when a publication is given.
+fn cdc(args: &[String]) -> Result<String, String> {
+    use cdc::*;
+    use std::env;
+    use std::time::Duration;
+
+    let mut replay: Option<String> = None;
+    let mut slot: Option<String> = None;
+    let mut publication: Option<String> = None;
+    let mut create_slot = false;
+    let mut from_snapshot = false;
+    let mut interval = 5;
+    let mut once = false;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let mut value = || {
+            args.next()
+                .cloned()
+                .ok_or_else(|| format!("ERROR : '{}' expects a value.", arg))
+        };
+        match arg.as_str() {
+            "--replay" => replay = Some(value()?),
+            "--slot" => slot = Some(value()?),
+            "--publication" => publication = Some(value()?),
+            "--interval" => {
+                interval = value()?
+                    .parse::<u64>()
+                    .map_err(|_| String::from("ERROR : '--interval' expects a number of seconds."))?
+            }
+            "--create-slot" => create_slot = true,
+            "--from-snapshot" => from_snapshot = true,
+            "--once" => once = true,
+            _ => return Err(String::from(USAGE)),
+        }
+    }
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let (db_postgresql, db_neo4j) = connections()?;
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    let tables = sync_tables(&format!("{}/Data/postgresql_meta_data.json", current_dir), &mapping)?;
+    let source = match (replay, slot) {
+        (Some(path), None) => ChangeSource::Replay(path),
+        (None, Some(name)) => {
+            if create_slot {
+                let plugin = if publication.is_some() { "pgoutput" } else { "wal2json" };
+                db_postgresql.execute_query(
+                    &format!("SELECT pg_create_logical_replication_slot('{}', '{}')", name.replace('\'', "''"), plugin),
+                    true,
+                )?;
+                println!("Successfully create the replication slot {} with {} !", name, plugin);
+            }
+            // The snapshot is saved by the export of the tables.
+            let start_lsn = if from_snapshot {
+                let path = format!("{}/Data/postgresql_snapshot.json", current_dir);
+                let content = std::fs::read_to_string(&path)
+                    .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", path, error))?;
+                let json = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+                Some(postgresql::Snapshot::from_json(&json)?.lsn)
+            } else {
+                None
+            };
+            ChangeSource::Slot { name, publication, start_lsn, interval: Duration::from_secs(interval), once }
+        }
+        _ => return Err(String::from(USAGE)),
+    };
+    run_sync(&db_postgresql, &db_neo4j, &source, &tables, &format!("{}/Neo4j/cdc_changes.cql", current_dir))
+}
+
+/// Upsert the rows of the tables changed since their watermark, the deleted rows are detected every 10 runs by
+/// default (```--diff``` compares the primary keys at this run).
+fn incremental(args: &[String]) -> Result<String, String> {
+    use incremental::*;
+    use std::env;
+
+    let diff_every = match args {
+        [] => DEFAULT_DIFF_EVERY,
+        [flag] if flag == "--diff" => 1,
+        [flag, runs] if flag == "--diff-every" => runs
+            .parse::<u64>()
+            .map_err(|_| String::from("ERROR : '--diff-every' expects a number of runs."))?,
+        _ => return Err(String::from(USAGE)),
+    };
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+    let (db_postgresql, db_neo4j) = connections()?;
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    let tables = cdc::sync_tables(&format!("{}/Data/postgresql_meta_data.json", current_dir), &mapping)?;
+    let watermarks = watermark_columns(&tables, &mapping)?;
+    run_incremental(
+        &db_postgresql,
+        &db_neo4j,
+        &tables,
+        &watermarks,
+        &format!("{}/Data/watermarks.json", current_dir),
+        diff_every,
+        &format!("{}/Neo4j/incremental_changes.cql", current_dir),
+    )
+}
+
+fn demo() -> Result<(), String> {
+    use format_to_neo4j::*;
+    use load_to_neo4j::*;
+    use std::env;
+    use std::io;
+    use std::time::Instant;
+
+    let current_dir = format!("{}", env::current_dir().unwrap().display());
+
+    let (db_postgresql, mut db_neo4j) = connections()?;
 
     // PostgreSQL part
 
     let script_meta_data = format!("{}/PostgreSQL/meta_data.sql", current_dir);
     let function_meta_data = "export_tables_metadata";
     let save_meta_data = format!("{}/Data/postgresql_meta_data.json", current_dir);
+    let save_snapshot = format!("{}/Data/postgresql_snapshot.json", current_dir);
 
     let tables_folder = format!("{}/Data/", current_dir);
     let save_fk = format!("{}/Neo4j/FK.csv", current_dir);
 
+    let save_views = format!("{}/Data/postgresql_views.json", current_dir);
+    let save_views_cypher = format!("{}/Neo4j/views.cql", current_dir);
+
+    let mapping = mapping::Mapping::load(&format!("{}/mapping.json", current_dir))?;
+    let save_report = format!("{}/Data/migration_report", current_dir);
+    let mut report = report::MigrationReport::default();
+
+    let start = Instant::now();
     match db_postgresql.export_from_sql(&script_meta_data, function_meta_data, &save_meta_data) {
         Ok(_) => {
             println!("Successfuly export meta data !");
-            match db_postgresql.export_tables_csv(&tables_folder) {
-                Ok(_) => {
-                    println!("Successfuly export tables !");
+            db_postgresql
+                .export_from_sql(&script_meta_data, "export_views_metadata", &save_views)
+                .map_err(|error| format!("ERROR when try to export the views :\n{}", error))?;
+            println!(
+                "{}",
+                views::generate_views_file(&save_views, &save_views_cypher, views::ViewsMode::Catalog, &mapping)?
+            );
+            report.stage("Export the meta-data", start);
+            let start = Instant::now();
+            let large_objects = binary::large_object_columns(&save_meta_data, &mapping)?;
+            match db_postgresql.export_tables_csv(&tables_folder, &large_objects, mapping.workers()?) {
+                Ok(snapshot) => {
+                    println!("Successfuly export tables at the LSN {} !", snapshot.lsn);
+                    // A CDC sync started from this LSN continues where the export ended.
+                    utils::write_file(
+                        serde_json::to_string_pretty(&snapshot.to_json()).map_err(|error| format!("{}", error))?,
+                        &save_snapshot,
+                    )?;
+                    report.stage("Export the tables", start);
                 }
                 Err(result) => {
                     return Err(format!("ERROR when try to export tables :\n{}", result))
@@ -60,7 +573,7 @@ fn demo() -> Result<(), String> {
     match db_neo4j.configure_db_on_linux() {
         Ok(result) => {
             println!("{}", result);
-            match generate_import_files(&db_neo4j, &save_meta_data, &tables_folder, &save_fk) {
+            match generate_import_files(&db_neo4j, &save_meta_data, &tables_folder, &save_fk, &mapping, &mut report) {
                 Ok(result) => println!("{}", result),
                 Err(result) => println!("{}", result),
             }
@@ -74,8 +587,11 @@ fn demo() -> Result<(), String> {
         .read_line(&mut user_input)
         .expect("Error when try to read the user input.");
 
-    if user_input.trim() == "YES" {
-        match load_with_admin(&db_neo4j) {
+    let result = if user_input.trim() == "YES" {
+        let start = Instant::now();
+        let result = load_with_admin(&db_neo4j);
+        report.stage("Import with neo4j-admin", start);
+        match result {
             Ok(result) => {
                 println!("{}", result);
                 Ok(())
@@ -85,5 +601,7 @@ fn demo() -> Result<(), String> {
     } else {
         println!("Ok, you could done the import later with the method 'load_with_admin()' of the struct 'Neo4j'.");
         Ok(())
-    }
+    };
+    println!("{}", report.write(&save_report)?);
+    result
 }
\ No newline at end of file
@@ -0,0 +1,362 @@
+//! This module contains the mapping : the optional configuration of the migration stored in ***mapping.json***.<br>
+//! The options of a table or a column are stored like this :
+//! ```json
+//! {
+//!     "tables": {
+//!         "customers": {
+//!             "columns": {
+//!                 "address": { "json": "flatten" }
+//!             }
+//!         }
+//!     }
+//! }
+//! ```
+
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+use serde_json::{Map, Value};
+
+use crate::utils::write_file;
+
+/// The memory in MB that the tables being transformed can use, by default.
+pub const DEFAULT_MEMORY_LIMIT: usize = 2048;
+
+/// A structure that represent the configuration of the migration.
+#[derive(Debug, Default)]
+pub struct Mapping {
+    config: Value,
+}
+
+/// How a JSON/JSONB column is migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum JsonStrategy {
+    /// Keep the JSON text in a ```STRING``` property.
+    String,
+    /// The top-level keys become prefixed properties : ```address_city```, ```address_zip```...
+    Flatten,
+    /// The nested objects and arrays become child nodes connected by a ```HAS_<KEY>``` relationship.
+    Nodes,
+}
+
+/// How the enum columns and the lookup tables are migrated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CategoryStrategy {
+    /// A ```STRING``` property restricted to the allowed values by an APOC trigger.
+    Property,
+    /// An extra label per value, like ```:ORDERS:SHIPPED```.
+    Labels,
+    /// A shared category node connected by a relationship.
+    Nodes,
+}
+
+impl CategoryStrategy {
+    fn parse(value: &Value) -> Option<Self> {
+        match value.as_str()? {
+            "property" => Some(Self::Property),
+            "labels" => Some(Self::Labels),
+            "nodes" => Some(Self::Nodes),
+            _ => None,
+        }
+    }
+}
+
+/// How the binary values (```bytea``` and large objects) are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// A base64 ```STRING``` property.
+    Base64,
+    /// A ```BYTE[]``` property.
+    Bytes,
+    /// An external file in the blob folder, the node hold its path and its SHA-256.
+    Files,
+}
+
+/// The storage of a binary column : its format and the size (in bytes) above which the values are stored
+/// in external files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BinaryStorage {
+    pub format: BinaryFormat,
+    pub threshold: Option<u64>,
+}
+
+impl BinaryStorage {
+    /// Return true if some values can be stored in external files.
+    pub fn is_external(&self) -> bool {
+        self.format == BinaryFormat::Files || self.threshold.is_some()
+    }
+
+    /// Return the Neo4j type of the property that contains the values stored in the nodes.
+    pub fn data_type(&self) -> &'static str {
+        match self.format {
+            BinaryFormat::Bytes => "BYTE[]",
+            _ => "STRING",
+        }
+    }
+}
+
+impl Mapping {
+    /// Read the mapping from the JSON file, the default mapping is used if the file doesn't exist.
+    pub fn load(path: &str) -> Result<Self, String> {
+        if !Path::new(path).exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path)
+            .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", path, error))?;
+        let config: Value = serde_json::from_str(&content)
+            .map_err(|error| format!("ERROR : when try to parse the mapping {}\n{}", path, error))?;
+        if !config.is_object() {
+            return Err(format!("ERROR : The mapping {} need to be a JSON object.", path));
+        }
+        Ok(Self { config })
+    }
+
+    #[allow(unused)]
+    pub fn from_value(config: Value) -> Self {
+        Self { config }
+    }
+
+    /// Return the global option ```key```.
+    pub fn option(&self, key: &str) -> Option<&Value> {
+        self.config.get(key)
+    }
+
+    /// Return the option ```key``` of the table, the names of the tables are case insensitive.
+    pub fn table_option(&self, table: &str, key: &str) -> Option<&Value> {
+        self.config
+            .get("tables")?
+            .as_object()?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(table))
+            .and_then(|(_, options)| options.get(key))
+    }
+
+    /// Return the option ```key``` of the column of the table.
+    pub fn column_option(&self, table: &str, column: &str, key: &str) -> Option<&Value> {
+        self.table_option(table, "columns")?
+            .as_object()?
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(column))
+            .and_then(|(_, options)| options.get(key))
+    }
+
+    /// Return the strategy used to migrate the JSON column, ```JsonStrategy::String``` by default.
+    pub fn json_strategy(&self, table: &str, column: &str) -> Result<JsonStrategy, String> {
+        match self.column_option(table, column, "json") {
+            None => Ok(JsonStrategy::String),
+            Some(value) => match value.as_str() {
+                Some("string") => Ok(JsonStrategy::String),
+                Some("flatten") => Ok(JsonStrategy::Flatten),
+                Some("nodes") => Ok(JsonStrategy::Nodes),
+                _ => Err(format!(
+                    "ERROR : Unknown JSON strategy {} for the column {}.{}, expected \"string\", \"flatten\" or \"nodes\".",
+                    value, table, column
+                )),
+            },
+        }
+    }
+
+    /// Return true if the non-point geometries of the column are stored as a list of points
+    /// (```"geometry": "points"```) instead of WKT (```"geometry": "wkt"```, by default).
+    pub fn geometry_as_points(&self, table: &str, column: &str) -> Result<bool, String> {
+        match self.column_option(table, column, "geometry") {
+            None => Ok(false),
+            Some(value) => match value.as_str() {
+                Some("wkt") => Ok(false),
+                Some("points") => Ok(true),
+                _ => Err(format!(
+                    "ERROR : Unknown geometry format {} for the column {}.{}, expected \"wkt\" or \"points\".",
+                    value, table, column
+                )),
+            },
+        }
+    }
+
+    /// Return the strategy used to migrate the enum column (```"enum"``` option), ```CategoryStrategy::Property``` by default.
+    pub fn enum_strategy(&self, table: &str, column: &str) -> Result<CategoryStrategy, String> {
+        match self.column_option(table, column, "enum") {
+            None => Ok(CategoryStrategy::Property),
+            Some(value) => CategoryStrategy::parse(value).ok_or_else(|| {
+                format!(
+                    "ERROR : Unknown enum strategy {} for the column {}.{}, expected \"property\", \"labels\" or \"nodes\".",
+                    value, table, column
+                )
+            }),
+        }
+    }
+
+    /// Return the strategy used to migrate the foreign keys that reference the lookup table and the column
+    /// of the lookup table that contains the values : ```"lookup": { "strategy": "labels", "column": "name" }```.<br>
+    /// Return ```None``` if the table isn't a lookup table.
+    pub fn lookup_strategy(&self, table: &str) -> Result<Option<(CategoryStrategy, String)>, String> {
+        let lookup = match self.table_option(table, "lookup") {
+            None => return Ok(None),
+            Some(lookup) => lookup,
+        };
+        let strategy = CategoryStrategy::parse(&lookup["strategy"]).ok_or_else(|| {
+            format!(
+                "ERROR : Unknown lookup strategy {} for the table {}, expected \"property\", \"labels\" or \"nodes\".",
+                lookup["strategy"], table
+            )
+        })?;
+        let column = lookup["column"]
+            .as_str()
+            .ok_or_else(|| format!("ERROR : The lookup table {} need a 'column' that contains the values.", table))?;
+        Ok(Some((strategy, String::from(column))))
+    }
+
+    /// Return the strategy used to migrate the composite column (```"composite"``` option) : its fields
+    /// are flattened into properties (```"properties"```, by default) or stored in a child node (```"nodes"```).
+    pub fn composite_strategy(&self, table: &str, column: &str) -> Result<JsonStrategy, String> {
+        match self.column_option(table, column, "composite") {
+            None => Ok(JsonStrategy::Flatten),
+            Some(value) => match value.as_str() {
+                Some("properties") => Ok(JsonStrategy::Flatten),
+                Some("nodes") => Ok(JsonStrategy::Nodes),
+                Some("string") => Ok(JsonStrategy::String),
+                _ => Err(format!(
+                    "ERROR : Unknown composite strategy {} for the column {}.{}, expected \"properties\", \"nodes\" or \"string\".",
+                    value, table, column
+                )),
+            },
+        }
+    }
+
+    /// Return the storage of the binary column, with the options ```"binary"``` (```"base64"``` by default,
+    /// ```"bytes"``` or ```"files"```) and ```"binary_threshold"``` of the column, or of the mapping if the
+    /// column doesn't have them.
+    pub fn binary_storage(&self, table: &str, column: &str) -> Result<BinaryStorage, String> {
+        let format = match self.column_option(table, column, "binary").or(self.option("binary")) {
+            None => BinaryFormat::Base64,
+            Some(value) => match value.as_str() {
+                Some("base64") => BinaryFormat::Base64,
+                Some("bytes") => BinaryFormat::Bytes,
+                Some("files") => BinaryFormat::Files,
+                _ => {
+                    return Err(format!(
+                        "ERROR : Unknown binary format {} for the column {}.{}, expected \"base64\", \"bytes\" or \"files\".",
+                        value, table, column
+                    ))
+                }
+            },
+        };
+        let threshold = match self.column_option(table, column, "binary_threshold").or(self.option("binary_threshold")) {
+            None => None,
+            Some(value) => Some(value.as_u64().ok_or_else(|| {
+                format!(
+                    "ERROR : The binary threshold of the column {}.{} need to be a number of bytes, found {}.",
+                    table, column, value
+                )
+            })?),
+        };
+        Ok(BinaryStorage { format, threshold })
+    }
+
+    /// Return true if the ```oid``` column reference large objects (```"large_object": true```).
+    pub fn is_large_object(&self, table: &str, column: &str) -> bool {
+        self.column_option(table, column, "large_object").and_then(|value| value.as_bool()) == Some(true)
+    }
+
+    /// Return the options of the self-referencing foreign key (a hierarchy) with the column option
+    /// ```"hierarchy": { "type": "REPORTS_TO", "depth": true }``` : the type of the relationships (the generic
+    /// ```<TABLE>_ref_<COLUMN>``` by default) and if the ```depth``` and ```root``` properties are computed.
+    pub fn hierarchy(&self, table: &str, column: &str) -> Result<(Option<String>, bool), String> {
+        let Some(options) = self.column_option(table, column, "hierarchy") else {
+            return Ok((None, false));
+        };
+        let relationship_type = match options.get("type") {
+            None => None,
+            Some(value) => Some(relationship_type_value(value, table, column)?),
+        };
+        let depth = options.get("depth").and_then(|depth| depth.as_bool()).unwrap_or(false);
+        Ok((relationship_type, depth))
+    }
+
+    /// Return the type of the relationships of the foreign key, chosen with the column option ```"relationship"```
+    /// or the ```"type"``` of its ```"hierarchy"```. ```None``` for the generic ```<TABLE>_ref_<COLUMN>```.
+    pub fn relationship_type(&self, table: &str, column: &str) -> Result<Option<String>, String> {
+        match self.column_option(table, column, "relationship") {
+            Some(value) => Ok(Some(relationship_type_value(value, table, column)?)),
+            None => Ok(self.hierarchy(table, column)?.0),
+        }
+    }
+
+    /// Set the option ```key``` of the column, the table and the column are added to the mapping if needed.
+    pub fn set_column_option(&mut self, table: &str, column: &str, key: &str, value: Value) {
+        if !self.config.is_object() {
+            self.config = Value::Object(Map::new());
+        }
+        let Some(config) = self.config.as_object_mut() else {
+            return;
+        };
+        let options = object_entry(config, "tables")
+            .and_then(|tables| object_entry(tables, table))
+            .and_then(|table| object_entry(table, "columns"))
+            .and_then(|columns| object_entry(columns, column));
+        if let Some(options) = options {
+            options.insert(String::from(key), value);
+        }
+    }
+
+    /// Write the mapping in the file ```path```.
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(&self.config).map_err(|error| format!("{}", error))?;
+        write_file(format!("{}\n", content), path)
+    }
+
+    /// Return true if the migration fail when the foreign keys have orphan values (```"strict": true```).
+    pub fn is_strict(&self) -> bool {
+        self.option("strict").and_then(|strict| strict.as_bool()) == Some(true)
+    }
+
+    /// Return the number of tables exported and transformed at the same time (```"workers": 8```), by default
+    /// the number of CPUs up to 8.
+    pub fn workers(&self) -> Result<usize, String> {
+        match self.option("workers") {
+            None => Ok(thread::available_parallelism().map(|count| count.get().min(8)).unwrap_or(1)),
+            Some(workers) => match workers.as_u64() {
+                Some(workers) if workers > 0 => Ok(workers as usize),
+                _ => Err(format!("ERROR : The number of workers need to be a positive integer, found {}", workers)),
+            },
+        }
+    }
+
+    /// Return the memory in bytes that the tables being transformed can use (```"memory_limit": 4096``` in MB),
+    /// 2 GB by default.
+    pub fn memory_limit(&self) -> Result<usize, String> {
+        match self.option("memory_limit") {
+            None => Ok(DEFAULT_MEMORY_LIMIT << 20),
+            Some(limit) => match limit.as_u64() {
+                Some(limit) if limit > 0 => Ok((limit as usize) << 20),
+                _ => Err(format!("ERROR : The memory limit need to be a positive number of MB, found {}", limit)),
+            },
+        }
+    }
+}
+
+/// Check the type of relationships written in the mapping for the column.
+fn relationship_type_value(value: &Value, table: &str, column: &str) -> Result<String, String> {
+    match value.as_str() {
+        Some(relationship_type)
+            if !relationship_type.is_empty()
+                && relationship_type.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') =>
+        {
+            Ok(String::from(relationship_type))
+        }
+        _ => Err(format!(
+            "ERROR : The relationship type {} of the foreign key {}.{} need to contain only letters, digits and '_'.",
+            value, table, column
+        )),
+    }
+}
+
+/// Return the object of the ```key``` (case insensitive), an empty object is added if the key is missing.
+fn object_entry<'a>(object: &'a mut Map<String, Value>, key: &str) -> Option<&'a mut Map<String, Value>> {
+    let key = object
+        .keys()
+        .find(|name| name.eq_ignore_ascii_case(key))
+        .cloned()
+        .unwrap_or_else(|| String::from(key));
+    object.entry(key).or_insert_with(|| Value::Object(Map::new())).as_object_mut()
+}
@@ -5,6 +5,8 @@ use std::fs::OpenOptions;
 use std::io::Write;
 use std::process::Command;
 
+use crate::spatial::parse_spatial_type;
+
 /// A structure that represent a Neo4j connection
 #[derive(Debug)]
 pub struct Neo4j {
@@ -138,25 +140,59 @@ impl Neo4j {
     }
 
     /// Convert PostgreSQL Type into Neo4j type.<br>
+    /// The arrays are converted with the ```udt_name``` of their column (like ```_int4```).<br>
+    /// The point geometries are converted into ```POINT```, the other geometries are stored in WKT.<br>
     /// CAUTION : These convertion are suitable for mass export.
     pub fn convert_postgresql_type(postgresql_type: &str) -> Result<String, String> {
-        let target_type = postgresql_type.to_uppercase();
+        let target_type = postgresql_type.trim().to_uppercase();
+        // The arrays are named '_int4' by the udt_name and 'integer[]' by format_type.
+        if let Some(element_type) = target_type.strip_prefix('_').or(target_type.strip_suffix("[]")) {
+            let element_type = Neo4j::convert_postgresql_type(element_type)?;
+            if element_type.ends_with("[]") {
+                return Ok(element_type);
+            }
+            return Ok(format!("{}[]", element_type));
+        }
+        // The PostGIS types are converted with their definition, like 'geometry(Point,4326)'.
+        if let Some(spatial_type) = parse_spatial_type(&target_type) {
+            if spatial_type.is_point() {
+                return Ok(String::from("POINT"));
+            }
+            return Ok(String::from("STRING"));
+        }
+        // The modifiers are ignored, like in 'numeric(10,2)' or 'timestamp(3) with time zone'.
+        let mut depth = 0;
+        let target_type = target_type
+            .chars()
+            .filter(|c| {
+                match c {
+                    '(' => depth += 1,
+                    ')' => depth -= 1,
+                    _ => return depth == 0,
+                }
+                false
+            })
+            .collect::<String>()
+            .split_whitespace()
+            .collect::<Vec<&str>>()
+            .join(" ");
         match target_type.as_str() {
             "SMALLINT" | "INT" | "INTEGER" | "BIGINT" => Ok(String::from("LONG")),
+            "INT2" | "INT4" | "INT8" => Ok(String::from("LONG")),
             "BIGSERIAL" | "SMALLSERIAL" | "SERIAL" => Ok(String::from("LONG")),
-            "REAL" | "DOUBLE" | "DECIMAL" | "PRECISION" | "FLOAT8" | "DOUBLE PRECISION"
+            "REAL" | "DOUBLE" | "DECIMAL" | "PRECISION" | "FLOAT4" | "FLOAT8" | "DOUBLE PRECISION"
             | "NUMERIC" => Ok(String::from("DOUBLE")),
-            "VARCHAR" | "TEXT" | "CHAR" | "CHARACTER VARYING" | "CHARACTER" | "BPCHAR" => {
+            "VARCHAR" | "TEXT" | "CHAR" | "CHARACTER VARYING" | "CHARACTER" | "BPCHAR" | "NAME" => {
                 Ok(String::from("STRING"))
             }
-            "BOOLEAN" => Ok(String::from("BOOLEAN")),
-            "DATE" | "TIME" | "TIMESTAMP" => Ok(String::from("DATE")),
-            "TIMESTAMP WITHOUT TIME ZONE"
-            | "TIME WITH TIME ZONE"
-            | "TIME WITHOUT TIME ZONE"
-            | "TIMESTAMP WITH TIME ZONE" => Ok(String::from("STRING")),
-            "JSON" | "XML" | "JSONB" | "INTERVAL" | "UUID" | "MONEY" => Ok(String::from("STRING")),
-            "POINT" => Ok(String::from("STRING")),
+            "BOOLEAN" | "BOOL" => Ok(String::from("BOOLEAN")),
+            "DATE" => Ok(String::from("DATE")),
+            "TIME" | "TIME WITHOUT TIME ZONE" => Ok(String::from("LOCALTIME")),
+            "TIMETZ" | "TIME WITH TIME ZONE" => Ok(String::from("TIME")),
+            "TIMESTAMP" | "TIMESTAMP WITHOUT TIME ZONE" => Ok(String::from("LOCALDATETIME")),
+            "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => Ok(String::from("DATETIME")),
+            "INTERVAL" => Ok(String::from("DURATION")),
+            "JSON" | "XML" | "JSONB" | "UUID" | "MONEY" => Ok(String::from("STRING")),
             "ARRAY" | "TSVECTOR" | "TSQUERY" => Ok(String::from("STRING[]")),
             "BIGINT[]" => Ok(String::from("LONG[]")),
             "BYTEA" | "ENUM" | "BIT" | "BIT VARYING" => Ok(String::from("STRING")),
@@ -217,3 +253,63 @@ impl Neo4j {
         }
     }
 }
+
+#[test]
+fn test_convert_temporal_types() {
+    let expected = [
+        ("date", "DATE"),
+        ("time without time zone", "LOCALTIME"),
+        ("time with time zone", "TIME"),
+        ("timestamp without time zone", "LOCALDATETIME"),
+        ("timestamp with time zone", "DATETIME"),
+        ("interval", "DURATION"),
+    ];
+    for (postgresql_type, neo4j_type) in expected {
+        assert_eq!(Neo4j::convert_postgresql_type(postgresql_type).unwrap(), neo4j_type);
+    }
+}
+
+#[test]
+fn test_convert_array_types() {
+    let expected = [
+        ("_int4", "LONG[]"),
+        ("_float8", "DOUBLE[]"),
+        ("_bool", "BOOLEAN[]"),
+        ("_text", "STRING[]"),
+        ("_date", "DATE[]"),
+        ("_timestamptz", "DATETIME[]"),
+        ("_tsvector", "STRING[]"),
+    ];
+    for (udt_name, neo4j_type) in expected {
+        assert_eq!(Neo4j::convert_postgresql_type(udt_name).unwrap(), neo4j_type);
+    }
+}
+
+#[test]
+fn test_convert_spatial_types() {
+    let expected = [
+        ("point", "POINT"),
+        ("geometry(Point,4326)", "POINT"),
+        ("geography(PointZ,4326)", "POINT"),
+        ("geometry(Polygon,2154)", "STRING"),
+        ("geometry", "STRING"),
+        ("line", "STRING"),
+    ];
+    for (postgresql_type, neo4j_type) in expected {
+        assert_eq!(Neo4j::convert_postgresql_type(postgresql_type).unwrap(), neo4j_type);
+    }
+}
+
+#[test]
+fn test_convert_type_definitions() {
+    let expected = [
+        ("character varying(255)", "STRING"),
+        ("numeric(10,2)", "DOUBLE"),
+        ("timestamp(3) with time zone", "DATETIME"),
+        ("integer[]", "LONG[]"),
+    ];
+    for (postgresql_type, neo4j_type) in expected {
+        assert_eq!(Neo4j::convert_postgresql_type(postgresql_type).unwrap(), neo4j_type);
+    }
+    assert!(Neo4j::convert_postgresql_type("mood").is_err());
+}
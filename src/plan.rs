@@ -0,0 +1,232 @@
+//! This module preview a migration : the meta-data read from PostgreSQL (in a transaction rolled back) are
+//! translated like during the migration, but no file is written and the databases aren't modified. The plan lists the
+//! labels and their properties, the relationships, the constraints, the triggers and the columns that are
+//! converted or unsupported, with the size of the tables estimated by PostgreSQL.
+
+use std::collections::HashMap;
+use std::fs;
+
+use serde_json::{json, Value};
+
+use crate::format_to_neo4j::plan_meta_data;
+use crate::mapping::{JsonStrategy, Mapping};
+
+/// Return the size in a human readable form, like ```12.5 MB```.
+fn format_size(bytes: u64) -> String {
+    let units = ["B", "KB", "MB", "GB", "TB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < units.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", size, units[unit])
+    }
+}
+
+/// Return the properties (name, type) of the headers of a nodes file.
+pub fn header_properties(headers: &str) -> Vec<(String, String)> {
+    headers
+        .trim()
+        .split(';')
+        .filter(|header| !header.starts_with(':'))
+        .filter_map(|header| header.split_once(':'))
+        .map(|(name, data_type)| (String::from(name), String::from(data_type)))
+        .collect()
+}
+
+/// Return the name of the APOC trigger added by the statement.
+pub fn trigger_name(statement: &str) -> Option<&str> {
+    statement.split_once("apoc.trigger.add('")?.1.split_once('\'').map(|(name, _)| name)
+}
+
+/// Return the relationships (type, start label, end label, foreign key column) of the foreign keys file.
+pub fn plan_relationships(foreign_keys: &str) -> Vec<(String, String, String, String)> {
+    foreign_keys
+        .lines()
+        .filter_map(|line| {
+            let elements = line.split(';').collect::<Vec<&str>>();
+            let (start, end) = elements.first()?.split_once("_ref_")?;
+            let column = elements.get(1)?;
+            let relationship_type = match elements.get(3) {
+                Some(relationship_type) => String::from(relationship_type.trim()),
+                None => format!("{}_ref_{}", start, column.to_uppercase()),
+            };
+            Some((relationship_type, String::from(start), String::from(end), String::from(*column)))
+        })
+        .collect()
+}
+
+/// Generate the plan of the migration from the meta-data and the mapping, in a human readable form and in JSON.
+pub fn generate_plan(meta_data_path: &str, mapping: &Mapping) -> Result<(String, Value), String> {
+    let files = plan_meta_data(meta_data_path, mapping)?;
+    let content = fs::read_to_string(meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+
+    // The statistics of the tables, and the JSON columns whose properties depend on the data.
+    let mut statistics: HashMap<String, (u64, u64)> = HashMap::new();
+    let mut conversions = files.conversions.clone();
+    for table in json_object.as_array().into_iter().flatten() {
+        let Some(table_name) = table["table_name"].as_str() else {
+            continue;
+        };
+        let label = table_name.to_uppercase();
+        statistics.insert(
+            String::clone(&label),
+            (
+                table["estimated_rows"].as_u64().unwrap_or(0),
+                table["estimated_bytes"].as_u64().unwrap_or(0),
+            ),
+        );
+        for column in table["columns"].as_array().into_iter().flatten() {
+            let (Some(column_name), Some(data_type)) = (column["column_name"].as_str(), column["data_type"].as_str()) else {
+                continue;
+            };
+            if data_type != "json" && data_type != "jsonb" {
+                continue;
+            }
+            let description = match mapping.json_strategy(&label, column_name)? {
+                JsonStrategy::String => continue,
+                JsonStrategy::Flatten => "JSON flattened into prefixed properties, found in the data",
+                JsonStrategy::Nodes => "JSON exploded into child nodes, found in the data",
+            };
+            conversions.push((String::clone(&label), String::from(column_name), String::from(description)));
+        }
+    }
+
+    let mut text = String::from("Migration plan (no file is written and no database is modified)\n");
+    let mut labels = Vec::new();
+    let mut total_bytes = 0;
+    text.push_str(&format!("\nLabels : {}\n", files.headers.len()));
+    for (label, headers) in &files.headers {
+        let properties = header_properties(headers);
+        let extra_labels = files.parent_labels.get(label).cloned().unwrap_or_default();
+        let (rows, bytes) = statistics.get(label).copied().unwrap_or((0, 0));
+        total_bytes += bytes;
+        text.push_str(&format!(
+            "  :{}{} - {} rows, {}\n",
+            label,
+            extra_labels.iter().map(|extra_label| format!(":{}", extra_label)).collect::<String>(),
+            rows,
+            format_size(bytes)
+        ));
+        for (name, data_type) in &properties {
+            text.push_str(&format!("    {} : {}\n", name, data_type));
+        }
+        labels.push(json!({
+            "label": label,
+            "extra_labels": extra_labels,
+            "properties": properties
+                .iter()
+                .map(|(name, data_type)| json!({ "name": name, "type": data_type }))
+                .collect::<Vec<Value>>(),
+            "estimated_rows": rows,
+            "estimated_bytes": bytes,
+        }));
+    }
+
+    let relationships = plan_relationships(&files.foreign_keys);
+    text.push_str(&format!("\nRelationships : {}\n", relationships.len()));
+    for (relationship_type, start, end, column) in &relationships {
+        text.push_str(&format!("  (:{})-[:{}]->(:{}) from {}.{}\n", start, relationship_type, end, start, column));
+    }
+
+    let constraints = files
+        .constraints
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect::<Vec<&str>>();
+    text.push_str(&format!("\nConstraints and indexes : {}\n", constraints.len()));
+    for constraint in &constraints {
+        text.push_str(&format!("  {}\n", constraint));
+    }
+
+    let triggers = files.triggers.lines().filter_map(trigger_name).collect::<Vec<&str>>();
+    text.push_str(&format!("\nTriggers : {}\n", triggers.len()));
+    for trigger in &triggers {
+        text.push_str(&format!("  {}\n", trigger));
+    }
+
+    text.push_str(&format!("\nConverted columns : {}\n", conversions.len()));
+    for (label, column, conversion) in &conversions {
+        text.push_str(&format!("  {}.{} : {}\n", label, column, conversion));
+    }
+    text.push_str(&format!("\nUnsupported columns (stored as STRING) : {}\n", files.unsupported_columns.len()));
+    for column in &files.unsupported_columns {
+        text.push_str(&format!("  {}\n", column));
+    }
+    text.push_str(&format!("\nUntranslated CHECK constraints : {}\n", files.checks_report.len()));
+    for check in &files.checks_report {
+        text.push_str(&format!("  {}\n", check));
+    }
+    text.push_str(&format!("\nEstimated size of the files : {}\n", format_size(total_bytes)));
+
+    let json_plan = json!({
+        "labels": labels,
+        "relationships": relationships
+            .iter()
+            .map(|(relationship_type, start, end, column)| json!({
+                "type": relationship_type,
+                "start": start,
+                "end": end,
+                "foreign_key": format!("{}.{}", start, column),
+            }))
+            .collect::<Vec<Value>>(),
+        "constraints": constraints,
+        "triggers": triggers,
+        "converted_columns": conversions
+            .iter()
+            .map(|(label, column, conversion)| json!({ "label": label, "column": column, "conversion": conversion }))
+            .collect::<Vec<Value>>(),
+        "unsupported_columns": files.unsupported_columns,
+        "untranslated_checks": files.checks_report,
+        "estimated_bytes": total_bytes,
+    });
+    Ok((text, json_plan))
+}
+
+#[test]
+fn test_generate_plan() {
+    assert_eq!(format_size(512), "512 B");
+    assert_eq!(format_size(1536), "1.5 KB");
+    assert_eq!(
+        header_properties(":ID;name:STRING;tags:STRING[];:LABEL\n"),
+        vec![(String::from("name"), String::from("STRING")), (String::from("tags"), String::from("STRING[]"))]
+    );
+
+    let folder = format!("{}/plan_test", std::env::temp_dir().display());
+    fs::create_dir_all(&folder).unwrap();
+    let meta_data_path = format!("{}/meta_data.json", folder);
+    let meta_data = json!([
+        {
+            "table_name": "customers",
+            "estimated_rows": 3,
+            "estimated_bytes": 2048,
+            "columns": [
+                { "column_name": "id", "data_type": "integer", "is_nullable": "NO", "primary_key": true, "foreign_key": null },
+                { "column_name": "shape", "data_type": "USER-DEFINED", "udt_name": "unknown_type", "foreign_key": null }
+            ]
+        },
+        {
+            "table_name": "orders",
+            "columns": [
+                { "column_name": "id", "data_type": "integer", "foreign_key": null },
+                { "column_name": "customer_id", "data_type": "integer",
+                  "foreign_key": [{ "referenced_table": "customers", "referenced_column": "id" }] }
+            ]
+        }
+    ]);
+    fs::write(&meta_data_path, meta_data.to_string()).unwrap();
+    let (text, json_plan) = generate_plan(&meta_data_path, &Mapping::default()).unwrap();
+    let _ = fs::remove_dir_all(&folder);
+
+    assert!(text.contains("  :CUSTOMERS - 3 rows, 2.0 KB\n    id : LONG\n    shape : STRING\n"));
+    assert!(text.contains("  (:ORDERS)-[:ORDERS_ref_CUSTOMER_ID]->(:CUSTOMERS) from ORDERS.customer_id\n"));
+    assert_eq!(json_plan["unsupported_columns"], json!(["CUSTOMERS.shape (unknown_type)"]));
+    assert_eq!(json_plan["triggers"], json!(["type_customers_id", "type_customers_shape", "type_orders_id"]));
+    assert_eq!(json_plan["constraints"].as_array().unwrap().len(), 2);
+}
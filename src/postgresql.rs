@@ -1,6 +1,79 @@
 //! This module simplify interactions with PostgreSQL database
 
-use std::process::Command;
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::process::{Child, Command, Stdio};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use crate::equivalence::parse_psql_records;
+use crate::workers::run_pool;
+
+/// Keep the LSN of the WAL in the session before the transaction of the export takes its snapshot : a LSN read in the
+/// transaction could be after the commits not visible in its snapshot, a CDC sync started from it would miss them.
+const SNAPSHOT_LSN: &str = "SELECT set_config('neo4j_migrator.snapshot_lsn', pg_current_wal_insert_lsn()::text, false);";
+
+/// The columns that read the position of the snapshot : the LSN kept by ```SNAPSHOT_LSN```, the time and the
+/// transactions visible.
+const SNAPSHOT_COLUMNS: &str =
+    "current_setting('neo4j_migrator.snapshot_lsn') AS snapshot_lsn, now() AS snapshot_time, pg_current_snapshot() AS snapshot";
+
+/// The point in time of an export. Its LSN is a lower bound of the snapshot : a CDC sync started from it doesn't
+/// miss a change, but replays the changes committed between the LSN and the snapshot, which are already exported.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    pub lsn: String,
+    pub time: String,
+    /// The transactions visible in the snapshot, like ```pg_current_snapshot()```.
+    pub transactions: String,
+}
+
+impl Snapshot {
+    /// Read the snapshot in the output of the export script.
+    fn parse(output: &str) -> Result<Self, String> {
+        let start = output
+            .find("snapshot_lsn,")
+            .ok_or_else(|| format!("ERROR : The snapshot of the export isn't in the output :\n{}", output))?;
+        let (_, records) = parse_psql_records(&output[start..])?;
+        match records.first().map(|record| record.as_slice()) {
+            Some([Some(lsn), Some(time), Some(transactions), ..]) => Ok(Self {
+                lsn: String::clone(lsn),
+                time: String::clone(time),
+                transactions: String::clone(transactions),
+            }),
+            _ => Err(format!("ERROR : The snapshot of the export isn't valid :\n{}", output)),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({ "lsn": self.lsn, "time": self.time, "transactions": self.transactions })
+    }
+
+    /// Read the snapshot saved with ```to_json```.
+    pub fn from_json(json: &Value) -> Result<Self, String> {
+        let field = |key: &str| {
+            json[key]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("ERROR : The snapshot doesn't contain the field '{}' : {}", key, json))
+        };
+        Ok(Self { lsn: field("lsn")?, time: field("time")?, transactions: field("transactions")? })
+    }
+}
+
+/// The time given to the coordinator of the export to open its transaction and export its snapshot.
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Return the ```\copy``` of the large objects referenced by the column.
+fn large_objects_copy(table: &str, column: &str, folder: &str) -> String {
+    format!(
+        r"\copy (SELECT DISTINCT {} AS oid, lo_get({}) AS data FROM {} WHERE {} IS NOT NULL AND EXISTS (SELECT 1 FROM pg_largeobject_metadata WHERE oid = {})) to '{}{}.{}.csv' CSV HEADER",
+        column, column, table, column, column, folder, table.to_lowercase(), column
+    )
+}
 
 /// A structure that represent a PostgreSQL connection
 #[derive(Debug)]
@@ -89,6 +162,15 @@ impl PostgreSQL {
         }
     }
 
+    /// Run the script written in the temporary folder, the file is removed once it's done.
+    fn execute_temporary_script(&self, name: &str, script: String) -> Result<String, String> {
+        let script_path = format!("{}", env::temp_dir().join(format!("neo4j_migrator_{}_{}.sql", name, std::process::id())).display());
+        fs::write(&script_path, script).map_err(|error| format!("ERROR : when try to write the file : {}\n{}", script_path, error))?;
+        let result = self.execute_script(&script_path);
+        let _ = fs::remove_file(&script_path);
+        result
+    }
+
     /// This method allows you to export the result of the SQL function called ```function_name```
     /// and define in the PostgreSQL script ```script_path``` to the file specified in ```save_path```.
     /// You should use it to export the meta data of your PostgreSQL database.
@@ -115,34 +197,159 @@ impl PostgreSQL {
         }
     }
 
+    /// Export the result of the function of the script in the file ```save_path``` without modifying the database :
+    /// the function is created in a transaction rolled back after the export.
+    pub fn read_from_sql(&self, script_path: &str, function_name: &str, save_path: &str) -> Result<String, String> {
+        let functions = fs::read_to_string(script_path)
+            .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", script_path, error))?;
+        let script = format!(
+            "\\set ON_ERROR_STOP on\nBEGIN;\n{}\n\\copy (select {}()) to '{}'\nROLLBACK;\n",
+            functions, function_name, save_path
+        );
+        self.execute_temporary_script("read_meta_data", script)
+            .map_err(|error| format!("ERROR : when try to read the result of {}\n{}", function_name, error))
+    }
+
     /// This method export in CSV all the tables from the public scheme of the
-    /// PostgreSQL database to the folder passed in argument.
-    pub fn export_tables_csv(&self, folder_path: &str) -> Result<String, String> {
-        let query =
-            "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public'";
-        match &self.execute_query(query, true) {
-            Ok(result) => {
-                let tables = result.split("\n").collect::<Vec<&str>>();
-                for index in 1..tables.len() - 1 {
-                    if let Some(table) = tables.get(index) {
-                        let table = *table;
-                        if let Err(error) = &self.execute_query(
-                            &format!(
-                                r"\copy {} to '{}{}.csv' CSV HEADER",
-                                table, folder_path, table
-                            ),
-                            true,
-                        ) {
-                            return Err(format!(
-                                "ERROR : when try to export the data of the table : '{}'\n{}",
-                                table, error
-                            ));
-                        }
-                    }
+    /// PostgreSQL database to the folder passed in argument.<br>
+    /// The views are ignored, they are translated into Cypher queries by the module ```views```.<br>
+    /// The partitions are exported once with their partitioned table, the rows of the tables inherited by
+    /// other tables (```INHERITS```) are exported without the rows of their children.<br>
+    /// The tables and the large objects of the ```large_objects``` columns are exported in ```REPEATABLE READ```
+    /// read-only transactions that share the same snapshot : they are consistent, the foreign keys can't reference
+    /// rows inserted during the export. With more than one of the ```workers```, the snapshot is exported by a
+    /// coordinator (```pg_export_snapshot```) and each worker exports a table at a time. Return the snapshot.
+    pub fn export_tables_csv(&self, folder_path: &str, large_objects: &[(String, String)], workers: usize) -> Result<Snapshot, String> {
+        let query = "SELECT c.table_name, pgc.relkind = 'p' AS partitioned FROM information_schema.tables c \
+            JOIN pg_class pgc ON pgc.relname = c.table_name \
+            JOIN pg_namespace n ON n.oid = pgc.relnamespace AND n.nspname = 'public' \
+            WHERE c.table_schema = 'public' AND c.table_type = 'BASE TABLE' AND NOT pgc.relispartition \
+            ORDER BY c.table_name";
+        let result = self.execute_query(query, true)?;
+        let tables = result.split("\n").collect::<Vec<&str>>();
+
+        let mut copies = Vec::new();
+        for index in 1..tables.len() - 1 {
+            if let Some((table, partitioned)) = tables.get(index).and_then(|line| line.split_once(',')) {
+                // The rows of a partitioned table are in its partitions, ONLY would return nothing.
+                let only = if partitioned == "t" { "" } else { "ONLY " };
+                copies.push((
+                    String::from(table),
+                    format!("\\copy (SELECT * FROM {}{}) to '{}{}.csv' CSV HEADER", only, table, folder_path, table),
+                ));
+            }
+        }
+        if !large_objects.is_empty() {
+            let folder = format!("{}LargeObjects/", folder_path);
+            fs::create_dir_all(&folder)
+                .map_err(|error| format!("ERROR : when try to create the folder {}\n{}", folder, error))?;
+            for (table, column) in large_objects {
+                copies.push((format!("{}.{} (large objects)", table, column), large_objects_copy(table, column, &folder)));
+            }
+        }
+
+        if workers <= 1 {
+            // The first query of the transaction takes its snapshot.
+            let mut script = format!(
+                "\\set ON_ERROR_STOP on\n{}\nBEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY;\nSELECT {};\n",
+                SNAPSHOT_LSN, SNAPSHOT_COLUMNS
+            );
+            for (_, copy) in &copies {
+                script.push_str(&format!("{}\n", copy));
+            }
+            script.push_str("COMMIT;\n");
+            let output = self
+                .execute_temporary_script("export_tables", script)
+                .map_err(|error| format!("ERROR : when try to export the data of the tables\n{}", error))?;
+            return Snapshot::parse(&output);
+        }
+
+        let (mut coordinator, snapshot, snapshot_id) = self.export_snapshot()?;
+        let copies = copies
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, copy))| (name, (index, copy)))
+            .collect::<Vec<(String, (usize, String))>>();
+        let result = run_pool("Export the tables", &copies, workers, |(index, copy)| {
+            let script = format!(
+                "\\set ON_ERROR_STOP on\nBEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY;\nSET TRANSACTION SNAPSHOT '{}';\n{}\nCOMMIT;\n",
+                snapshot_id, copy
+            );
+            self.execute_temporary_script(&format!("export_{}", index), script)
+        });
+        // The transaction of the coordinator is ended once all the workers have imported its snapshot.
+        if let Some(mut stdin) = coordinator.stdin.take() {
+            let _ = stdin.write_all(b"COMMIT;\n");
+        }
+        let _ = coordinator.wait();
+        result?;
+        Ok(snapshot)
+    }
+
+    /// Start a ```psql``` that keeps a ```REPEATABLE READ``` transaction open and exports its snapshot, return the
+    /// process, the position of the snapshot and its identifier for ```SET TRANSACTION SNAPSHOT```.
+    fn export_snapshot(&self) -> Result<(Child, Snapshot, String), String> {
+        let snapshot_path = format!("{}", env::temp_dir().join(format!("neo4j_migrator_snapshot_{}.csv", std::process::id())).display());
+        let _ = fs::remove_file(&snapshot_path);
+        let mut coordinator = Command::new("psql")
+            .args(["-h", &self.host, "-p", &self.port, "-U", &self.username, "-d", &self.database, "-v", "ON_ERROR_STOP=1"])
+            .env("PGPASSWORD", &self.password)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|error| format!("ERROR : when try to run psql\n{}", error))?;
+        let commands = format!(
+            "{}\nBEGIN ISOLATION LEVEL REPEATABLE READ READ ONLY;\n\\copy (SELECT {}, pg_export_snapshot() AS snapshot_id) to '{}' CSV HEADER\n",
+            SNAPSHOT_LSN, SNAPSHOT_COLUMNS, snapshot_path
+        );
+        coordinator
+            .stdin
+            .as_mut()
+            .map(|stdin| stdin.write_all(commands.as_bytes()).and_then(|_| stdin.flush()))
+            .transpose()
+            .map_err(|error| format!("ERROR : when try to send the commands to psql\n{}", error))?;
+
+        // The file is complete once it contains the row of the snapshot.
+        let start = Instant::now();
+        loop {
+            if let Some(content) = fs::read_to_string(&snapshot_path).ok().filter(|content| content.lines().count() >= 2) {
+                let snapshot = Snapshot::parse(&content)?;
+                let (_, records) = parse_psql_records(&content)?;
+                let snapshot_id = records
+                    .first()
+                    .and_then(|record| record.get(3).cloned().flatten())
+                    .ok_or_else(|| format!("ERROR : The identifier of the snapshot isn't in {}", snapshot_path))?;
+                let _ = fs::remove_file(&snapshot_path);
+                return Ok((coordinator, snapshot, snapshot_id));
+            }
+            if let Ok(Some(_)) = coordinator.try_wait() {
+                let mut error = String::new();
+                if let Some(mut stderr) = coordinator.stderr.take() {
+                    let _ = stderr.read_to_string(&mut error);
                 }
-                Ok(String::from(""))
+                return Err(format!("ERROR : when try to export the snapshot of the tables\n{}", error));
+            }
+            if start.elapsed() > SNAPSHOT_TIMEOUT {
+                let _ = coordinator.kill();
+                let _ = coordinator.wait();
+                let _ = fs::remove_file(&snapshot_path);
+                return Err(format!(
+                    "ERROR : The snapshot of the tables isn't exported after {} s, the transaction may wait for a lock.",
+                    SNAPSHOT_TIMEOUT.as_secs()
+                ));
             }
-            Err(error) => Err(String::clone(error)),
+            thread::sleep(Duration::from_millis(50));
         }
     }
 }
+
+#[test]
+fn test_snapshot() {
+    let output = "set_config\n0/1A2B3C8\nBEGIN\nsnapshot_lsn,snapshot_time,snapshot\n0/1A2B3C8,2024-01-05 10:00:00.123+02,748:748:\nCOPY 12\nCOMMIT\n";
+    let snapshot = Snapshot::parse(output).unwrap();
+    assert_eq!(snapshot.lsn, "0/1A2B3C8");
+    assert_eq!(snapshot.transactions, "748:748:");
+    assert_eq!(Snapshot::from_json(&snapshot.to_json()).unwrap(), snapshot);
+    assert!(Snapshot::parse("BEGIN\nCOMMIT\n").is_err());
+}
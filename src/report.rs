@@ -0,0 +1,349 @@
+//! This module write the report of a migration run, in Markdown and in HTML : the schema of the source database,
+//! the conversions of the types (the lossy ones are flagged), the constraints and the triggers generated, the
+//! number of nodes and relationships, the duration of each stage, the warnings and the integrity findings.
+
+use std::time::{Duration, Instant};
+
+use serde_json::Value;
+
+use crate::format_to_neo4j::MetaDataFiles;
+use crate::plan::{header_properties, trigger_name};
+use crate::utils::{take_warnings, write_file};
+
+/// The number of significant digits that a ```DOUBLE``` store exactly.
+const DOUBLE_DIGITS: u32 = 15;
+
+/// The conversion of a column from PostgreSQL to Neo4j.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TypeConversion {
+    pub label: String,
+    pub column: String,
+    pub postgresql: String,
+    pub neo4j: String,
+    /// Why the conversion can lose information.
+    pub lossy: Option<String>,
+}
+
+/// What was done by a migration run, filled by the stages of the migration.
+#[derive(Debug, Default)]
+pub struct MigrationReport {
+    /// The tables (label, number of columns, number of foreign keys, estimated rows).
+    pub tables: Vec<(String, usize, usize, u64)>,
+    pub conversions: Vec<TypeConversion>,
+    pub constraints: Vec<String>,
+    pub triggers: Vec<String>,
+    pub untranslated_checks: Vec<String>,
+    /// The number of nodes by label.
+    pub nodes: Vec<(String, usize)>,
+    /// The number of relationships by type.
+    pub relationships: Vec<(String, usize)>,
+    /// The duration of each stage.
+    pub stages: Vec<(String, Duration)>,
+    pub warnings: Vec<String>,
+    pub integrity: Vec<String>,
+    pub hierarchies: Vec<String>,
+}
+
+/// A section of the report, rendered in Markdown or in HTML.
+enum Section {
+    Table(Vec<&'static str>, Vec<Vec<String>>),
+    List(Vec<String>),
+}
+
+/// Return why the conversion of the PostgreSQL type into the Neo4j type can lose information.
+fn lossy_conversion(postgresql: &str, neo4j: &str) -> Option<String> {
+    let postgresql = postgresql.trim().to_lowercase();
+    if (postgresql.starts_with("numeric") || postgresql.starts_with("decimal")) && neo4j.starts_with("DOUBLE") {
+        // A 'numeric(p,s)' whose precision fit in a DOUBLE is stored exactly.
+        let precision = postgresql
+            .split_once('(')
+            .and_then(|(_, modifiers)| modifiers.split([',', ')']).next())
+            .and_then(|precision| precision.trim().parse::<u32>().ok());
+        return match precision {
+            Some(precision) if precision <= DOUBLE_DIGITS => None,
+            _ => Some(format!("stored as a 64 bits float, only {} significant digits are kept", DOUBLE_DIGITS)),
+        };
+    }
+    if neo4j.contains("list of points") {
+        return Some(String::from("only the vertices of the geometry are kept"));
+    }
+    None
+}
+
+impl MigrationReport {
+    /// Add the duration of the stage started at ```start```.
+    pub fn stage(&mut self, name: &str, start: Instant) {
+        self.stages.push((String::from(name), start.elapsed()));
+    }
+
+    /// Add the schema of the source database and the files generated from its meta-data.
+    pub fn record_meta_data(&mut self, json_object: &Value, files: &MetaDataFiles) {
+        for table in json_object.as_array().into_iter().flatten() {
+            let Some(table_name) = table["table_name"].as_str() else {
+                continue;
+            };
+            let label = table_name.to_uppercase();
+            let columns = table["columns"].as_array().cloned().unwrap_or_default();
+            let properties = files
+                .headers
+                .iter()
+                .find(|(header_label, _)| *header_label == label)
+                .map(|(_, headers)| header_properties(headers))
+                .unwrap_or_default();
+            let mut foreign_keys = 0;
+            for column in &columns {
+                let Some(column_name) = column["column_name"].as_str() else {
+                    continue;
+                };
+                if !column["foreign_key"].is_null() {
+                    foreign_keys += 1;
+                }
+                let postgresql = column["type_definition"]
+                    .as_str()
+                    .or(column["data_type"].as_str())
+                    .unwrap_or_default();
+                let conversion = files
+                    .conversions
+                    .iter()
+                    .find(|(conversion_label, conversion_column, _)| *conversion_label == label && conversion_column == column_name)
+                    .map(|(_, _, conversion)| String::clone(conversion));
+                let neo4j = match conversion {
+                    Some(conversion) => conversion,
+                    None => match properties.iter().find(|(name, _)| name == column_name) {
+                        Some((_, data_type)) => String::clone(data_type),
+                        None if !column["foreign_key"].is_null() => String::from("relationship"),
+                        None => continue,
+                    },
+                };
+                let unsupported = format!("{}.{} (", label, column_name);
+                let lossy = if files.unsupported_columns.iter().any(|column| column.starts_with(&unsupported)) {
+                    Some(String::from("unknown type stored as STRING"))
+                } else {
+                    lossy_conversion(postgresql, &neo4j)
+                };
+                self.conversions.push(TypeConversion {
+                    label: String::clone(&label),
+                    column: String::from(column_name),
+                    postgresql: String::from(postgresql),
+                    neo4j,
+                    lossy,
+                });
+            }
+            self.tables.push((label, columns.len(), foreign_keys, table["estimated_rows"].as_u64().unwrap_or(0)));
+        }
+        self.constraints = files
+            .constraints
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect();
+        self.triggers = files.triggers.lines().filter_map(trigger_name).map(String::from).collect();
+        self.untranslated_checks = files.checks_report.clone();
+    }
+
+    /// Return the sections of the report, with their title.
+    fn sections(&self) -> Vec<(String, Section)> {
+        let lossy = self.conversions.iter().filter(|conversion| conversion.lossy.is_some()).count();
+        vec![
+            (
+                format!("Source schema : {} tables", self.tables.len()),
+                Section::Table(
+                    vec!["Table", "Columns", "Foreign keys", "Estimated rows"],
+                    self.tables
+                        .iter()
+                        .map(|(label, columns, foreign_keys, rows)| {
+                            vec![String::clone(label), columns.to_string(), foreign_keys.to_string(), rows.to_string()]
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                format!("Type conversions : {} lossy", lossy),
+                Section::Table(
+                    vec!["Column", "PostgreSQL", "Neo4j", "Lossy"],
+                    self.conversions
+                        .iter()
+                        .map(|conversion| {
+                            vec![
+                                format!("{}.{}", conversion.label, conversion.column),
+                                String::clone(&conversion.postgresql),
+                                String::clone(&conversion.neo4j),
+                                conversion.lossy.clone().unwrap_or_default(),
+                            ]
+                        })
+                        .collect(),
+                ),
+            ),
+            (format!("Constraints and indexes : {}", self.constraints.len()), Section::List(self.constraints.clone())),
+            (format!("Triggers : {}", self.triggers.len()), Section::List(self.triggers.clone())),
+            (
+                format!("Untranslated CHECK constraints : {}", self.untranslated_checks.len()),
+                Section::List(self.untranslated_checks.clone()),
+            ),
+            (
+                format!("Nodes : {}", self.nodes.iter().map(|(_, count)| count).sum::<usize>()),
+                Section::Table(
+                    vec!["Label", "Nodes"],
+                    self.nodes.iter().map(|(label, count)| vec![String::clone(label), count.to_string()]).collect(),
+                ),
+            ),
+            (
+                format!("Relationships : {}", self.relationships.iter().map(|(_, count)| count).sum::<usize>()),
+                Section::Table(
+                    vec!["Type", "Relationships"],
+                    self.relationships
+                        .iter()
+                        .map(|(relationship_type, count)| vec![String::clone(relationship_type), count.to_string()])
+                        .collect(),
+                ),
+            ),
+            (
+                format!("Timings : {:.3} s", self.stages.iter().map(|(_, duration)| duration.as_secs_f64()).sum::<f64>()),
+                Section::Table(
+                    vec!["Stage", "Duration"],
+                    self.stages
+                        .iter()
+                        .map(|(stage, duration)| vec![String::clone(stage), format!("{:.3} s", duration.as_secs_f64())])
+                        .collect(),
+                ),
+            ),
+            (format!("Warnings : {}", self.warnings.len()), Section::List(self.warnings.clone())),
+            (format!("Referential integrity : {} foreign keys", self.integrity.len()), Section::List(self.integrity.clone())),
+            (format!("Hierarchies : {} problems", self.hierarchies.len()), Section::List(self.hierarchies.clone())),
+        ]
+    }
+
+    /// Return the report in Markdown.
+    pub fn to_markdown(&self) -> String {
+        let cell = |value: &str| value.replace('|', "\\|").replace('\n', " ");
+        let mut markdown = String::from("# Migration report\n");
+        for (title, section) in self.sections() {
+            markdown.push_str(&format!("\n## {}\n\n", title));
+            match section {
+                Section::Table(_, rows) if rows.is_empty() => markdown.push_str("None.\n"),
+                Section::List(items) if items.is_empty() => markdown.push_str("None.\n"),
+                Section::Table(headers, rows) => {
+                    markdown.push_str(&format!("| {} |\n", headers.join(" | ")));
+                    markdown.push_str(&format!("|{}\n", " --- |".repeat(headers.len())));
+                    for row in rows {
+                        markdown.push_str(&format!("| {} |\n", row.iter().map(|value| cell(value)).collect::<Vec<String>>().join(" | ")));
+                    }
+                }
+                Section::List(items) => {
+                    for item in items {
+                        markdown.push_str(&format!("- {}\n", cell(&item)));
+                    }
+                }
+            }
+        }
+        markdown
+    }
+
+    /// Return the report in a standalone HTML page.
+    pub fn to_html(&self) -> String {
+        let escape = |value: &str| {
+            value
+                .replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let mut html = String::from(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Migration report</title>\n<style>\n\
+             body { font-family: sans-serif; margin: 2em; }\n\
+             table { border-collapse: collapse; }\n\
+             th, td { border: 1px solid #ccc; padding: 4px 8px; text-align: left; }\n\
+             </style>\n</head>\n<body>\n<h1>Migration report</h1>\n",
+        );
+        for (title, section) in self.sections() {
+            html.push_str(&format!("<h2>{}</h2>\n", escape(&title)));
+            match section {
+                Section::Table(_, rows) if rows.is_empty() => html.push_str("<p>None.</p>\n"),
+                Section::List(items) if items.is_empty() => html.push_str("<p>None.</p>\n"),
+                Section::Table(headers, rows) => {
+                    html.push_str("<table>\n<tr>");
+                    for header in headers {
+                        html.push_str(&format!("<th>{}</th>", header));
+                    }
+                    html.push_str("</tr>\n");
+                    for row in rows {
+                        html.push_str("<tr>");
+                        for value in row {
+                            html.push_str(&format!("<td>{}</td>", escape(&value)));
+                        }
+                        html.push_str("</tr>\n");
+                    }
+                    html.push_str("</table>\n");
+                }
+                Section::List(items) => {
+                    html.push_str("<ul>\n");
+                    for item in items {
+                        html.push_str(&format!("<li>{}</li>\n", escape(&item)));
+                    }
+                    html.push_str("</ul>\n");
+                }
+            }
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Write the report in ```{path}.md``` and ```{path}.html```, with the warnings displayed since the last report.
+    pub fn write(&mut self, path: &str) -> Result<String, String> {
+        self.warnings.extend(take_warnings());
+        write_file(self.to_markdown(), &format!("{}.md", path))?;
+        write_file(self.to_html(), &format!("{}.html", path))?;
+        Ok(format!("\nSuccessfully write the migration report in {}.md and {}.html", path, path))
+    }
+}
+
+#[test]
+fn test_migration_report() {
+    use serde_json::json;
+
+    assert_eq!(lossy_conversion("numeric(10,2)", "DOUBLE"), None);
+    assert!(lossy_conversion("numeric", "DOUBLE").is_some());
+    assert!(lossy_conversion("numeric(20,4)", "DOUBLE").is_some());
+    assert_eq!(lossy_conversion("time without time zone", "LOCALTIME"), None);
+
+    let meta_data = json!([
+        {
+            "table_name": "orders",
+            "estimated_rows": 12,
+            "columns": [
+                { "column_name": "id", "type_definition": "integer", "foreign_key": null },
+                { "column_name": "total", "type_definition": "numeric", "foreign_key": null },
+                { "column_name": "shape", "type_definition": "unknown_type", "foreign_key": null },
+                { "column_name": "customer_id", "type_definition": "integer", "foreign_key": [{ "referenced_table": "customers" }] }
+            ]
+        }
+    ]);
+    let files = MetaDataFiles {
+        headers: vec![(String::from("ORDERS"), String::from(":ID;id:LONG;total:DOUBLE;shape:STRING;:LABEL\n"))],
+        constraints: String::from("CREATE CONSTRAINT orders_id IF NOT EXISTS FOR (n:ORDERS) REQUIRE n.id IS UNIQUE;\n"),
+        unsupported_columns: vec![String::from("ORDERS.shape (unknown_type)")],
+        ..Default::default()
+    };
+    let mut report = MigrationReport::default();
+    report.record_meta_data(&meta_data, &files);
+    report.nodes.push((String::from("ORDERS"), 12));
+    report.relationships.push((String::from("ORDERS_ref_CUSTOMER_ID"), 11));
+    report.warnings.push(String::from("1 values of ORDERS.note aren't <valid> JSON"));
+
+    assert_eq!(report.tables, vec![(String::from("ORDERS"), 4, 1, 12)]);
+    let lossy = report
+        .conversions
+        .iter()
+        .filter(|conversion| conversion.lossy.is_some())
+        .map(|conversion| conversion.column.as_str())
+        .collect::<Vec<&str>>();
+    assert_eq!(lossy, vec!["total", "shape"]);
+
+    let markdown = report.to_markdown();
+    assert!(markdown.contains("## Type conversions : 2 lossy\n"));
+    assert!(markdown.contains("| ORDERS.customer_id | integer | relationship |  |\n"));
+    assert!(markdown.contains("## Relationships : 11\n\n| Type | Relationships |\n| --- | --- |\n| ORDERS_ref_CUSTOMER_ID | 11 |\n"));
+    assert!(markdown.contains("## Triggers : 0\n\nNone.\n"));
+    let html = report.to_html();
+    assert!(html.contains("<li>1 values of ORDERS.note aren't &lt;valid&gt; JSON</li>"));
+}
@@ -0,0 +1,1055 @@
+//! This module migrate a Neo4j graph back into PostgreSQL, for a rollback or for a SQL copy of the graph.<br>
+//! The schema is read from the meta-data exported by the migration, so a graph produced by Neo4j-Migrator
+//! go back to its original tables, or it is introspected from the graph : a table by label, a foreign key for
+//! the relationships that have at most one end by node, a junction table for the others and for the
+//! relationships with properties. The schema is saved in a mapping that can be edited before the data are
+//! exported in CSV with APOC and loaded with ```COPY```.
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+
+use serde_json::{json, Value};
+
+use crate::categories::{category_label, CategoryColumn};
+use crate::cypher::escape_string;
+use crate::equivalence::{parse_cypher_plain, Cell};
+use crate::format_to_neo4j::{inheritance_labels, meta_data_conversions, ColumnConversion};
+use crate::json_columns::sanitize_key;
+use crate::mapping::{JsonStrategy, Mapping};
+use crate::neo4j::Neo4j;
+use crate::spatial::parse_spatial_type;
+
+/// The column that store the ```elementId``` of the nodes of the tables introspected from the graph.
+const NODE_ID_COLUMN: &str = "neo4j_id";
+
+/// A column of a table, with the Cypher expression that return its values.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReverseColumn {
+    pub name: String,
+    pub data_type: String,
+    pub not_null: bool,
+    pub unique: bool,
+    pub default: Option<String>,
+    /// The Cypher expression of the value, like ```n.`name` ```.
+    pub source: String,
+    /// The SQL expression that convert the exported text into the type of the column.
+    pub load: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReverseForeignKey {
+    pub name: String,
+    pub column: String,
+    pub referenced_table: String,
+    pub referenced_column: String,
+}
+
+/// A table and the Cypher clause that match its nodes (or its relationships for the junction tables).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReverseTable {
+    pub name: String,
+    pub source: String,
+    pub columns: Vec<ReverseColumn>,
+    pub primary_key: Vec<String>,
+    pub foreign_keys: Vec<ReverseForeignKey>,
+    /// The CHECK constraints, like ```CONSTRAINT "positive_price" CHECK (price > 0)```.
+    pub checks: Vec<String>,
+    /// The tables inherited with ```INHERITS```.
+    pub parents: Vec<String>,
+}
+
+/// The schema of the PostgreSQL database generated from the graph.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ReverseSchema {
+    /// The statements that create the enums, the composite types and the domains.
+    pub types: Vec<String>,
+    /// The sequences (name, table, column) used by the default values of the columns.
+    pub sequences: Vec<(String, String, String)>,
+    pub tables: Vec<ReverseTable>,
+}
+
+/// A row of ```db.schema.nodeTypeProperties``` : the labels, the property, its types and if it's mandatory.
+pub type NodeTypeProperty = (Vec<String>, Option<String>, Vec<String>, bool);
+
+/// The relationships of a type between two labels combinations, with the maximum number of relationships of
+/// a start node.
+pub type RelationshipStatistics = (String, Vec<String>, Vec<String>, usize);
+
+/// The types of a property of a table introspected from the graph, if it's mandatory and the number of labels
+/// combinations that have it.
+type GraphProperty = (BTreeSet<String>, bool, usize);
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+fn backtick(name: &str) -> String {
+    format!("`{}`", name.replace('`', "``"))
+}
+
+/// Return the Cypher condition on the label of the node, the nodes of the ```excluded``` labels belong to other tables.
+fn node_condition(variable: &str, label: &str, excluded: &BTreeSet<String>) -> String {
+    let mut condition = format!("{}:{}", variable, backtick(label));
+    for excluded_label in excluded {
+        condition.push_str(&format!(" AND NOT {}:{}", variable, backtick(excluded_label)));
+    }
+    condition
+}
+
+/// Return the SQL expression that convert the exported text of the column into its type.<br>
+/// APOC export the lists and the points in JSON, the binary values are in base64 or a list of bytes.
+fn load_expression(column: &str, data_type: &str, composite: bool) -> String {
+    let value = quote_identifier(column);
+    let lower_type = data_type.trim().to_lowercase();
+    let expression = if composite {
+        format!("jsonb_populate_record(NULL::{}, {}::jsonb)", data_type, value)
+    } else if lower_type.ends_with("[]") {
+        format!("ARRAY(SELECT json_array_elements_text({}::json))::{}", value, data_type)
+    } else if lower_type == "bytea" {
+        format!(
+            "CASE WHEN {value} LIKE '[%' THEN (SELECT decode(string_agg(lpad(to_hex((byte::int + 256) % 256), 2, '0'), '' ORDER BY position), 'hex') \
+             FROM json_array_elements_text({value}::json) WITH ORDINALITY AS bytes(byte, position)) ELSE decode({value}, 'base64') END",
+            value = value
+        )
+    } else if lower_type == "point" {
+        format!("point(({value}::json->>'x')::float8, ({value}::json->>'y')::float8)", value = value)
+    } else if let Some(spatial_type) = parse_spatial_type(data_type) {
+        format!(
+            "(CASE WHEN {value} LIKE '{{%' THEN ST_SetSRID(ST_MakePoint(COALESCE({value}::json->>'x', {value}::json->>'longitude')::float8, \
+             COALESCE({value}::json->>'y', {value}::json->>'latitude')::float8), \
+             CASE {value}::json->>'crs' WHEN 'wgs-84' THEN 4326 WHEN 'wgs-84-3d' THEN 4979 ELSE 0 END) \
+             WHEN ST_SRID({value}::geometry) = 0 THEN ST_SetSRID({value}::geometry, {srid}) ELSE {value}::geometry END)::{data_type}",
+            value = value,
+            srid = spatial_type.srid.unwrap_or(0),
+            data_type = data_type
+        )
+    } else if lower_type.contains("with time zone") || lower_type == "timestamptz" || lower_type == "timetz" {
+        // Neo4j add the name of the time zone after the offset, like '+01:00[Europe/Paris]'.
+        return format!("regexp_replace({}, '\\[.*\\]$', '')::{}", value, data_type);
+    } else {
+        return format!("{}::{}", value, data_type);
+    };
+    format!("CASE WHEN {} IS NULL THEN NULL ELSE {} END", value, expression)
+}
+
+/// Return the Cypher expression and the SQL load expression of a column converted by the mapping, ```None``` if
+/// its values are stored in a property or in a relationship.<br>
+/// The JSON columns flattened or exploded into nodes can't be read back exactly (their keys are sanitized and
+/// their nested values stored as text), they are refused.
+fn converted_column(
+    label: &str,
+    column_name: &str,
+    column: &Value,
+    data_type: &str,
+    conversion: Option<&ColumnConversion>,
+    mapping: &Mapping,
+) -> Result<Option<(String, String)>, String> {
+    let value = quote_identifier(column_name);
+    let source = match conversion {
+        Some(ColumnConversion::Category(CategoryColumn::Labels)) => {
+            let cases = column["enum_values"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|value| value.as_str())
+                .map(|value| format!(" WHEN n:{} THEN '{}'", backtick(&category_label(value)), escape_string(value)))
+                .collect::<String>();
+            format!("CASE{} END", cases)
+        }
+        Some(ColumnConversion::Category(CategoryColumn::Nodes { label: category, relationship_type, .. })) => format!(
+            "[(n)-[:{}]->(c:{}) | c.value][0]",
+            backtick(relationship_type),
+            backtick(category)
+        ),
+        Some(ColumnConversion::Category(CategoryColumn::Lookup { table, key_column, value_column, property: Some(property) })) => {
+            format!(
+                "[(m:{}) WHERE m.{} = n.{} | m.{}][0]",
+                backtick(table),
+                backtick(value_column),
+                backtick(property),
+                backtick(key_column)
+            )
+        }
+        // The extra label is the value of the lookup table converted by category_label.
+        Some(ColumnConversion::Category(CategoryColumn::Lookup { table, key_column, value_column, property: None })) => format!(
+            r"[(m:{}) WHERE apoc.text.regreplace(toUpper(trim(toString(m.{}))), '[^\\p{{L}}\\p{{N}}]', '_') IN labels(n) | m.{}][0]",
+            backtick(table),
+            backtick(value_column),
+            backtick(key_column)
+        ),
+        Some(ColumnConversion::Composite(fields)) => match mapping.composite_strategy(label, column_name)? {
+            JsonStrategy::String => return Ok(None),
+            JsonStrategy::Flatten => {
+                let property = |field: &str| format!("n.{}", backtick(&format!("{}_{}", column_name, sanitize_key(field))));
+                let nulls = fields
+                    .iter()
+                    .map(|(field, _)| format!("{} IS NULL", property(field)))
+                    .collect::<Vec<String>>();
+                let values = fields
+                    .iter()
+                    .map(|(field, _)| format!("{}: {}", backtick(field), property(field)))
+                    .collect::<Vec<String>>();
+                format!(
+                    "CASE WHEN {} THEN NULL ELSE apoc.convert.toJson({{{}}}) END",
+                    nulls.join(" AND "),
+                    values.join(", ")
+                )
+            }
+            JsonStrategy::Nodes => {
+                let key = sanitize_key(column_name).to_uppercase();
+                format!(
+                    "[(n)-[:{}]->(c:{}) | apoc.convert.toJson(properties(c))][0]",
+                    backtick(&format!("HAS_{}", key)),
+                    backtick(&format!("{}_{}", label, key))
+                )
+            }
+        },
+        Some(ColumnConversion::Binary(binary)) => {
+            let (source, mut load) = if binary.storage.is_external() {
+                // The values stored in files are read by PostgreSQL from their path.
+                (
+                    format!(
+                        "CASE WHEN n.{column} IS NULL AND n.{path} IS NOT NULL THEN 'file:' + n.{path} ELSE n.{column} END",
+                        column = backtick(column_name),
+                        path = backtick(&format!("{}_path", column_name))
+                    ),
+                    format!(
+                        "CASE WHEN {value} LIKE 'file:%' THEN pg_read_binary_file(substr({value}, 6)) ELSE {bytes} END",
+                        value = value,
+                        bytes = load_expression(column_name, "bytea", false)
+                    ),
+                )
+            } else {
+                (format!("n.{}", backtick(column_name)), load_expression(column_name, "bytea", false))
+            };
+            if binary.large_object {
+                load = format!(
+                    "CASE WHEN {} IS NULL THEN NULL ELSE lo_from_bytea(0, {})::{} END",
+                    value, load, data_type
+                );
+            }
+            return Ok(Some((source, load)));
+        }
+        Some(ColumnConversion::Spatial(_)) | Some(ColumnConversion::Hierarchy(_)) => return Ok(None),
+        None => match mapping.json_strategy(label, column_name)? {
+            JsonStrategy::String => return Ok(None),
+            strategy => {
+                return Err(format!(
+                    "ERROR : The column {}.{} was migrated with the JSON strategy \"{}\", its values can't be read back exactly. \
+                     Migrate it with the strategy \"string\" or use --from-graph.",
+                    label.to_lowercase(),
+                    column_name,
+                    if strategy == JsonStrategy::Flatten { "flatten" } else { "nodes" }
+                ))
+            }
+        },
+    };
+    Ok(Some((source, load_expression(column_name, data_type, column["composite_fields"].is_array()))))
+}
+
+/// Convert the types of a Neo4j property (like ```Long``` or ```StringArray```) into a PostgreSQL type.<br>
+/// The properties that have several types are stored as ```text```.
+pub fn convert_neo4j_type(property_types: &[String]) -> String {
+    let [property_type] = property_types else {
+        return String::from("text");
+    };
+    let (element_type, array) = match property_type.strip_suffix("Array") {
+        Some("Byte") => return String::from("bytea"),
+        Some(element_type) => (element_type, "[]"),
+        None => (property_type.as_str(), ""),
+    };
+    let data_type = match element_type {
+        "Long" | "Integer" => "bigint",
+        "Double" | "Float" => "double precision",
+        "Boolean" => "boolean",
+        "Date" => "date",
+        "LocalTime" => "time",
+        "Time" => "time with time zone",
+        "LocalDateTime" => "timestamp",
+        "DateTime" => "timestamp with time zone",
+        "Duration" => "interval",
+        "Point" if array.is_empty() => "point",
+        _ => "text",
+    };
+    format!("{}{}", data_type, array)
+}
+
+/// Return the statement that create the enum, the domain or the composite type of the column.
+fn type_statement(column: &Value, data_type: &str) -> Option<String> {
+    let quote_literal = |value: &str| format!("'{}'", value.replace('\'', "''"));
+    if let Some(values) = column["enum_values"].as_array() {
+        let values = values.iter().filter_map(|value| value.as_str()).map(quote_literal).collect::<Vec<String>>();
+        return Some(format!("CREATE TYPE {} AS ENUM ({});", data_type, values.join(", ")));
+    }
+    if let Some(base_type) = column["domain"]["base_type"].as_str() {
+        let mut statement = format!("CREATE DOMAIN {} AS {}", data_type, base_type);
+        for check in column["domain"]["check_constraints"].as_array().into_iter().flatten() {
+            if let (Some(name), Some(definition)) = (check["constraint_name"].as_str(), check["definition"].as_str()) {
+                statement.push_str(&format!(" CONSTRAINT {} {}", quote_identifier(name), definition));
+            }
+        }
+        statement.push(';');
+        return Some(statement);
+    }
+    let fields = column["composite_fields"].as_array()?;
+    let fields = fields
+        .iter()
+        .filter_map(|field| Some(format!("{} {}", quote_identifier(field["name"].as_str()?), field["type"].as_str()?)))
+        .collect::<Vec<String>>();
+    Some(format!("CREATE TYPE {} AS ({});", data_type, fields.join(", ")))
+}
+
+/// Generate the schema from the meta-data exported by the migration : the tables, their types and constraints
+/// are the original ones, the foreign keys are read from the relationships chosen in the ```mapping```.
+pub fn schema_from_meta_data(meta_data: &Value, mapping: &Mapping) -> Result<ReverseSchema, String> {
+    let tables = meta_data
+        .as_array()
+        .ok_or_else(|| format!("ERROR : The meta-data need to be an array of tables, found :\n{}", meta_data))?;
+    let ancestors = inheritance_labels(tables);
+    let conversions = meta_data_conversions(meta_data, mapping)?;
+    let mut schema = ReverseSchema::default();
+    let mut types = HashSet::new();
+    for table in tables {
+        let name = table["table_name"]
+            .as_str()
+            .ok_or_else(|| format!("Error when try to get the 'table_name' field in {}", table))?;
+        let label = name.to_uppercase();
+        // The nodes of the inherited tables also have the labels of their parents.
+        let children = ancestors
+            .iter()
+            .filter(|(_, parents)| parents.contains(&label))
+            .map(|(child, _)| String::clone(child))
+            .collect::<BTreeSet<String>>();
+        let mut reverse_table = ReverseTable {
+            name: String::from(name),
+            source: format!("MATCH (n) WHERE {}", node_condition("n", &label, &children)),
+            parents: table["parent_tables"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|parent| parent.as_str().map(String::from))
+                .collect(),
+            ..Default::default()
+        };
+        for check in table["check_constraints"].as_array().into_iter().flatten() {
+            if let (Some(constraint_name), Some(definition)) = (check["constraint_name"].as_str(), check["definition"].as_str()) {
+                reverse_table.checks.push(format!("CONSTRAINT {} {}", quote_identifier(constraint_name), definition));
+            }
+        }
+        for column in table["columns"].as_array().into_iter().flatten() {
+            let column_name = column["column_name"]
+                .as_str()
+                .ok_or_else(|| format!("Error when try to get the 'column_name' field in {}", column))?;
+            let data_type = column["type_definition"]
+                .as_str()
+                .or(column["data_type"].as_str())
+                .ok_or_else(|| format!("Error when try to get the 'type_definition' field in {}", column))?;
+            if let Some(statement) = type_statement(column, data_type) {
+                if types.insert(String::from(data_type)) {
+                    schema.types.push(statement);
+                }
+            }
+            let default = column["column_default"].as_str().map(String::from);
+            if let Some(sequence) = default
+                .as_deref()
+                .and_then(|default| default.strip_prefix("nextval('"))
+                .and_then(|default| default.split_once("'::regclass)"))
+                .map(|(sequence, _)| sequence.replace("''", "'"))
+            {
+                schema.sequences.push((sequence, String::from(name), String::from(column_name)));
+            }
+            if column["primary_key"].as_bool() == Some(true) {
+                reverse_table.primary_key.push(String::from(column_name));
+            }
+            let source = match column["foreign_key"].as_array().and_then(|foreign_keys| foreign_keys.first()) {
+                Some(foreign_key) => {
+                    let (Some(referenced_table), Some(referenced_column)) =
+                        (foreign_key["referenced_table"].as_str(), foreign_key["referenced_column"].as_str())
+                    else {
+                        return Err(format!("Error when try to get the referenced column in {}", foreign_key));
+                    };
+                    let relationship_type = mapping
+                        .relationship_type(&label, column_name)?
+                        .unwrap_or_else(|| format!("{}_ref_{}", label, column_name.to_uppercase()));
+                    reverse_table.foreign_keys.push(ReverseForeignKey {
+                        name: foreign_key["constraint_name"]
+                            .as_str()
+                            .map(String::from)
+                            .unwrap_or_else(|| format!("{}_{}_fkey", name, column_name)),
+                        column: String::from(column_name),
+                        referenced_table: String::from(referenced_table),
+                        referenced_column: String::from(referenced_column),
+                    });
+                    format!(
+                        "[(n)-[:{}]->(m:{}) | m.{}][0]",
+                        backtick(&relationship_type),
+                        backtick(&referenced_table.to_uppercase()),
+                        backtick(referenced_column)
+                    )
+                }
+                None => format!("n.{}", backtick(column_name)),
+            };
+            // The columns converted by the mapping are read from their labels, nodes, properties or files.
+            let conversion = conversions
+                .get(&label)
+                .and_then(|label_conversions| label_conversions.iter().find(|(name, _)| name == column_name))
+                .map(|(_, conversion)| conversion);
+            let (source, load) = match converted_column(&label, column_name, column, data_type, conversion, mapping)? {
+                Some(converted) => converted,
+                None => (source, load_expression(column_name, data_type, column["composite_fields"].is_array())),
+            };
+            reverse_table.columns.push(ReverseColumn {
+                name: String::from(column_name),
+                data_type: String::from(data_type),
+                not_null: column["is_nullable"].as_str() == Some("NO"),
+                unique: column["is_unique"].as_bool() == Some(true),
+                default,
+                source,
+                load,
+            });
+        }
+        schema.tables.push(reverse_table);
+    }
+    Ok(schema)
+}
+
+/// Return the label of the table of the nodes that have the ```labels```.<br>
+/// A label is the parent of another one when all its nodes have the label of the parent, the nodes belong to
+/// the table of their most specific label.
+fn table_label(labels: &[String], combinations: &BTreeSet<Vec<String>>) -> String {
+    let is_parent = |parent: &String, child: &String| {
+        combinations.iter().filter(|labels| labels.contains(child)).all(|labels| labels.contains(parent))
+            && combinations.iter().any(|labels| labels.contains(parent) && !labels.contains(child))
+    };
+    labels
+        .iter()
+        .find(|label| !labels.iter().any(|other| other != *label && is_parent(label, other)))
+        .or(labels.first())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// Generate the schema from the introspection of the graph : a table by label with the ```elementId``` of the
+/// nodes as primary key, a foreign key for the relationships without properties that have at most one end by
+/// node, and a junction table for the other relationships.
+pub fn schema_from_graph(
+    node_types: &[NodeTypeProperty],
+    relationships: &[RelationshipStatistics],
+    relationship_properties: &[(String, String, Vec<String>)],
+) -> ReverseSchema {
+    let sorted = |labels: &Vec<String>| {
+        let mut labels = labels.clone();
+        labels.sort();
+        labels
+    };
+    let combinations = node_types
+        .iter()
+        .map(|(labels, _, _, _)| sorted(labels))
+        .chain(relationships.iter().flat_map(|(_, start, end, _)| [sorted(start), sorted(end)]))
+        .filter(|labels| !labels.is_empty())
+        .collect::<BTreeSet<Vec<String>>>();
+
+    // The labels combinations of each table.
+    let mut tables: BTreeMap<String, BTreeSet<Vec<String>>> = BTreeMap::new();
+    for labels in &combinations {
+        tables.entry(table_label(labels, &combinations)).or_default().insert(labels.clone());
+    }
+    let mut excluded: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+    for labels in &combinations {
+        let table = table_label(labels, &combinations);
+        for label in labels.iter().filter(|label| **label != table && tables.contains_key(*label)) {
+            excluded.entry(String::clone(label)).or_default().insert(String::clone(&table));
+        }
+    }
+    let mut properties: BTreeMap<String, BTreeMap<String, GraphProperty>> = BTreeMap::new();
+    for (labels, property, property_types, mandatory) in node_types {
+        let (Some(property), false) = (property, labels.is_empty()) else {
+            continue;
+        };
+        let table = table_label(&sorted(labels), &combinations);
+        let entry = properties
+            .entry(table)
+            .or_default()
+            .entry(String::clone(property))
+            .or_insert((BTreeSet::new(), true, 0));
+        entry.0.extend(property_types.iter().cloned());
+        entry.1 &= *mandatory;
+        entry.2 += 1;
+    }
+
+    let condition = |variable: &str, label: &String| node_condition(variable, label, excluded.get(label).unwrap_or(&BTreeSet::new()));
+    let mut schema = ReverseSchema::default();
+    for (label, labels_combinations) in &tables {
+        let mut columns = vec![ReverseColumn {
+            name: String::from(NODE_ID_COLUMN),
+            data_type: String::from("text"),
+            not_null: true,
+            source: String::from("elementId(n)"),
+            load: load_expression(NODE_ID_COLUMN, "text", false),
+            ..Default::default()
+        }];
+        for (property, (property_types, mandatory, combinations_count)) in properties.get(label).into_iter().flatten() {
+            let data_type = convert_neo4j_type(&property_types.iter().cloned().collect::<Vec<String>>());
+            columns.push(ReverseColumn {
+                name: String::clone(property),
+                not_null: *mandatory && *combinations_count == labels_combinations.len(),
+                source: format!("n.{}", backtick(property)),
+                load: load_expression(property, &data_type, false),
+                data_type,
+                ..Default::default()
+            });
+        }
+        schema.tables.push(ReverseTable {
+            name: label.to_lowercase(),
+            source: format!("MATCH (n) WHERE {}", condition("n", label)),
+            columns,
+            primary_key: vec![String::from(NODE_ID_COLUMN)],
+            ..Default::default()
+        });
+    }
+
+    // The relationships of a type between two tables.
+    let mut relationship_tables: BTreeMap<(String, String, String), usize> = BTreeMap::new();
+    for (relationship_type, start, end, maximum) in relationships {
+        let key = (
+            String::clone(relationship_type),
+            table_label(&sorted(start), &combinations),
+            table_label(&sorted(end), &combinations),
+        );
+        let entry = relationship_tables.entry(key).or_insert(0);
+        *entry = (*entry).max(*maximum);
+    }
+    for ((relationship_type, start, end), maximum) in relationship_tables {
+        let properties = relationship_properties
+            .iter()
+            .filter(|(property_type, _, _)| *property_type == relationship_type)
+            .collect::<Vec<&(String, String, Vec<String>)>>();
+        let (start_table, end_table) = (start.to_lowercase(), end.to_lowercase());
+        if properties.is_empty() && maximum <= 1 {
+            let Some(table) = schema.tables.iter_mut().find(|table| table.name == start_table) else {
+                continue;
+            };
+            let mut column = format!("{}_id", relationship_type.to_lowercase());
+            if table.columns.iter().any(|existing| existing.name == column) {
+                column = format!("{}_{}_id", relationship_type.to_lowercase(), end_table);
+            }
+            table.columns.push(ReverseColumn {
+                name: String::clone(&column),
+                data_type: String::from("text"),
+                source: format!(
+                    "[(n)-[:{}]->(m) WHERE {} | elementId(m)][0]",
+                    backtick(&relationship_type),
+                    condition("m", &end)
+                ),
+                load: load_expression(&column, "text", false),
+                ..Default::default()
+            });
+            table.foreign_keys.push(ReverseForeignKey {
+                name: format!("{}_{}_fkey", start_table, column),
+                column,
+                referenced_table: end_table,
+                referenced_column: String::from(NODE_ID_COLUMN),
+            });
+            continue;
+        }
+
+        let name = format!("{}_{}_{}", start_table, relationship_type.to_lowercase(), end_table);
+        let mut columns = Vec::new();
+        for (column, variable) in [("start_id", "a"), ("end_id", "b")] {
+            columns.push(ReverseColumn {
+                name: String::from(column),
+                data_type: String::from("text"),
+                not_null: true,
+                source: format!("elementId({})", variable),
+                load: load_expression(column, "text", false),
+                ..Default::default()
+            });
+        }
+        for (_, property, property_types) in properties {
+            let data_type = convert_neo4j_type(property_types);
+            columns.push(ReverseColumn {
+                name: String::clone(property),
+                source: format!("r.{}", backtick(property)),
+                load: load_expression(property, &data_type, false),
+                data_type,
+                ..Default::default()
+            });
+        }
+        schema.tables.push(ReverseTable {
+            name: String::clone(&name),
+            source: format!(
+                "MATCH (a)-[r:{}]->(b) WHERE {} AND {}",
+                backtick(&relationship_type),
+                condition("a", &start),
+                condition("b", &end)
+            ),
+            columns,
+            foreign_keys: [("start_id", start_table), ("end_id", end_table)]
+                .into_iter()
+                .map(|(column, referenced_table)| ReverseForeignKey {
+                    name: format!("{}_{}_fkey", name, column),
+                    column: String::from(column),
+                    referenced_table,
+                    referenced_column: String::from(NODE_ID_COLUMN),
+                })
+                .collect(),
+            ..Default::default()
+        });
+    }
+    schema
+}
+
+/// Return the strings of a Cypher list written by *cypher-shell*, like ```["Person", "Actor"]```.
+fn cypher_list(cell: &Cell) -> Vec<String> {
+    let Cell::Text(text) = cell else {
+        return Vec::new();
+    };
+    let Some(content) = text.trim().strip_prefix('[').and_then(|text| text.strip_suffix(']')) else {
+        return vec![String::clone(text)];
+    };
+    content
+        .split(", ")
+        .map(|value| value.trim().trim_matches('"'))
+        .filter(|value| !value.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+fn cell_text(cell: &Cell) -> Option<String> {
+    match cell {
+        Cell::Null => None,
+        Cell::Boolean(value) => Some(value.to_string()),
+        Cell::Number(value) => Some(value.to_string()),
+        Cell::Text(text) => Some(String::clone(text)),
+    }
+}
+
+/// Introspect the labels, the properties and the relationships of the graph to generate its schema.
+pub fn introspect_graph(db_neo4j: &Neo4j) -> Result<ReverseSchema, String> {
+    let nodes = parse_cypher_plain(&db_neo4j.execute_query(
+        "CALL db.schema.nodeTypeProperties() YIELD nodeLabels, propertyName, propertyTypes, mandatory \
+         RETURN nodeLabels, propertyName, propertyTypes, mandatory",
+    )?)?;
+    let node_types = nodes
+        .rows
+        .iter()
+        .filter(|row| row.len() == 4)
+        .map(|row| (cypher_list(&row[0]), cell_text(&row[1]), cypher_list(&row[2]), row[3] == Cell::Boolean(true)))
+        .collect::<Vec<NodeTypeProperty>>();
+
+    let relationships = parse_cypher_plain(&db_neo4j.execute_query(
+        "MATCH (a)-[r]->(b) WITH type(r) AS type, labels(a) AS start, labels(b) AS end, a, count(r) AS relationships \
+         RETURN type, start, end, max(relationships)",
+    )?)?;
+    let relationships = relationships
+        .rows
+        .iter()
+        .filter(|row| row.len() == 4)
+        .filter_map(|row| {
+            let Cell::Number(maximum) = row[3] else {
+                return None;
+            };
+            Some((cell_text(&row[0])?, cypher_list(&row[1]), cypher_list(&row[2]), maximum as usize))
+        })
+        .collect::<Vec<RelationshipStatistics>>();
+
+    let properties = parse_cypher_plain(&db_neo4j.execute_query(
+        "CALL db.schema.relTypeProperties() YIELD relType, propertyName, propertyTypes \
+         RETURN relType, propertyName, propertyTypes",
+    )?)?;
+    let relationship_properties = properties
+        .rows
+        .iter()
+        .filter(|row| row.len() == 3)
+        .filter_map(|row| {
+            // The types are written like ':`ACTED_IN`'.
+            let relationship_type = cell_text(&row[0])?;
+            let relationship_type = relationship_type.trim_start_matches(':').trim_matches('`').replace("``", "`");
+            Some((relationship_type, cell_text(&row[1])?, cypher_list(&row[2])))
+        })
+        .collect::<Vec<(String, String, Vec<String>)>>();
+
+    Ok(schema_from_graph(&node_types, &relationships, &relationship_properties))
+}
+
+/// Return the name of the CSV file of the table, in the import folder of Neo4j.
+fn export_file(table: &ReverseTable) -> String {
+    format!("reverse_{}.csv", table.name)
+}
+
+/// Return the Cypher query that return the values of the columns of the table.
+pub fn export_query(table: &ReverseTable) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| format!("{} AS {}", column.source, backtick(&column.name)))
+        .collect::<Vec<String>>();
+    format!("{} RETURN {}", table.source, columns.join(", "))
+}
+
+/// Export the values of the tables in CSV files in the import folder of Neo4j, with APOC.
+pub fn export_graph(db_neo4j: &Neo4j, schema: &ReverseSchema) -> Result<String, String> {
+    for table in &schema.tables {
+        let query = export_query(table).replace('\\', "\\\\").replace('\'', "\\'");
+        db_neo4j
+            .execute_query(&format!(
+                "CALL apoc.export.csv.query('{}', '{}', {{quotes: 'ifNeeded'}})",
+                query,
+                export_file(table)
+            ))
+            .map_err(|error| format!("ERROR : when try to export the table {}\n{}", table.name, error))?;
+    }
+    Ok(format!("Successfully export {} tables from Neo4j !", schema.tables.len()))
+}
+
+/// Generate the psql script that create the schema and load the CSV files of the ```csv_folder``` with ```COPY```.<br>
+/// The values are copied in a temporary table of text before their conversion, the foreign keys are added
+/// after the data and the sequences restart after the greatest value.
+pub fn reverse_script(schema: &ReverseSchema, csv_folder: &str) -> String {
+    let mut script = String::from("\\set ON_ERROR_STOP on\nBEGIN;\n\n");
+    for statement in &schema.types {
+        script.push_str(&format!("{}\n", statement));
+    }
+    for (sequence, _, _) in &schema.sequences {
+        script.push_str(&format!("CREATE SEQUENCE IF NOT EXISTS {};\n", sequence));
+    }
+
+    // The parents are created before the tables that inherit them.
+    let mut tables: Vec<&ReverseTable> = Vec::new();
+    while tables.len() < schema.tables.len() {
+        let created = tables.len();
+        for table in &schema.tables {
+            let is_ready = table
+                .parents
+                .iter()
+                .all(|parent| tables.iter().any(|table| table.name == *parent) || schema.tables.iter().all(|table| table.name != *parent));
+            if is_ready && tables.iter().all(|existing| existing.name != table.name) {
+                tables.push(table);
+            }
+        }
+        if tables.len() == created {
+            break;
+        }
+    }
+
+    for table in &tables {
+        let mut definitions = table
+            .columns
+            .iter()
+            .map(|column| {
+                let mut definition = format!("    {} {}", quote_identifier(&column.name), column.data_type);
+                if let Some(default) = &column.default {
+                    definition.push_str(&format!(" DEFAULT {}", default));
+                }
+                if column.not_null {
+                    definition.push_str(" NOT NULL");
+                }
+                if column.unique {
+                    definition.push_str(" UNIQUE");
+                }
+                definition
+            })
+            .collect::<Vec<String>>();
+        definitions.extend(table.checks.iter().map(|check| format!("    {}", check)));
+        if !table.primary_key.is_empty() {
+            definitions.push(format!(
+                "    PRIMARY KEY ({})",
+                table.primary_key.iter().map(|column| quote_identifier(column)).collect::<Vec<String>>().join(", ")
+            ));
+        }
+        let inherits = if table.parents.is_empty() {
+            String::new()
+        } else {
+            format!(" INHERITS ({})", table.parents.iter().map(|parent| quote_identifier(parent)).collect::<Vec<String>>().join(", "))
+        };
+        script.push_str(&format!("\nCREATE TABLE {} (\n{}\n){};\n", quote_identifier(&table.name), definitions.join(",\n"), inherits));
+    }
+
+    for table in &tables {
+        let staging = quote_identifier(&format!("reverse_{}", table.name));
+        let columns = table.columns.iter().map(|column| quote_identifier(&column.name)).collect::<Vec<String>>();
+        script.push_str(&format!(
+            "\nCREATE TEMPORARY TABLE {} ({}) ON COMMIT DROP;\n",
+            staging,
+            columns.iter().map(|column| format!("{} text", column)).collect::<Vec<String>>().join(", ")
+        ));
+        script.push_str(&format!(
+            "\\copy {} FROM '{}{}' CSV HEADER\n",
+            staging,
+            csv_folder.replace('\'', "''"),
+            export_file(table)
+        ));
+        script.push_str(&format!(
+            "INSERT INTO {} ({}) SELECT {} FROM {};\n",
+            quote_identifier(&table.name),
+            columns.join(", "),
+            table.columns.iter().map(|column| column.load.as_str()).collect::<Vec<&str>>().join(", "),
+            staging
+        ));
+    }
+
+    script.push('\n');
+    for table in &tables {
+        for foreign_key in &table.foreign_keys {
+            script.push_str(&format!(
+                "ALTER TABLE {} ADD CONSTRAINT {} FOREIGN KEY ({}) REFERENCES {} ({});\n",
+                quote_identifier(&table.name),
+                quote_identifier(&foreign_key.name),
+                quote_identifier(&foreign_key.column),
+                quote_identifier(&foreign_key.referenced_table),
+                quote_identifier(&foreign_key.referenced_column)
+            ));
+        }
+    }
+    for (sequence, table, column) in &schema.sequences {
+        script.push_str(&format!(
+            "SELECT setval('{}', COALESCE(max({}), 0) + 1, false) FROM {};\n",
+            sequence.replace('\'', "''"),
+            quote_identifier(column),
+            quote_identifier(table)
+        ));
+    }
+    script.push_str("\nCOMMIT;\n");
+    script
+}
+
+impl ReverseSchema {
+    /// Return the schema as a JSON mapping, that can be edited and read with ```ReverseSchema::from_json```.
+    pub fn to_json(&self) -> Value {
+        json!({
+            "types": self.types,
+            "sequences": self
+                .sequences
+                .iter()
+                .map(|(name, table, column)| json!({ "name": name, "table": table, "column": column }))
+                .collect::<Vec<Value>>(),
+            "tables": self.tables.iter().map(|table| json!({
+                "name": table.name,
+                "source": table.source,
+                "columns": table.columns.iter().map(|column| json!({
+                    "name": column.name,
+                    "type": column.data_type,
+                    "not_null": column.not_null,
+                    "unique": column.unique,
+                    "default": column.default,
+                    "source": column.source,
+                    "load": column.load,
+                })).collect::<Vec<Value>>(),
+                "primary_key": table.primary_key,
+                "foreign_keys": table.foreign_keys.iter().map(|foreign_key| json!({
+                    "name": foreign_key.name,
+                    "column": foreign_key.column,
+                    "referenced_table": foreign_key.referenced_table,
+                    "referenced_column": foreign_key.referenced_column,
+                })).collect::<Vec<Value>>(),
+                "checks": table.checks,
+                "parents": table.parents,
+            })).collect::<Vec<Value>>(),
+        })
+    }
+
+    /// Read the schema from a JSON mapping written by ```ReverseSchema::to_json```.
+    pub fn from_json(mapping: &Value) -> Result<Self, String> {
+        let text = |value: &Value, key: &str| {
+            value[key]
+                .as_str()
+                .map(String::from)
+                .ok_or_else(|| format!("ERROR : The field '{}' is missing in the reverse mapping :\n{}", key, value))
+        };
+        let texts = |value: &Value| value.as_array().into_iter().flatten().filter_map(|value| value.as_str().map(String::from)).collect();
+        let mut schema = ReverseSchema { types: texts(&mapping["types"]), ..Default::default() };
+        for sequence in mapping["sequences"].as_array().into_iter().flatten() {
+            schema.sequences.push((text(sequence, "name")?, text(sequence, "table")?, text(sequence, "column")?));
+        }
+        for table in mapping["tables"].as_array().into_iter().flatten() {
+            let mut reverse_table = ReverseTable {
+                name: text(table, "name")?,
+                source: text(table, "source")?,
+                primary_key: texts(&table["primary_key"]),
+                checks: texts(&table["checks"]),
+                parents: texts(&table["parents"]),
+                ..Default::default()
+            };
+            for column in table["columns"].as_array().into_iter().flatten() {
+                let name = text(column, "name")?;
+                let data_type = text(column, "type")?;
+                reverse_table.columns.push(ReverseColumn {
+                    not_null: column["not_null"].as_bool().unwrap_or(false),
+                    unique: column["unique"].as_bool().unwrap_or(false),
+                    default: column["default"].as_str().map(String::from),
+                    source: text(column, "source")?,
+                    // The conversion is generated again when the type is edited without it.
+                    load: column["load"].as_str().map(String::from).unwrap_or_else(|| load_expression(&name, &data_type, false)),
+                    name,
+                    data_type,
+                });
+            }
+            for foreign_key in table["foreign_keys"].as_array().into_iter().flatten() {
+                reverse_table.foreign_keys.push(ReverseForeignKey {
+                    name: text(foreign_key, "name")?,
+                    column: text(foreign_key, "column")?,
+                    referenced_table: text(foreign_key, "referenced_table")?,
+                    referenced_column: text(foreign_key, "referenced_column")?,
+                });
+            }
+            schema.tables.push(reverse_table);
+        }
+        Ok(schema)
+    }
+}
+
+#[test]
+fn test_reverse_schema() {
+    let meta_data = json!([
+        {
+            "table_name": "customers",
+            "columns": [
+                { "column_name": "id", "data_type": "integer", "type_definition": "integer", "is_nullable": "NO", "primary_key": true,
+                  "column_default": "nextval('customers_id_seq'::regclass)", "foreign_key": null },
+                { "column_name": "mood", "data_type": "USER-DEFINED", "udt_name": "mood", "type_definition": "mood", "enum_values": ["happy", "sad"], "foreign_key": null },
+                { "column_name": "tags", "data_type": "ARRAY", "udt_name": "text", "type_definition": "text[]", "foreign_key": null }
+            ],
+            "check_constraints": [{ "constraint_name": "id_positive", "definition": "CHECK ((id > 0))" }]
+        },
+        {
+            "table_name": "orders",
+            "columns": [
+                { "column_name": "id", "data_type": "bigint", "type_definition": "bigint", "is_nullable": "NO", "primary_key": true, "foreign_key": null },
+                { "column_name": "customer_id", "type_definition": "integer", "is_nullable": "YES",
+                  "foreign_key": [{ "constraint_name": "orders_customer_fk", "referenced_table": "customers", "referenced_column": "id" }] }
+            ]
+        },
+        {
+            "table_name": "vip_orders",
+            "parent_tables": ["orders"],
+            "columns": [{ "column_name": "id", "data_type": "bigint", "type_definition": "bigint", "is_nullable": "NO", "foreign_key": null }]
+        }
+    ]);
+    let schema = schema_from_meta_data(&meta_data, &Mapping::default()).unwrap();
+    assert_eq!(schema.types, vec![String::from("CREATE TYPE mood AS ENUM ('happy', 'sad');")]);
+    assert_eq!(schema.sequences, vec![(String::from("customers_id_seq"), String::from("customers"), String::from("id"))]);
+    assert_eq!(
+        export_query(&schema.tables[1]),
+        "MATCH (n) WHERE n:`ORDERS` AND NOT n:`VIP_ORDERS` RETURN n.`id` AS `id`, \
+         [(n)-[:`ORDERS_ref_CUSTOMER_ID`]->(m:`CUSTOMERS`) | m.`id`][0] AS `customer_id`"
+    );
+    assert_eq!(ReverseSchema::from_json(&schema.to_json()).unwrap(), schema);
+
+    let script = reverse_script(&schema, "/import/");
+    assert!(script.contains(
+        "CREATE TABLE \"customers\" (\n    \"id\" integer DEFAULT nextval('customers_id_seq'::regclass) NOT NULL,\n    \"mood\" mood,\n    \
+         \"tags\" text[],\n    CONSTRAINT \"id_positive\" CHECK ((id > 0)),\n    PRIMARY KEY (\"id\")\n);\n"
+    ));
+    assert!(script.contains("\n) INHERITS (\"orders\");\n"));
+    assert!(script.contains("\\copy \"reverse_orders\" FROM '/import/reverse_orders.csv' CSV HEADER\n"));
+    assert!(script.contains(
+        "INSERT INTO \"customers\" (\"id\", \"mood\", \"tags\") SELECT \"id\"::integer, \"mood\"::mood, \
+         CASE WHEN \"tags\" IS NULL THEN NULL ELSE ARRAY(SELECT json_array_elements_text(\"tags\"::json))::text[] END FROM \"reverse_customers\";\n"
+    ));
+    assert!(script.contains(
+        "ALTER TABLE \"orders\" ADD CONSTRAINT \"orders_customer_fk\" FOREIGN KEY (\"customer_id\") REFERENCES \"customers\" (\"id\");\n"
+    ));
+    assert!(script.contains("SELECT setval('customers_id_seq', COALESCE(max(\"id\"), 0) + 1, false) FROM \"customers\";\n"));
+
+    let labels = |labels: &[&str]| labels.iter().map(|label| String::from(*label)).collect::<Vec<String>>();
+    let node_types = vec![
+        (labels(&["Person"]), Some(String::from("name")), labels(&["String"]), true),
+        (labels(&["Actor", "Person"]), Some(String::from("name")), labels(&["String"]), true),
+        (labels(&["Actor", "Person"]), Some(String::from("awards")), labels(&["Long"]), false),
+        (labels(&["Movie"]), Some(String::from("title")), labels(&["String"]), true),
+    ];
+    let relationships = vec![
+        (String::from("ACTED_IN"), labels(&["Actor", "Person"]), labels(&["Movie"]), 3),
+        (String::from("BORN_IN"), labels(&["Person"]), labels(&["Movie"]), 1),
+    ];
+    let relationship_properties = vec![(String::from("ACTED_IN"), String::from("role"), labels(&["String"]))];
+    let schema = schema_from_graph(&node_types, &relationships, &relationship_properties);
+    let names = schema.tables.iter().map(|table| table.name.as_str()).collect::<Vec<&str>>();
+    assert_eq!(names, vec!["actor", "movie", "person", "actor_acted_in_movie"]);
+    assert_eq!(schema.tables[2].source, "MATCH (n) WHERE n:`Person` AND NOT n:`Actor`");
+    assert_eq!(
+        schema.tables[2].columns.iter().map(|column| (column.name.as_str(), column.data_type.as_str())).collect::<Vec<_>>(),
+        vec![("neo4j_id", "text"), ("name", "text"), ("born_in_id", "text")]
+    );
+    assert_eq!(
+        export_query(&schema.tables[3]),
+        "MATCH (a)-[r:`ACTED_IN`]->(b) WHERE a:`Actor` AND b:`Movie` RETURN elementId(a) AS `start_id`, elementId(b) AS `end_id`, r.`role` AS `role`"
+    );
+    assert_eq!(convert_neo4j_type(&labels(&["DateTime"])), "timestamp with time zone");
+    assert_eq!(convert_neo4j_type(&labels(&["LongArray"])), "bigint[]");
+    assert_eq!(convert_neo4j_type(&labels(&["Long", "String"])), "text");
+
+    // The columns converted by the mapping are read back from their labels, nodes, properties or files.
+    let meta_data = json!([
+        { "table_name": "statuses", "columns": [
+            { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+            { "column_name": "name", "data_type": "text", "foreign_key": null }
+        ]},
+        { "table_name": "countries", "columns": [
+            { "column_name": "code", "data_type": "text", "primary_key": true, "foreign_key": null },
+            { "column_name": "name", "data_type": "text", "foreign_key": null }
+        ]},
+        { "table_name": "orders", "columns": [
+            { "column_name": "mood", "data_type": "USER-DEFINED", "udt_name": "mood", "type_definition": "mood",
+              "enum_values": ["happy", "very sad"], "foreign_key": null },
+            { "column_name": "priority", "data_type": "USER-DEFINED", "udt_name": "priority", "type_definition": "priority",
+              "enum_values": ["low", "high"], "foreign_key": null },
+            { "column_name": "status_id", "data_type": "integer", "is_nullable": "NO",
+              "foreign_key": [{ "referenced_table": "statuses", "referenced_column": "id" }] },
+            { "column_name": "country", "data_type": "text",
+              "foreign_key": [{ "referenced_table": "countries", "referenced_column": "code" }] },
+            { "column_name": "address", "data_type": "USER-DEFINED", "udt_name": "address", "type_definition": "address",
+              "composite_fields": [{ "name": "street", "type": "text" }, { "name": "zip", "type": "text" }], "foreign_key": null },
+            { "column_name": "billing", "data_type": "USER-DEFINED", "udt_name": "address", "type_definition": "address",
+              "composite_fields": [{ "name": "street", "type": "text" }, { "name": "zip", "type": "text" }], "foreign_key": null },
+            { "column_name": "scan", "data_type": "bytea", "foreign_key": null },
+            { "column_name": "document", "data_type": "oid", "udt_name": "oid", "foreign_key": null },
+            { "column_name": "extra", "data_type": "jsonb", "foreign_key": null }
+        ]}
+    ]);
+    let mapping = Mapping::from_value(json!({
+        "tables": {
+            "statuses": { "lookup": { "strategy": "property", "column": "name" } },
+            "countries": { "lookup": { "strategy": "labels", "column": "name" } },
+            "orders": { "columns": {
+                "mood": { "enum": "labels" },
+                "priority": { "enum": "nodes" },
+                "billing": { "composite": "nodes" },
+                "scan": { "binary": "files" },
+                "document": { "large_object": true }
+            }}
+        }
+    }));
+    let schema = schema_from_meta_data(&meta_data, &mapping).unwrap();
+    let column = |name: &str| schema.tables[2].columns.iter().find(|column| column.name == name).unwrap().clone();
+    assert_eq!(column("mood").source, "CASE WHEN n:`HAPPY` THEN 'happy' WHEN n:`VERY_SAD` THEN 'very sad' END");
+    assert_eq!(column("priority").source, "[(n)-[:`ORDERS_ref_PRIORITY`]->(c:`PRIORITY`) | c.value][0]");
+    assert_eq!(column("status_id").source, "[(m:`STATUSES`) WHERE m.`name` = n.`status` | m.`id`][0]");
+    assert_eq!(
+        column("country").source,
+        r"[(m:`COUNTRIES`) WHERE apoc.text.regreplace(toUpper(trim(toString(m.`name`))), '[^\\p{L}\\p{N}]', '_') IN labels(n) | m.`code`][0]"
+    );
+    assert_eq!(
+        column("address").source,
+        "CASE WHEN n.`address_street` IS NULL AND n.`address_zip` IS NULL THEN NULL \
+         ELSE apoc.convert.toJson({`street`: n.`address_street`, `zip`: n.`address_zip`}) END"
+    );
+    assert!(column("address").load.contains("jsonb_populate_record(NULL::address, \"address\"::jsonb)"));
+    assert_eq!(
+        column("billing").source,
+        "[(n)-[:`HAS_BILLING`]->(c:`ORDERS_BILLING`) | apoc.convert.toJson(properties(c))][0]"
+    );
+    assert_eq!(
+        column("scan").source,
+        "CASE WHEN n.`scan` IS NULL AND n.`scan_path` IS NOT NULL THEN 'file:' + n.`scan_path` ELSE n.`scan` END"
+    );
+    assert!(column("scan").load.starts_with("CASE WHEN \"scan\" LIKE 'file:%' THEN pg_read_binary_file(substr(\"scan\", 6)) ELSE "));
+    assert!(column("document").load.contains("lo_from_bytea(0, CASE WHEN \"document\" IS NULL THEN NULL ELSE CASE WHEN \"document\" LIKE '[%'"));
+    assert!(column("document").load.ends_with(")::oid END"));
+    assert_eq!(column("extra").source, "n.`extra`");
+
+    for strategy in ["flatten", "nodes"] {
+        let mapping = Mapping::from_value(json!({ "tables": { "orders": { "columns": { "extra": { "json": strategy } } } } }));
+        let error = schema_from_meta_data(&meta_data, &mapping).unwrap_err();
+        assert!(error.contains("orders.extra") && error.contains(strategy));
+    }
+}
@@ -0,0 +1,422 @@
+//! This module convert the PostgreSQL ```point``` and the PostGIS ```geometry```/```geography``` values
+//! into Neo4j spatial values.<br>
+//! The values are read from the psql point format ```(x,y)```, from WKT/EWKT (```SRID=4326;POINT(2.35 48.85)```)
+//! or from EWKB hex, the format used by PostGIS to export the geometries.
+
+use crate::load_to_neo4j::ARRAY_DELIMITER;
+
+/// The SRID of the WGS-84 coordinates in 2D and in 3D.
+const SRID_WGS84: u32 = 4326;
+const SRID_WGS84_3D: u32 = 4979;
+
+/// How the values of a spatial column are converted.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SpatialColumn {
+    /// A ```POINT``` property, the SRID of the column is used when the values don't contain one.
+    Point { srid: Option<u32> },
+    /// The other geometries are stored in WKT, or as a ```POINT[]``` property when ```as_points``` is true.
+    Geometry { srid: Option<u32>, as_points: bool },
+}
+
+/// The spatial type of a column, read in the type definition of PostgreSQL (like ```geometry(Point,4326)```).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SpatialType {
+    /// The geometry type in uppercase (like ```POINT``` or ```POLYGONZ```), ```None``` for the unconstrained columns.
+    pub subtype: Option<String>,
+    pub srid: Option<u32>,
+}
+
+impl SpatialType {
+    pub fn is_point(&self) -> bool {
+        self.subtype.as_deref().is_some_and(|subtype| subtype.starts_with("POINT"))
+    }
+}
+
+/// Parse the PostgreSQL type definition, return ```None``` if it isn't a spatial type.
+pub fn parse_spatial_type(type_definition: &str) -> Option<SpatialType> {
+    let type_definition = type_definition.trim().to_uppercase();
+    if type_definition == "POINT" {
+        return Some(SpatialType { subtype: Some(String::from("POINT")), srid: None });
+    }
+    let is_geography = type_definition.starts_with("GEOGRAPHY");
+    if !is_geography && !type_definition.starts_with("GEOMETRY") {
+        return None;
+    }
+    let default_srid = if is_geography { Some(SRID_WGS84) } else { None };
+    let modifiers = match (type_definition.find('('), type_definition.rfind(')')) {
+        (Some(start), Some(end)) if start < end => &type_definition[start + 1..end],
+        _ => return Some(SpatialType { subtype: None, srid: default_srid }),
+    };
+    let mut modifiers = modifiers.split(',').map(|modifier| modifier.trim());
+    let subtype = modifiers
+        .next()
+        .filter(|subtype| *subtype != "GEOMETRY")
+        .map(String::from);
+    let srid = modifiers
+        .next()
+        .and_then(|srid| srid.parse::<u32>().ok())
+        .filter(|srid| *srid != 0)
+        .or(default_srid);
+    Some(SpatialType { subtype, srid })
+}
+
+/// Return the conversion of the spatial column, ```as_points``` is used for the non-point geometries.
+pub fn spatial_column(type_definition: &str, as_points: bool) -> Option<SpatialColumn> {
+    let spatial_type = parse_spatial_type(type_definition)?;
+    if spatial_type.is_point() {
+        Some(SpatialColumn::Point { srid: spatial_type.srid })
+    } else {
+        Some(SpatialColumn::Geometry { srid: spatial_type.srid, as_points })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Geometry {
+    /// The coordinates of the point, empty for ```POINT EMPTY```.
+    Point(Vec<f64>),
+    LineString(Vec<Vec<f64>>),
+    Polygon(Vec<Vec<Vec<f64>>>),
+    /// The multi geometries and the geometry collections, with their WKT name.
+    Collection(&'static str, Vec<Geometry>),
+    /// A geometry read in WKT that is kept as is, with all its coordinates.
+    Text(String, Vec<Vec<f64>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct ParsedGeometry {
+    srid: Option<u32>,
+    has_z: bool,
+    has_m: bool,
+    geometry: Geometry,
+}
+
+impl ParsedGeometry {
+    /// The dimension written after the name of the geometry in WKT.
+    fn dimension(&self) -> &'static str {
+        match (self.has_z, self.has_m) {
+            (true, true) => "ZM",
+            (true, false) => "Z",
+            (false, true) => "M",
+            (false, false) => "",
+        }
+    }
+}
+
+/// Read the EWKB bytes of a geometry.
+struct WkbReader {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl WkbReader {
+    fn read_bytes<const N: usize>(&mut self, little_endian: bool) -> Option<[u8; N]> {
+        let mut bytes: [u8; N] = self.bytes.get(self.position..self.position + N)?.try_into().ok()?;
+        self.position += N;
+        if !little_endian {
+            bytes.reverse();
+        }
+        Some(bytes)
+    }
+
+    fn read_u32(&mut self, little_endian: bool) -> Option<u32> {
+        self.read_bytes::<4>(little_endian).map(u32::from_le_bytes)
+    }
+
+    fn read_coordinates(&mut self, little_endian: bool, dimensions: usize) -> Option<Vec<f64>> {
+        (0..dimensions)
+            .map(|_| self.read_bytes::<8>(little_endian).map(f64::from_le_bytes))
+            .collect()
+    }
+
+    fn read_coordinates_list(&mut self, little_endian: bool, dimensions: usize) -> Option<Vec<Vec<f64>>> {
+        let count = self.read_u32(little_endian)?;
+        (0..count).map(|_| self.read_coordinates(little_endian, dimensions)).collect()
+    }
+
+    fn read_geometry(&mut self) -> Option<ParsedGeometry> {
+        let little_endian = match self.read_bytes::<1>(true)?[0] {
+            0 => false,
+            1 => true,
+            _ => return None,
+        };
+        let type_code = self.read_u32(little_endian)?;
+        let mut has_z = type_code & 0x8000_0000 != 0;
+        let mut has_m = type_code & 0x4000_0000 != 0;
+        let srid = if type_code & 0x2000_0000 != 0 {
+            Some(self.read_u32(little_endian)?)
+        } else {
+            None
+        };
+        // The ISO WKB add 1000 (Z), 2000 (M) or 3000 (ZM) to the type of the geometry.
+        let mut base_type = type_code & 0x0FFF_FFFF;
+        match base_type / 1000 {
+            1 => has_z = true,
+            2 => has_m = true,
+            3 => (has_z, has_m) = (true, true),
+            _ => {}
+        }
+        base_type %= 1000;
+        let dimensions = 2 + has_z as usize + has_m as usize;
+
+        let geometry = match base_type {
+            1 => {
+                let coordinates = self.read_coordinates(little_endian, dimensions)?;
+                if coordinates.iter().all(|value| value.is_nan()) {
+                    Geometry::Point(Vec::new())
+                } else {
+                    Geometry::Point(coordinates)
+                }
+            }
+            2 => Geometry::LineString(self.read_coordinates_list(little_endian, dimensions)?),
+            3 => {
+                let count = self.read_u32(little_endian)?;
+                let rings = (0..count)
+                    .map(|_| self.read_coordinates_list(little_endian, dimensions))
+                    .collect::<Option<Vec<Vec<Vec<f64>>>>>()?;
+                Geometry::Polygon(rings)
+            }
+            4..=7 => {
+                let name = ["MULTIPOINT", "MULTILINESTRING", "MULTIPOLYGON", "GEOMETRYCOLLECTION"][base_type as usize - 4];
+                let count = self.read_u32(little_endian)?;
+                let members = (0..count)
+                    .map(|_| self.read_geometry().map(|member| member.geometry))
+                    .collect::<Option<Vec<Geometry>>>()?;
+                Geometry::Collection(name, members)
+            }
+            _ => return None,
+        };
+        Some(ParsedGeometry { srid, has_z, has_m, geometry })
+    }
+}
+
+/// Parse the coordinates of a WKT geometry, like ```(1 2, 3 4)```.
+fn parse_wkt_coordinates(text: &str) -> Option<Vec<Vec<f64>>> {
+    text.split([',', '(', ')'])
+        .map(|coordinates| coordinates.trim())
+        .filter(|coordinates| !coordinates.is_empty())
+        .map(|coordinates| {
+            coordinates
+                .split_whitespace()
+                .map(|value| value.parse::<f64>().ok())
+                .collect::<Option<Vec<f64>>>()
+        })
+        .collect()
+}
+
+/// Parse the value of a spatial column.
+fn parse_geometry(value: &str) -> Option<ParsedGeometry> {
+    let value = value.trim();
+    // The PostgreSQL point : (x,y)
+    if value.starts_with('(') {
+        let coordinates = value
+            .trim_start_matches('(')
+            .trim_end_matches(')')
+            .split(',')
+            .map(|value| value.trim().parse::<f64>().ok())
+            .collect::<Option<Vec<f64>>>()?;
+        return Some(ParsedGeometry { srid: None, has_z: false, has_m: false, geometry: Geometry::Point(coordinates) });
+    }
+    // The EWKB hex
+    if value.len().is_multiple_of(2) && !value.is_empty() && value.chars().all(|c| c.is_ascii_hexdigit()) {
+        let bytes = (0..value.len())
+            .step_by(2)
+            .map(|index| u8::from_str_radix(&value[index..index + 2], 16).ok())
+            .collect::<Option<Vec<u8>>>()?;
+        let mut reader = WkbReader { bytes, position: 0 };
+        return reader.read_geometry();
+    }
+    // The WKT, with the SRID for the EWKT : SRID=4326;POINT(1 2)
+    let (srid, wkt) = match value.strip_prefix("SRID=").and_then(|value| value.split_once(';')) {
+        Some((srid, wkt)) => (Some(srid.trim().parse::<u32>().ok()?), wkt.trim()),
+        None => (None, value),
+    };
+    let (name, body) = match wkt.find('(') {
+        Some(index) => (wkt[..index].trim().to_uppercase(), &wkt[index..]),
+        None => (wkt.to_uppercase(), ""),
+    };
+    // The dimension follows the name of the geometry : POINT Z, POINTZM...
+    let dimension = &name[name.trim_end_matches(['Z', 'M', ' ']).len()..];
+    let (has_z, has_m) = (dimension.contains('Z'), dimension.contains('M'));
+    let coordinates = parse_wkt_coordinates(body)?;
+    let geometry = if name.starts_with("POINT") {
+        Geometry::Point(coordinates.into_iter().next().unwrap_or_default())
+    } else {
+        Geometry::Text(String::from(wkt), coordinates)
+    };
+    Some(ParsedGeometry { srid, has_z, has_m, geometry })
+}
+
+fn coordinates_wkt(coordinates: &[f64]) -> String {
+    coordinates
+        .iter()
+        .map(|value| value.to_string())
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn coordinates_list_wkt(coordinates_list: &[Vec<f64>]) -> String {
+    coordinates_list
+        .iter()
+        .map(|coordinates| coordinates_wkt(coordinates))
+        .collect::<Vec<String>>()
+        .join(",")
+}
+
+/// Return the text between the parenthesis of the WKT of the geometry.
+fn geometry_body(geometry: &Geometry, dimension: &str) -> String {
+    match geometry {
+        Geometry::Point(coordinates) => coordinates_wkt(coordinates),
+        Geometry::LineString(points) => coordinates_list_wkt(points),
+        Geometry::Polygon(rings) => rings
+            .iter()
+            .map(|ring| format!("({})", coordinates_list_wkt(ring)))
+            .collect::<Vec<String>>()
+            .join(","),
+        Geometry::Collection(name, members) => members
+            .iter()
+            .map(|member| match *name {
+                "GEOMETRYCOLLECTION" => geometry_wkt(member, dimension),
+                _ => format!("({})", geometry_body(member, dimension)),
+            })
+            .collect::<Vec<String>>()
+            .join(","),
+        Geometry::Text(wkt, _) => String::clone(wkt),
+    }
+}
+
+/// Write the geometry in WKT, like ```ST_AsText``` of PostGIS.
+fn geometry_wkt(geometry: &Geometry, dimension: &str) -> String {
+    let name = match geometry {
+        Geometry::Point(coordinates) if coordinates.is_empty() => return String::from("POINT EMPTY"),
+        Geometry::Point(_) => "POINT",
+        Geometry::LineString(_) => "LINESTRING",
+        Geometry::Polygon(_) => "POLYGON",
+        Geometry::Collection(name, _) => name,
+        Geometry::Text(wkt, _) => return String::clone(wkt),
+    };
+    if dimension.is_empty() {
+        format!("{}({})", name, geometry_body(geometry, dimension))
+    } else {
+        format!("{} {} ({})", name, dimension, geometry_body(geometry, dimension))
+    }
+}
+
+/// Return all the coordinates of the geometry.
+fn geometry_points(geometry: &Geometry) -> Vec<Vec<f64>> {
+    match geometry {
+        Geometry::Point(coordinates) if coordinates.is_empty() => Vec::new(),
+        Geometry::Point(coordinates) => vec![coordinates.clone()],
+        Geometry::LineString(points) => points.clone(),
+        Geometry::Polygon(rings) => rings.concat(),
+        Geometry::Collection(_, members) => members.iter().flat_map(geometry_points).collect(),
+        Geometry::Text(_, coordinates) => coordinates.clone(),
+    }
+}
+
+/// Write the coordinates as a Neo4j point, with the CRS deduced from the SRID.<br>
+/// Return the point and true if the SRID isn't supported by Neo4j : the coordinates are kept as cartesian.
+fn neo4j_point(coordinates: &[f64], has_z: bool, srid: Option<u32>) -> (String, bool) {
+    let (x, y) = (coordinates[0], coordinates[1]);
+    let z = if has_z { coordinates.get(2) } else { None };
+    match (srid, z) {
+        (Some(SRID_WGS84 | SRID_WGS84_3D), Some(z)) => (format!("{{crs:WGS-84-3D,longitude:{},latitude:{},height:{}}}", x, y, z), false),
+        (Some(SRID_WGS84 | SRID_WGS84_3D), None) => (format!("{{crs:WGS-84,longitude:{},latitude:{}}}", x, y), false),
+        (_, Some(z)) => (format!("{{crs:cartesian-3D,x:{},y:{},z:{}}}", x, y, z), srid.is_some()),
+        (_, None) => (format!("{{crs:cartesian,x:{},y:{}}}", x, y), srid.is_some()),
+    }
+}
+
+/// Convert the value of the spatial column into the format expected by neo4j-admin.<br>
+/// Return the value and true if it was altered : the values that aren't points in a ```POINT``` column are
+/// removed, the structure of the geometries stored as ```POINT[]``` is lost and the unsupported SRID are
+/// stored as cartesian coordinates.
+pub fn normalize_spatial(value: &str, column: &SpatialColumn) -> (Option<String>, bool) {
+    let parsed = match parse_geometry(value) {
+        Some(parsed) => parsed,
+        None => return (None, true),
+    };
+    match column {
+        SpatialColumn::Point { srid } => match &parsed.geometry {
+            Geometry::Point(coordinates) if coordinates.len() >= 2 => {
+                let (point, is_lossy) = neo4j_point(coordinates, parsed.has_z, parsed.srid.or(*srid));
+                (Some(point), is_lossy)
+            }
+            Geometry::Point(_) => (None, false),
+            _ => (None, true),
+        },
+        SpatialColumn::Geometry { as_points: false, .. } => {
+            (Some(geometry_wkt(&parsed.geometry, parsed.dimension())), false)
+        }
+        SpatialColumn::Geometry { srid, as_points: true } => {
+            let mut is_lossy = match &parsed.geometry {
+                Geometry::Polygon(_) | Geometry::Text(..) => true,
+                Geometry::Collection(name, _) => *name != "MULTIPOINT",
+                _ => false,
+            };
+            let points = geometry_points(&parsed.geometry)
+                .iter()
+                .filter(|coordinates| coordinates.len() >= 2)
+                .map(|coordinates| {
+                    let (point, is_lossy_point) = neo4j_point(coordinates, parsed.has_z, parsed.srid.or(*srid));
+                    is_lossy |= is_lossy_point;
+                    point
+                })
+                .collect::<Vec<String>>();
+            if points.is_empty() {
+                (None, is_lossy)
+            } else {
+                (Some(points.join(&ARRAY_DELIMITER.to_string())), is_lossy)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_parse_spatial_type() {
+    assert_eq!(
+        parse_spatial_type("geometry(Point,4326)"),
+        Some(SpatialType { subtype: Some(String::from("POINT")), srid: Some(4326) })
+    );
+    assert_eq!(parse_spatial_type("geography"), Some(SpatialType { subtype: None, srid: Some(4326) }));
+    assert_eq!(
+        parse_spatial_type("geometry(PolygonZ)"),
+        Some(SpatialType { subtype: Some(String::from("POLYGONZ")), srid: None })
+    );
+    assert!(parse_spatial_type("point").unwrap().is_point());
+    assert_eq!(parse_spatial_type("text"), None);
+}
+
+#[test]
+fn test_normalize_spatial() {
+    let point = SpatialColumn::Point { srid: None };
+    assert_eq!(normalize_spatial("(1.5,-2)", &point), (Some(String::from("{crs:cartesian,x:1.5,y:-2}")), false));
+    // POINT(2.35 48.85) with the SRID 4326 in EWKB
+    assert_eq!(
+        normalize_spatial("0101000020E6100000CDCCCCCCCCCC0240CDCCCCCCCC6C4840", &point),
+        (Some(String::from("{crs:WGS-84,longitude:2.35,latitude:48.85}")), false)
+    );
+    assert_eq!(
+        normalize_spatial("SRID=4326;POINT Z (2 48 35)", &point),
+        (Some(String::from("{crs:WGS-84-3D,longitude:2,latitude:48,height:35}")), false)
+    );
+    assert_eq!(
+        normalize_spatial("POINT(1 2)", &SpatialColumn::Point { srid: Some(2154) }),
+        (Some(String::from("{crs:cartesian,x:1,y:2}")), true)
+    );
+    assert_eq!(normalize_spatial("POINT EMPTY", &point), (None, false));
+
+    // LINESTRING(0 0,1 1) in WKB
+    let line = "01020000000200000000000000000000000000000000000000000000000000F03F000000000000F03F";
+    let wkt = SpatialColumn::Geometry { srid: None, as_points: false };
+    assert_eq!(normalize_spatial(line, &wkt), (Some(String::from("LINESTRING(0 0,1 1)")), false));
+    let points = SpatialColumn::Geometry { srid: None, as_points: true };
+    assert_eq!(
+        normalize_spatial(line, &points),
+        (Some(format!("{{crs:cartesian,x:0,y:0}}{}{{crs:cartesian,x:1,y:1}}", ARRAY_DELIMITER)), false)
+    );
+    assert!(normalize_spatial("POLYGON((0 0,1 0,0 1,0 0))", &points).1);
+    assert_eq!(
+        normalize_spatial("MULTIPOLYGON(((0 0,1 0,0 1,0 0)))", &wkt),
+        (Some(String::from("MULTIPOLYGON(((0 0,1 0,0 1,0 0)))")), false)
+    );
+}
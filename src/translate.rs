@@ -1,246 +1,1080 @@
-//! Note : This is just a demo of translating SQL queries into Cypher queries
+//! This module translate SQL queries into Cypher queries.<br>
+//! The SQL AST produced by *sqlparser* is lowered onto the Cypher AST of the module ```cypher```.
 
-use std::collections::HashMap;
-
-use sqlparser::ast::*;
-use sqlparser::dialect::GenericDialect;
+use sqlparser::ast::{
+    BinaryOperator as SqlBinaryOperator, DataType, Distinct, Expr as SqlExpr, Function,
+    FunctionArg, FunctionArgExpr, FunctionArguments, GroupByExpr, Ident, Join, JoinConstraint,
+    JoinOperator, ObjectName, OrderByKind, Select, SelectItem, SelectItemQualifiedWildcardKind,
+    SetExpr, Statement, TableFactor, TableWithJoins, UnaryOperator, Value,
+};
+use sqlparser::dialect::{GenericDialect, PostgreSqlDialect};
 use sqlparser::parser::Parser;
 
-#[allow(unused)]
-/// Return the node from the ```TableFactor``` object.
-fn from_table_factor(table_factor: TableFactor,hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    let mut res_alias = String::new();
-    if let TableFactor::Table {
-        name,
-        alias,
-        args,
-        with_hints,
-        version,
-        with_ordinality,
-        partitions,
-        json_path,
-        sample,
-        index_hints,
-    } = table_factor
-    {
-        if let Some(table_alias) = alias {
-            res_alias.push_str(&table_alias.name.value);
+use crate::cypher::*;
+use crate::mapping::Mapping;
+
+/// The state of the translation of one ```SELECT``` statement.
+#[derive(Default)]
+struct Translator<'a> {
+    /// The mapping of the migration, which can rename the relationships of the foreign keys.
+    mapping: Option<&'a Mapping>,
+    /// The tables of the ```FROM``` clause : (variable, label)
+    tables: Vec<(String, String)>,
+    /// The variables already bound in a pattern of the ```MATCH``` clauses.
+    bound: Vec<String>,
+    matches: Vec<Pattern>,
+    optional_matches: Vec<Pattern>,
+    /// The aliases of the ```SELECT``` clause, used to resolve them in ```ORDER BY```.
+    select_aliases: Vec<String>,
+    /// The expressions projected by an intermediate ```WITH``` clause and their alias.
+    renamed: Vec<(Expr, String)>,
+    /// Counter for the anonymous ```?``` placeholders.
+    placeholders: usize,
+}
+
+/// Return the name of the SQL identifier as PostgreSQL resolve it (unquoted identifiers are lower case).
+fn ident_name(ident: &Ident) -> String {
+    match ident.quote_style {
+        Some(_) => String::clone(&ident.value),
+        None => ident.value.to_lowercase(),
+    }
+}
+
+fn object_name(name: &ObjectName) -> Result<String, String> {
+    name.0
+        .last()
+        .and_then(|part| part.as_ident())
+        .map(ident_name)
+        .ok_or_else(|| format!("ERROR : Unsupported object name '{}'.", name))
+}
+
+fn node(variable: &str, label: Option<&str>) -> NodePattern {
+    NodePattern {
+        variable: Some(String::from(variable)),
+        labels: label.into_iter().map(String::from).collect(),
+        properties: vec![],
+    }
+}
+
+impl Translator<'_> {
+    fn label_of(&self, variable: &str) -> Result<&str, String> {
+        self.tables
+            .iter()
+            .find(|(name, _)| name == variable)
+            .map(|(_, label)| label.as_str())
+            .ok_or_else(|| format!("ERROR : The table '{}' isn't in the 'FROM' clause.", variable))
+    }
+
+    /// Return the node pattern of the variable, with its label only the first time it's bound.
+    fn bind(&mut self, variable: &str) -> Result<NodePattern, String> {
+        if self.bound.iter().any(|name| name == variable) {
+            Ok(node(variable, None))
         } else {
-            return Err(());
+            let label = String::from(self.label_of(variable)?);
+            self.bound.push(String::from(variable));
+            Ok(node(variable, Some(&label)))
         }
-        if let Some(identifier) = name.0[0].clone().as_ident() {
-            hashmap.insert(
-                res_alias.clone(),
-                format!("({}:{})", res_alias, identifier.value.to_uppercase()),
-            );
-            Ok(())
-        } else {
-            Err(())
+    }
+
+    /// Register the table of the ```FROM``` clause and return its variable.
+    fn table_factor(&mut self, table_factor: &TableFactor) -> Result<String, String> {
+        match table_factor {
+            TableFactor::Table { name, alias, .. } => {
+                let table = object_name(name)?;
+                let variable = match alias {
+                    Some(alias) => ident_name(&alias.name),
+                    None => String::clone(&table),
+                };
+                if self.tables.iter().any(|(name, _)| *name == variable) {
+                    return Err(format!("ERROR : The table alias '{}' is used twice.", variable));
+                }
+                self.tables.push((String::clone(&variable), table.to_uppercase()));
+                Ok(variable)
+            }
+            _ => Err(format!("ERROR : Unsupported table expression '{}'.", table_factor)),
         }
-    } else {
-        Err(())
     }
-}
 
-#[allow(unused)]
-fn from_join_constraint(join_constraint: JoinConstraint) -> Result<(String, String, String, String), ()> {
-    if let JoinConstraint::On(Expr::BinaryOp { left, op, right }) = join_constraint {
-        if let Expr::CompoundIdentifier(vector1) = *left {
-            if let Expr::CompoundIdentifier(vector2) = *right {
-                // Return : alias1 column1 alias2 column2
-                Ok((
-                    String::clone(&vector1[0].value),
-                    String::clone(&vector1[1].value),
-                    String::clone(&vector2[0].value),
-                    String::clone(&vector2[1].value),
-                ))
-            } else {
-                Err(())
+    /// Return (alias1, column1, alias2, column2) from the ```ON alias1.column1 = alias2.column2``` constraint.
+    fn join_columns(join_constraint: &JoinConstraint) -> Result<(String, String, String, String), String> {
+        if let JoinConstraint::On(SqlExpr::BinaryOp {
+            left,
+            op: SqlBinaryOperator::Eq,
+            right,
+        }) = join_constraint
+        {
+            if let (SqlExpr::CompoundIdentifier(vector1), SqlExpr::CompoundIdentifier(vector2)) =
+                (left.as_ref(), right.as_ref())
+            {
+                if vector1.len() == 2 && vector2.len() == 2 {
+                    return Ok((
+                        ident_name(&vector1[0]),
+                        ident_name(&vector1[1]),
+                        ident_name(&vector2[0]),
+                        ident_name(&vector2[1]),
+                    ));
+                }
             }
-        } else {
-            Err(())
         }
-    } else {
-        Err(())
+        Err(format!(
+            "ERROR : Only the join constraints like 'ON alias1.column1 = alias2.column2' are supported, found '{}'.",
+            join_constraint_display(join_constraint)
+        ))
     }
-}
 
-#[allow(unused)]
-/// Return a tuple that contain the join
-fn from_join(join: Join, hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    match from_table_factor(join.relation, hashmap) {
-        Ok(_) => match join.join_operator {
-            JoinOperator::Inner(join_constraint) => {
-                if let Ok(result) = from_join_constraint(join_constraint) {
-                    let node1 = String::clone(hashmap.get(&result.0).ok_or_else(|| ())?);
-                    let label1 = node1.split(":").collect::<Vec<&str>>()[1].replace(")", "");
-                    let node2 = String::clone(hashmap.get(&result.2).ok_or_else(|| ())?);
-                    if let Some(match_clause) = hashmap.get_mut("match") {
-                        match_clause.push_str(&format!(
-                            "{}-[r{}{}{}:{}_ref_{}]-{},",
-                            node1, result.0, result.2, result.3, label1, result.1, node2
-                        ));
-                    }
-                    Ok(())
-                } else {
-                    Err(())
+    /// The relationship generated by Neo4j-Migrator for the foreign key ```column``` of the table ```label```,
+    /// its type is the one chosen in the mapping or ```<TABLE>_ref_<COLUMN>```.
+    fn relationship(&self, label: &str, column: &str) -> Result<RelationshipPattern, String> {
+        let relationship_type = match self.mapping {
+            Some(mapping) => mapping.relationship_type(label, column)?,
+            None => None,
+        };
+        Ok(RelationshipPattern {
+            variable: None,
+            types: vec![relationship_type.unwrap_or_else(|| format!("{}_ref_{}", label, column.to_uppercase()))],
+            direction: Direction::Outgoing,
+            properties: vec![],
+        })
+    }
+
+    fn join(&mut self, join: &Join) -> Result<(), String> {
+        let joined = self.table_factor(&join.relation)?;
+        let (constraint, optional_side) = match &join.join_operator {
+            JoinOperator::Join(constraint) | JoinOperator::Inner(constraint) => (constraint, None),
+            JoinOperator::Left(constraint) | JoinOperator::LeftOuter(constraint) => {
+                (constraint, Some(false))
+            }
+            JoinOperator::Right(constraint) | JoinOperator::RightOuter(constraint) => {
+                (constraint, Some(true))
+            }
+            _ => return Err(String::from("ERROR : Only the INNER, LEFT and RIGHT joins are supported.")),
+        };
+        // The foreign key is supposed to be held by the left side of the constraint.
+        let (alias1, column1, alias2, _) = Self::join_columns(constraint)?;
+        let relationship = self.relationship(self.label_of(&alias1)?, &column1)?;
+
+        match optional_side {
+            None => {
+                let start = self.bind(&alias1)?;
+                let end = self.bind(&alias2)?;
+                self.matches.push(Pattern {
+                    variable: None,
+                    start,
+                    chain: vec![(relationship, end)],
+                });
+            }
+            Some(right_join) => {
+                // The preserved side of the join is matched first, the other side is optional.
+                let preserved = match (right_join, joined == alias1) {
+                    (true, _) => joined,
+                    (false, true) => String::clone(&alias2),
+                    (false, false) => String::clone(&alias1),
+                };
+                if !self.bound.contains(&preserved) {
+                    let pattern = self.bind(&preserved)?;
+                    self.matches.push(Pattern {
+                        variable: None,
+                        start: pattern,
+                        chain: vec![],
+                    });
                 }
+                let start = self.bind(&alias1)?;
+                let end = self.bind(&alias2)?;
+                self.optional_matches.push(Pattern {
+                    variable: None,
+                    start,
+                    chain: vec![(relationship, end)],
+                });
             }
-            JoinOperator::Left(join_constraint) => {
-                if let Ok(result) = from_join_constraint(join_constraint) {
-                    let node1 = String::clone(hashmap.get(&result.0).ok_or_else(|| ())?);
-                    let label1 = node1.split(":").collect::<Vec<&str>>()[1].replace(")", "");
-                    let node2 = String::clone(hashmap.get(&result.2).ok_or_else(|| ())?);
-                    if let Some(match_clause) = hashmap.get_mut("match") {
-                        match_clause.push_str(&format!("{},", node1));
+        }
+        Ok(())
+    }
+
+    fn table_with_joins(&mut self, table_with_joins: &TableWithJoins) -> Result<(), String> {
+        self.table_factor(&table_with_joins.relation)?;
+        for join in &table_with_joins.joins {
+            self.join(join)?;
+        }
+        Ok(())
+    }
+
+    /// Resolve a column without its table, it's only possible if there is only one table.
+    fn column(&self, ident: &Ident) -> Result<Expr, String> {
+        let name = ident_name(ident);
+        if let Some(alias) = self
+            .select_aliases
+            .iter()
+            .find(|alias| alias.eq_ignore_ascii_case(&name))
+        {
+            return Ok(Expr::var(alias));
+        }
+        match self.tables.as_slice() {
+            [(variable, _)] => Ok(Expr::prop(variable, &name)),
+            _ => Err(format!(
+                "ERROR : The column '{}' is ambiguous, please prefix it with its table.",
+                ident
+            )),
+        }
+    }
+
+    fn literal(&mut self, value: &Value) -> Result<Expr, String> {
+        let literal = match value {
+            Value::Number(number, _) => match number.parse::<i64>() {
+                Ok(integer) => Literal::Integer(integer),
+                Err(_) => Literal::Float(
+                    number
+                        .parse::<f64>()
+                        .map_err(|error| format!("ERROR : Invalid number '{}'\n{}", number, error))?,
+                ),
+            },
+            Value::SingleQuotedString(string)
+            | Value::EscapedStringLiteral(string)
+            | Value::NationalStringLiteral(string) => Literal::String(String::clone(string)),
+            Value::DollarQuotedString(string) => Literal::String(String::clone(&string.value)),
+            Value::Boolean(boolean) => Literal::Boolean(*boolean),
+            Value::Null => Literal::Null,
+            Value::Placeholder(placeholder) => {
+                let name = match placeholder.strip_prefix(['$', ':', '@']) {
+                    Some(name) if name.chars().all(|c| c.is_ascii_digit()) => format!("p{}", name),
+                    Some(name) => String::from(name),
+                    None => {
+                        self.placeholders += 1;
+                        format!("p{}", self.placeholders)
                     }
-                    if let Some(match_clause) = hashmap.get_mut("optional match") {
-                        match_clause.push_str(&format!(
-                            "{}-[r{}{}{}:{}_ref_{}]-{},",
-                            node1, result.0, result.2, result.3, label1, result.1, node2
-                        ));
+                };
+                return Ok(Expr::Parameter(name));
+            }
+            _ => return Err(format!("ERROR : Unsupported SQL value '{}'.", value)),
+        };
+        Ok(Expr::Literal(literal))
+    }
+
+    fn binary_operator(op: &SqlBinaryOperator) -> Result<BinaryOperator, String> {
+        Ok(match op {
+            SqlBinaryOperator::Plus | SqlBinaryOperator::StringConcat => BinaryOperator::Add,
+            SqlBinaryOperator::Minus => BinaryOperator::Sub,
+            SqlBinaryOperator::Multiply => BinaryOperator::Mul,
+            SqlBinaryOperator::Divide => BinaryOperator::Div,
+            SqlBinaryOperator::Modulo => BinaryOperator::Mod,
+            SqlBinaryOperator::Gt => BinaryOperator::Gt,
+            SqlBinaryOperator::Lt => BinaryOperator::Lt,
+            SqlBinaryOperator::GtEq => BinaryOperator::GtEq,
+            SqlBinaryOperator::LtEq => BinaryOperator::LtEq,
+            SqlBinaryOperator::Eq => BinaryOperator::Eq,
+            SqlBinaryOperator::NotEq => BinaryOperator::NotEq,
+            SqlBinaryOperator::And => BinaryOperator::And,
+            SqlBinaryOperator::Or => BinaryOperator::Or,
+            SqlBinaryOperator::Xor => BinaryOperator::Xor,
+            SqlBinaryOperator::PGExp => BinaryOperator::Pow,
+            SqlBinaryOperator::PGRegexMatch => BinaryOperator::RegexMatch,
+            _ => return Err(format!("ERROR : Unsupported SQL operator '{}'.", op)),
+        })
+    }
+
+    /// Translate the ```LIKE``` pattern into ```starts with```, ```ends with```, ```contains``` or a regex.
+    fn like(&mut self, expr: &SqlExpr, pattern: &SqlExpr, negated: bool, case_insensitive: bool) -> Result<Expr, String> {
+        let mut target = self.expr(expr)?;
+        let result = match pattern {
+            SqlExpr::Value(value) => match &value.value {
+                Value::SingleQuotedString(pattern) => {
+                    let inner = pattern.trim_start_matches('%').trim_end_matches('%');
+                    let is_plain = !inner.contains(['%', '_', '\\']);
+                    let mut literal = String::from(inner);
+                    if case_insensitive {
+                        target = Expr::call("toLower", vec![target]);
+                        literal = literal.to_lowercase();
+                    }
+                    match (is_plain, pattern.starts_with('%'), pattern.ends_with('%')) {
+                        (true, false, false) => {
+                            Expr::binary(target, BinaryOperator::Eq, Expr::string(&literal))
+                        }
+                        (true, false, true) => {
+                            Expr::binary(target, BinaryOperator::StartsWith, Expr::string(&literal))
+                        }
+                        (true, true, false) => {
+                            Expr::binary(target, BinaryOperator::EndsWith, Expr::string(&literal))
+                        }
+                        (true, true, true) => {
+                            Expr::binary(target, BinaryOperator::Contains, Expr::string(&literal))
+                        }
+                        (false, _, _) => Expr::binary(
+                            target,
+                            BinaryOperator::RegexMatch,
+                            Expr::string(&like_to_regex(pattern, case_insensitive)),
+                        ),
+                    }
+                }
+                _ => return Err(format!("ERROR : Unsupported LIKE pattern '{}'.", pattern)),
+            },
+            _ => return Err(format!("ERROR : Unsupported LIKE pattern '{}'.", pattern)),
+        };
+        Ok(if negated { Expr::Not(Box::new(result)) } else { result })
+    }
+
+    fn function(&mut self, function: &Function) -> Result<Expr, String> {
+        let name = object_name(&function.name)?;
+        let mut distinct = false;
+        let mut args = Vec::new();
+        match &function.args {
+            FunctionArguments::None => {}
+            FunctionArguments::List(list) => {
+                distinct = matches!(
+                    list.duplicate_treatment,
+                    Some(sqlparser::ast::DuplicateTreatment::Distinct)
+                );
+                for arg in &list.args {
+                    match arg {
+                        FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => args.push(self.expr(expr)?),
+                        FunctionArg::Unnamed(FunctionArgExpr::Wildcard) if name == "count" => {
+                            return Ok(Expr::CountAll)
+                        }
+                        _ => return Err(format!("ERROR : Unsupported function argument '{}'.", arg)),
                     }
-                    Ok(())
+                }
+            }
+            FunctionArguments::Subquery(_) => {
+                return Err(format!("ERROR : Unsupported function '{}'.", function))
+            }
+        }
+        if function.over.is_some() || function.filter.is_some() {
+            return Err(format!("ERROR : Unsupported function '{}'.", function));
+        }
+        let cypher_name = match name.as_str() {
+            "upper" => "toUpper",
+            "lower" => "toLower",
+            "length" | "char_length" | "character_length" => "size",
+            "btrim" => "trim",
+            "stddev" | "stddev_samp" => "stDev",
+            "stddev_pop" => "stDevP",
+            "array_agg" => "collect",
+            "count" | "sum" | "avg" | "min" | "max" | "abs" | "round" | "ceil" | "floor"
+            | "sqrt" | "sign" | "exp" | "trim" | "ltrim" | "rtrim" | "replace"
+            | "coalesce" | "reverse" | "left" | "right" => name.as_str(),
+            "ceiling" => "ceil",
+            // The logarithm of SQL is in base 10 (or in the base of its first argument), Cypher's log is natural.
+            "ln" => "log",
+            "log10" => "log10",
+            "log" if args.len() == 2 => {
+                let mut args = args.into_iter();
+                let (Some(base), Some(value)) = (args.next(), args.next()) else {
+                    return Err(String::from("ERROR : The function 'log' expects 1 or 2 arguments."));
+                };
+                return Ok(Expr::binary(
+                    Expr::call("log", vec![value]),
+                    BinaryOperator::Div,
+                    Expr::call("log", vec![base]),
+                ));
+            }
+            "log" => "log10",
+            "mod" => {
+                return match <[Expr; 2]>::try_from(args) {
+                    Ok([left, right]) => Ok(Expr::binary(left, BinaryOperator::Mod, right)),
+                    Err(_) => Err(String::from("ERROR : The function 'mod' expects 2 arguments.")),
+                }
+            }
+            "concat" => {
+                // SQL concat skips the NULL arguments and joins the text of the others, Cypher + would return null
+                // or add the numbers.
+                return args
+                    .into_iter()
+                    .map(|arg| Expr::call("coalesce", vec![Expr::call("toString", vec![arg]), Expr::string("")]))
+                    .reduce(|left, right| Expr::binary(left, BinaryOperator::Add, right))
+                    .ok_or_else(|| String::from("ERROR : The function 'concat' expects arguments."))
+            }
+            "substring" | "substr" => {
+                // SQL strings start at the index 1, Cypher strings at the index 0.
+                let mut args = args.into_iter();
+                let string = args.next().ok_or("ERROR : The function 'substring' expects arguments.")?;
+                let start = args.next().ok_or("ERROR : The function 'substring' expects a start.")?;
+                let start = Expr::binary(start, BinaryOperator::Sub, Expr::Literal(Literal::Integer(1)));
+                let mut cypher_args = vec![string, start];
+                cypher_args.extend(args);
+                return Ok(Expr::call("substring", cypher_args));
+            }
+            "now" | "current_timestamp" => "datetime",
+            "current_date" => "date",
+            "localtimestamp" => "localdatetime",
+            _ => return Err(format!("ERROR : Unsupported SQL function '{}'.", name)),
+        };
+        Ok(Expr::FunctionCall {
+            name: String::from(cypher_name),
+            distinct,
+            args,
+        })
+    }
+
+    fn cast(&mut self, expr: &SqlExpr, data_type: &DataType) -> Result<Expr, String> {
+        let inner = self.expr(expr)?;
+        // PostgreSQL add casts to the literals and arrays of its expressions, like ```(0)::numeric```.
+        match (&inner, data_type) {
+            (Expr::Literal(Literal::String(_)), DataType::Text | DataType::Varchar(_) | DataType::Char(_) | DataType::CharacterVarying(_) | DataType::Character(_) | DataType::String(_))
+            | (Expr::Literal(Literal::Integer(_) | Literal::Float(_)), DataType::Numeric(_) | DataType::Decimal(_) | DataType::Integer(_) | DataType::Int(_) | DataType::BigInt(_) | DataType::DoublePrecision | DataType::Real)
+            | (Expr::List(_), DataType::Array(_)) => return Ok(inner),
+            _ => {}
+        }
+        let function = match data_type {
+            DataType::Int(_)
+            | DataType::Integer(_)
+            | DataType::BigInt(_)
+            | DataType::SmallInt(_)
+            | DataType::Int2(_)
+            | DataType::Int4(_)
+            | DataType::Int8(_) => "toInteger",
+            DataType::Real
+            | DataType::Double(_)
+            | DataType::DoublePrecision
+            | DataType::Float(_)
+            | DataType::Float4
+            | DataType::Float8
+            | DataType::Numeric(_)
+            | DataType::Decimal(_) => "toFloat",
+            DataType::Text
+            | DataType::Varchar(_)
+            | DataType::Char(_)
+            | DataType::CharacterVarying(_)
+            | DataType::Character(_)
+            | DataType::String(_) => "toString",
+            DataType::Boolean | DataType::Bool => "toBoolean",
+            DataType::Date => "date",
+            _ => return Err(format!("ERROR : Unsupported cast to '{}'.", data_type)),
+        };
+        Ok(Expr::call(function, vec![inner]))
+    }
+
+    /// Lower the SQL expression onto the Cypher expression.
+    fn expr(&mut self, expr: &SqlExpr) -> Result<Expr, String> {
+        Ok(match expr {
+            SqlExpr::Identifier(ident) => self.column(ident)?,
+            SqlExpr::CompoundIdentifier(idents) => match idents.as_slice() {
+                [table, column] => {
+                    let variable = ident_name(table);
+                    self.label_of(&variable)?;
+                    Expr::prop(&variable, &ident_name(column))
+                }
+                _ => return Err(format!("ERROR : Unsupported identifier '{}'.", expr)),
+            },
+            SqlExpr::Value(value) => self.literal(&value.value)?,
+            SqlExpr::Nested(expr) => self.expr(expr)?,
+            SqlExpr::BinaryOp { left, op, right } => {
+                Expr::binary(self.expr(left)?, Self::binary_operator(op)?, self.expr(right)?)
+            }
+            SqlExpr::UnaryOp { op, expr } => match op {
+                UnaryOperator::Not => Expr::Not(Box::new(self.expr(expr)?)),
+                UnaryOperator::Minus => Expr::Negative(Box::new(self.expr(expr)?)),
+                UnaryOperator::Plus => self.expr(expr)?,
+                _ => return Err(format!("ERROR : Unsupported SQL operator '{}'.", op)),
+            },
+            SqlExpr::IsNull(expr) => Expr::IsNull {
+                expr: Box::new(self.expr(expr)?),
+                negated: false,
+            },
+            SqlExpr::IsNotNull(expr) => Expr::IsNull {
+                expr: Box::new(self.expr(expr)?),
+                negated: true,
+            },
+            SqlExpr::IsTrue(expr) => Expr::binary(
+                self.expr(expr)?,
+                BinaryOperator::Eq,
+                Expr::Literal(Literal::Boolean(true)),
+            ),
+            SqlExpr::IsFalse(expr) => Expr::binary(
+                self.expr(expr)?,
+                BinaryOperator::Eq,
+                Expr::Literal(Literal::Boolean(false)),
+            ),
+            SqlExpr::InList {
+                expr,
+                list,
+                negated,
+            } => {
+                let list = list
+                    .iter()
+                    .map(|item| self.expr(item))
+                    .collect::<Result<Vec<Expr>, String>>()?;
+                let result = Expr::binary(self.expr(expr)?, BinaryOperator::In, Expr::List(list));
+                if *negated {
+                    Expr::Not(Box::new(result))
                 } else {
-                    Err(())
+                    result
                 }
             }
-            JoinOperator::Right(join_constraint) => {
-                if let Ok(result) = from_join_constraint(join_constraint) {
-                    let node1 = String::clone(hashmap.get(&result.0).ok_or_else(|| ())?);
-                    let label1 = node1.split(":").collect::<Vec<&str>>()[1].replace(")", "");
-                    let node2 = String::clone(hashmap.get(&result.2).ok_or_else(|| ())?);
-                    if let Some(match_clause) = hashmap.get_mut("match") {
-                        match_clause.push_str(&format!("{},", node2));
-                    }
-                    if let Some(match_clause) = hashmap.get_mut("optional match") {
-                        match_clause.push_str(&format!(
-                            "{}-[r{}{}{}:{}_ref_{}]-{},",
-                            node1, result.0, result.2, result.3, label1, result.1, node2
-                        ));
-                    }
-                    Ok(())
+            SqlExpr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => {
+                let target = self.expr(expr)?;
+                let result = Expr::binary(
+                    Expr::binary(target.clone(), BinaryOperator::GtEq, self.expr(low)?),
+                    BinaryOperator::And,
+                    Expr::binary(target, BinaryOperator::LtEq, self.expr(high)?),
+                );
+                if *negated {
+                    Expr::Not(Box::new(result))
                 } else {
-                    Err(())
+                    result
                 }
             }
-            _ => Err(()),
+            SqlExpr::Like {
+                negated,
+                any: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => self.like(expr, pattern, *negated, false)?,
+            SqlExpr::ILike {
+                negated,
+                any: false,
+                expr,
+                pattern,
+                escape_char: None,
+            } => self.like(expr, pattern, *negated, true)?,
+            SqlExpr::Function(function) => self.function(function)?,
+            SqlExpr::Array(array) => Expr::List(
+                array
+                    .elem
+                    .iter()
+                    .map(|item| self.expr(item))
+                    .collect::<Result<Vec<Expr>, String>>()?,
+            ),
+            // ```x = ANY (ARRAY[...])``` is the form of ```x IN (...)``` used by PostgreSQL.
+            SqlExpr::AnyOp {
+                left,
+                compare_op: SqlBinaryOperator::Eq,
+                right,
+                ..
+            } => Expr::binary(self.expr(left)?, BinaryOperator::In, self.expr(right)?),
+            SqlExpr::AllOp {
+                left,
+                compare_op: SqlBinaryOperator::NotEq,
+                right,
+            } => Expr::Not(Box::new(Expr::binary(
+                self.expr(left)?,
+                BinaryOperator::In,
+                self.expr(right)?,
+            ))),
+            SqlExpr::Cast {
+                expr, data_type, ..
+            } => self.cast(expr, data_type)?,
+            SqlExpr::Case {
+                operand,
+                conditions,
+                else_result,
+            } => Expr::Case {
+                operand: match operand {
+                    Some(operand) => Some(Box::new(self.expr(operand)?)),
+                    None => None,
+                },
+                when_then: conditions
+                    .iter()
+                    .map(|case_when| Ok((self.expr(&case_when.condition)?, self.expr(&case_when.result)?)))
+                    .collect::<Result<Vec<(Expr, Expr)>, String>>()?,
+                else_expr: match else_result {
+                    Some(else_result) => Some(Box::new(self.expr(else_result)?)),
+                    None => None,
+                },
+            },
+            _ => return Err(format!("ERROR : Unsupported SQL expression '{}'.", expr)),
+        })
+    }
+
+    fn select_item(&mut self, select_item: &SelectItem) -> Result<ProjectionItem, String> {
+        match select_item {
+            SelectItem::UnnamedExpr(expr) => Ok(ProjectionItem::Expr {
+                expr: self.expr(expr)?,
+                alias: None,
+            }),
+            SelectItem::ExprWithAlias { expr, alias } => Ok(ProjectionItem::Expr {
+                expr: self.expr(expr)?,
+                alias: Some(ident_name(alias)),
+            }),
+            SelectItem::Wildcard(_) => Ok(ProjectionItem::All),
+            SelectItem::QualifiedWildcard(SelectItemQualifiedWildcardKind::ObjectName(name), _) => {
+                let variable = object_name(name)?;
+                self.label_of(&variable)?;
+                Ok(ProjectionItem::Expr {
+                    expr: Expr::var(&variable),
+                    alias: None,
+                })
+            }
+            _ => Err(format!("ERROR : Unsupported select item '{}'.", select_item)),
+        }
+    }
+
+    /// Lower the ```SELECT``` statement onto the Cypher clauses.
+    fn select(&mut self, select: &Select) -> Result<(Vec<Clause>, Projection), String> {
+        if select.from.is_empty() {
+            return Err(String::from("ERROR : The 'FROM' clause is needed."));
+        }
+        for table_with_joins in &select.from {
+            self.table_with_joins(table_with_joins)
+                .map_err(|error| format!("ERROR : when try to transform the 'FROM' clause.\n{}", error))?;
+        }
+
+        // The tables without join are matched alone, before the joins.
+        let mut alone = Vec::new();
+        for (variable, _) in self.tables.clone() {
+            if !self.bound.contains(&variable) {
+                alone.push(Pattern {
+                    variable: None,
+                    start: self.bind(&variable)?,
+                    chain: vec![],
+                });
+            }
+        }
+        alone.append(&mut self.matches);
+
+        let where_clause = match &select.selection {
+            Some(selection) => Some(
+                self.expr(selection)
+                    .map_err(|error| format!("ERROR : when try to transform the 'WHERE' clause.\n{}", error))?,
+            ),
+            None => None,
+        };
+
+        let mut clauses = vec![Clause::Match {
+            optional: false,
+            patterns: alone,
+            where_clause: if self.optional_matches.is_empty() {
+                where_clause.clone()
+            } else {
+                None
+            },
+        }];
+        if !self.optional_matches.is_empty() {
+            clauses.push(Clause::Match {
+                optional: true,
+                patterns: std::mem::take(&mut self.optional_matches),
+                where_clause: None,
+            });
+        }
+
+        let items = select
+            .projection
+            .iter()
+            .map(|item| self.select_item(item))
+            .collect::<Result<Vec<ProjectionItem>, String>>()
+            .map_err(|error| format!("ERROR : when try to transform the 'SELECT' clause.\n{}", error))?;
+        self.select_aliases = items
+            .iter()
+            .filter_map(|item| match item {
+                ProjectionItem::Expr { alias: Some(alias), .. } => Some(String::clone(alias)),
+                _ => None,
+            })
+            .collect();
+
+        // With an OUTER JOIN the filter apply on the result of the join.
+        if clauses.len() > 1 && where_clause.is_some() {
+            clauses.push(Clause::With(Projection {
+                items: vec![ProjectionItem::All],
+                where_clause,
+                ..Default::default()
+            }));
+        }
+
+        let group_by = match &select.group_by {
+            GroupByExpr::Expressions(exprs, _) => exprs
+                .iter()
+                .map(|expr| self.expr(expr))
+                .collect::<Result<Vec<Expr>, String>>()
+                .map_err(|error| format!("ERROR : when try to transform the 'GROUP BY' clause.\n{}", error))?,
+            GroupByExpr::All(_) => vec![],
+        };
+        let having = match &select.having {
+            Some(having) => Some(
+                self.expr(having)
+                    .map_err(|error| format!("ERROR : when try to transform the 'HAVING' clause.\n{}", error))?,
+            ),
+            None => None,
+        };
+
+        let distinct = match &select.distinct {
+            None => false,
+            Some(Distinct::Distinct) => true,
+            Some(Distinct::On(_)) => return Err(String::from("ERROR : 'DISTINCT ON' isn't supported.")),
+        };
+
+        let projected = items.iter().filter_map(|item| match item {
+            ProjectionItem::Expr { expr, .. } => Some(expr),
+            ProjectionItem::All => None,
+        });
+        let hidden_keys = group_by
+            .iter()
+            .filter(|key| !projected.clone().any(|expr| expr == *key))
+            .cloned()
+            .collect::<Vec<Expr>>();
+
+        if having.is_none() && hidden_keys.is_empty() {
+            return Ok((
+                clauses,
+                Projection {
+                    distinct,
+                    items,
+                    ..Default::default()
+                },
+            ));
+        }
+
+        // Cypher group implicitly by the non aggregated expressions, so the HAVING clause and the
+        // grouping keys absent from the SELECT clause need an intermediate WITH clause.
+        let mut with_items = Vec::new();
+        let mut renamed = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            if let ProjectionItem::Expr { expr, alias } = item {
+                let alias = alias.clone().unwrap_or_else(|| format!("column{}", index + 1));
+                renamed.push((expr.clone(), String::clone(&alias)));
+                with_items.push(ProjectionItem::Expr {
+                    expr: expr.clone(),
+                    alias: Some(alias),
+                });
+            } else {
+                return Err(String::from("ERROR : 'SELECT *' can't be used with 'GROUP BY' or 'HAVING'."));
+            }
+        }
+        for (index, key) in hidden_keys.iter().enumerate() {
+            let alias = format!("group{}", index + 1);
+            renamed.push((key.clone(), String::clone(&alias)));
+            with_items.push(ProjectionItem::Expr {
+                expr: key.clone(),
+                alias: Some(alias),
+            });
+        }
+        let having = having.map(|having| {
+            let having = replace_projected(having, &renamed);
+            extract_aggregates(having, &mut with_items, &mut renamed)
+        });
+
+        let return_items = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| match item {
+                ProjectionItem::Expr { alias: Some(alias), .. } => ProjectionItem::Expr {
+                    expr: Expr::var(alias),
+                    alias: None,
+                },
+                _ => ProjectionItem::Expr {
+                    expr: Expr::var(&format!("column{}", index + 1)),
+                    alias: Some(format!("{}", item)),
+                },
+            })
+            .collect();
+        self.select_aliases = renamed.iter().map(|(_, alias)| String::clone(alias)).collect();
+        self.renamed = renamed;
+
+        clauses.push(Clause::With(Projection {
+            items: with_items,
+            where_clause: having,
+            ..Default::default()
+        }));
+        Ok((
+            clauses,
+            Projection {
+                distinct,
+                items: return_items,
+                ..Default::default()
+            },
+        ))
+    }
+}
+
+/// Replace the sub-expressions already projected in a ```WITH``` clause by their alias.
+fn replace_projected(expr: Expr, renamed: &[(Expr, String)]) -> Expr {
+    if let Some((_, alias)) = renamed.iter().find(|(projected, _)| *projected == expr) {
+        return Expr::var(alias);
+    }
+    match expr {
+        Expr::Binary { left, op, right } => Expr::binary(
+            replace_projected(*left, renamed),
+            op,
+            replace_projected(*right, renamed),
+        ),
+        Expr::Not(expr) => Expr::Not(Box::new(replace_projected(*expr, renamed))),
+        Expr::Negative(expr) => Expr::Negative(Box::new(replace_projected(*expr, renamed))),
+        Expr::IsNull { expr, negated } => Expr::IsNull {
+            expr: Box::new(replace_projected(*expr, renamed)),
+            negated,
         },
-        Err(_) => Err(()),
+        Expr::List(exprs) => Expr::List(
+            exprs
+                .into_iter()
+                .map(|expr| replace_projected(expr, renamed))
+                .collect(),
+        ),
+        other => other,
     }
 }
 
-#[allow(unused)]
-fn from_table_with_joins(table_with_joins: TableWithJoins,hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    match from_table_factor(table_with_joins.relation, hashmap) {
-        Ok(_) => {
-            for join in table_with_joins.joins {
-                if let Err(_) = from_join(join, hashmap) {
-                    return Err(());
+/// Move the aggregations of the expression into the ```WITH``` clause and replace them by their alias.
+fn extract_aggregates(expr: Expr, with_items: &mut Vec<ProjectionItem>, renamed: &mut Vec<(Expr, String)>) -> Expr {
+    if let Expr::Variable(_) = expr {
+        return expr;
+    }
+    if matches!(expr, Expr::CountAll | Expr::FunctionCall { .. }) && expr.is_aggregate() {
+        let alias = format!("aggregate{}", renamed.len() + 1);
+        renamed.push((expr.clone(), String::clone(&alias)));
+        with_items.push(ProjectionItem::Expr {
+            expr,
+            alias: Some(String::clone(&alias)),
+        });
+        return Expr::var(&alias);
+    }
+    match expr {
+        Expr::Binary { left, op, right } => Expr::binary(
+            extract_aggregates(*left, with_items, renamed),
+            op,
+            extract_aggregates(*right, with_items, renamed),
+        ),
+        Expr::Not(expr) => Expr::Not(Box::new(extract_aggregates(*expr, with_items, renamed))),
+        Expr::Negative(expr) => Expr::Negative(Box::new(extract_aggregates(*expr, with_items, renamed))),
+        Expr::IsNull { expr, negated } => Expr::IsNull {
+            expr: Box::new(extract_aggregates(*expr, with_items, renamed)),
+            negated,
+        },
+        other => other,
+    }
+}
+
+/// Convert a SQL ```LIKE``` pattern into a Java regex used by the Cypher operator ```=~```.
+fn like_to_regex(pattern: &str, case_insensitive: bool) -> String {
+    let mut regex = String::new();
+    if case_insensitive {
+        regex.push_str("(?i)");
+    }
+    let mut chars = pattern.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '%' => regex.push_str(".*"),
+            '_' => regex.push('.'),
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    regex.push_str(&regex_escape(escaped));
                 }
             }
-            Ok(())
+            _ => regex.push_str(&regex_escape(c)),
         }
-        Err(_) => Err(()),
     }
+    regex
 }
 
-#[allow(unused)]
-fn from_statement(vector_twj: Vec<TableWithJoins>,hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    for table_with_joins in vector_twj {
-        if let Err(_) = from_table_with_joins(table_with_joins, hashmap) {
-            return Err(());
-        }
+fn regex_escape(c: char) -> String {
+    if "\\.^$|?*+()[]{}".contains(c) {
+        format!("\\{}", c)
+    } else {
+        String::from(c)
     }
-    Ok(())
 }
 
-#[allow(unused)]
-fn select_select_item(select_item: SelectItem,hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    match select_item {
-        SelectItem::UnnamedExpr(Expr::CompoundIdentifier(vector)) => {
-            if let Some(return_clause) = hashmap.get_mut("return") {
-                return_clause.push_str(&format!("{}.{},", vector[0].value, vector[1].value));
-                Ok(())
-            } else {
-                Err(())
+fn join_constraint_display(join_constraint: &JoinConstraint) -> String {
+    match join_constraint {
+        JoinConstraint::On(expr) => format!("ON {}", expr),
+        JoinConstraint::Using(columns) => format!(
+            "USING ({})",
+            columns.iter().map(|c| c.to_string()).collect::<Vec<String>>().join(", ")
+        ),
+        JoinConstraint::Natural => String::from("NATURAL"),
+        JoinConstraint::None => String::new(),
+    }
+}
+
+/// Translate a SQL expression on the columns of the table ```table``` (like the expression of a
+/// ```CHECK``` constraint) into a Cypher expression on the properties of the node ```variable```.
+pub fn translate_expression(sql_expr: &str, table: &str, variable: &str) -> Result<Expr, String> {
+    let dialect = PostgreSqlDialect {};
+    let expr = Parser::new(&dialect)
+        .try_with_sql(sql_expr)
+        .and_then(|mut parser| parser.parse_expr())
+        .map_err(|error| format!("ERROR : when try to parse the SQL expression '{}'.\n{}", sql_expr, error))?;
+    let mut translator = Translator {
+        tables: vec![(String::from(variable), table.to_uppercase())],
+        ..Default::default()
+    };
+    translator.expr(&expr)
+}
+
+/// Translate the SQL query into the Cypher AST, the relationships of the joins are named like in the mapping.
+pub fn translate_query(sql_query: &str, mapping: &Mapping) -> Result<Query, String> {
+    let dialect = GenericDialect {};
+    let ast = Parser::parse_sql(&dialect, sql_query)
+        .map_err(|error| format!("ERROR : when try to parse the SQL query.\n{}", error))?;
+
+    let query = match ast.as_slice() {
+        [Statement::Query(query)] => query,
+        [_] => return Err(String::from("This function only support SQL queries.")),
+        _ => return Err(String::from("ERROR : This function expects exactly one SQL query.")),
+    };
+    let select = match query.body.as_ref() {
+        SetExpr::Select(select) => select,
+        _ => return Err(String::from("Your query is not yet supported by the funtion.")),
+    };
+    if query.with.is_some() {
+        return Err(String::from("ERROR : The 'WITH' clause isn't supported."));
+    }
+
+    let mut translator = Translator {
+        mapping: Some(mapping),
+        ..Default::default()
+    };
+    let (mut clauses, mut projection) = translator.select(select)?;
+
+    if let Some(order_by) = &query.order_by {
+        if let OrderByKind::Expressions(exprs) = &order_by.kind {
+            for order_by_expr in exprs {
+                let expr = translator
+                    .expr(&order_by_expr.expr)
+                    .map_err(|error| format!("ERROR : when try to transform the 'ORDER BY' clause.\n{}", error))?;
+                projection.order_by.push(SortItem {
+                    expr: replace_projected(expr, &translator.renamed),
+                    descending: order_by_expr.options.asc == Some(false),
+                });
             }
         }
-        _ => Err(()),
     }
+    if let Some(offset) = &query.offset {
+        projection.skip = Some(translator.expr(&offset.value)?);
+    }
+    if let Some(limit) = &query.limit {
+        projection.limit = Some(translator.expr(limit)?);
+    }
+    clauses.push(Clause::Return(projection));
+    Ok(Query::new(clauses))
 }
 
-#[allow(unused)]
-fn select_statement(vector_si: Vec<SelectItem>,hashmap: &mut HashMap<String, String>) -> Result<(), ()> {
-    for select_item in vector_si {
-        if let Err(_) = select_select_item(select_item, hashmap) {
-            return Err(());
-        }
+/// Return the name PostgreSQL gives to the column of an expression without alias : the name of the column, of the
+/// function, ```case```, ```array```, ```exists```... and ```?column?``` for the other expressions. The casts keep
+/// the name of their expression, or take the name of their type as written in the query.
+fn postgresql_column_name(expr: &SqlExpr) -> String {
+    match expr {
+        SqlExpr::Identifier(ident) => ident_name(ident),
+        SqlExpr::CompoundIdentifier(idents) => idents.last().map(ident_name).unwrap_or_else(|| String::from("?column?")),
+        SqlExpr::Function(function) => object_name(&function.name).unwrap_or_else(|_| String::from("?column?")),
+        SqlExpr::Nested(expr) => postgresql_column_name(expr),
+        SqlExpr::Cast { expr, data_type, .. } => match postgresql_column_name(expr).as_str() {
+            "?column?" => data_type.to_string().to_lowercase(),
+            name => String::from(name),
+        },
+        SqlExpr::Case { .. } => String::from("case"),
+        SqlExpr::Array(_) => String::from("array"),
+        SqlExpr::Exists { .. } => String::from("exists"),
+        SqlExpr::Extract { .. } => String::from("extract"),
+        SqlExpr::Ceil { .. } => String::from("ceil"),
+        SqlExpr::Floor { .. } => String::from("floor"),
+        SqlExpr::Position { .. } => String::from("position"),
+        SqlExpr::Substring { .. } => String::from("substring"),
+        _ => String::from("?column?"),
     }
-    Ok(())
 }
 
-#[allow(unused)]
-pub fn generate_cypher_query(sql_query: &str) -> Result<String, String> {
-    let dialect = GenericDialect {}; // or AnsiDialect
-    let ast = Parser::parse_sql(&dialect, sql_query).unwrap();
-
-    match &ast[0] {
-        Statement::Query(query) => {
-            let query = query.clone();
-            let body = query.body;
-            match *body {
-                SetExpr::Select(select) => {
-                    let mut hashmap: HashMap<String, String> = HashMap::new();
-                    hashmap.insert(String::from("match"), String::new());
-                    hashmap.insert(String::from("optional match"), String::new());
-                    hashmap.insert(String::from("return"), String::new());
-                    if let Err(_) = from_statement(select.from, &mut hashmap) {
-                        return Err(String::from(
-                            "ERROR : when try to transform the 'FROM' clause.",
-                        ));
-                    }
-                    if let Err(_) = select_statement(select.projection, &mut hashmap) {
-                        return Err(String::from(
-                            "ERROR : when try to transform the 'SELECT' clause.",
-                        ));
-                    }
-                    let mut result = String::from("match ");
-                    result.push_str(hashmap.get("match").unwrap());
-                    result.pop();
-                    let optional_match = hashmap.get("optional match").unwrap();
-                    if optional_match != "" {
-                        result.push_str(&format!(" optional match {}", optional_match));
-                        result.pop();
+/// Return the names PostgreSQL gives to the columns of the SQL query, ```None``` for the wildcards.
+pub fn postgresql_column_names(sql_query: &str) -> Result<Vec<Option<String>>, String> {
+    let dialect = GenericDialect {};
+    let ast = Parser::parse_sql(&dialect, sql_query)
+        .map_err(|error| format!("ERROR : when try to parse the SQL query.\n{}", error))?;
+    let select = match ast.as_slice() {
+        [Statement::Query(query)] => match query.body.as_ref() {
+            SetExpr::Select(select) => select,
+            _ => return Err(String::from("Your query is not yet supported by the funtion.")),
+        },
+        _ => return Err(String::from("ERROR : This function expects exactly one SQL query.")),
+    };
+    Ok(select
+        .projection
+        .iter()
+        .map(|select_item| match select_item {
+            SelectItem::UnnamedExpr(expr) => Some(postgresql_column_name(expr)),
+            SelectItem::ExprWithAlias { alias, .. } => Some(ident_name(alias)),
+            _ => None,
+        })
+        .collect())
+}
+
+/// Translate the SQL query into a Cypher query.
+pub fn generate_cypher_query(sql_query: &str, mapping: &Mapping) -> Result<String, String> {
+    translate_query(sql_query, mapping).map(|query| query.to_string())
+}
+
+/// A statement of a SQL file : the line number where it starts and the statement without its ```;```.
+pub type SqlStatement = (usize, String);
+
+/// Split the content of a SQL file into its statements.<br>
+/// Return the line number where each statement start and the statement without its ```;```.
+/// The ```;``` in the strings, quoted identifiers and comments are ignored.
+pub fn split_sql_statements(content: &str) -> Vec<SqlStatement> {
+    let (mut statements, rest) = split_terminated_statements(content);
+    statements.extend(rest);
+    statements
+}
+
+/// Split the content into the statements terminated by a ```;``` and the rest, the statement not terminated yet
+/// without its comments (with the start of its string or comment when they aren't closed).
+pub fn split_terminated_statements(content: &str) -> (Vec<SqlStatement>, Option<SqlStatement>) {
+    let mut statements = Vec::new();
+    let mut current = String::new();
+    let mut start_line = 1;
+    let mut line = 1;
+    let mut chars = content.chars().peekable();
+    let mut quote: Option<char> = None;
+
+    while let Some(c) = chars.next() {
+        if current.trim().is_empty() && !c.is_whitespace() {
+            start_line = line;
+        }
+        if c == '\n' {
+            line += 1;
+        }
+        match quote {
+            Some(q) => {
+                current.push(c);
+                if c == q {
+                    quote = None;
+                }
+            }
+            None => match c {
+                '\'' | '"' => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                '-' if chars.peek() == Some(&'-') => {
+                    // Skip the comment until the end of the line.
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            line += 1;
+                            current.push(c);
+                            break;
+                        }
                     }
-                    result.push_str(&format!(" return {}", hashmap.get("return").unwrap()));
-
-                    const KEYWORDS: [&str; 3] = ["match", "optional match", "return"];
-                    let mut others_relations = String::new();
-                    for (key, value) in hashmap.iter() {
-                        if !KEYWORDS.contains(&String::clone(key).as_str())
-                            && !result.contains(value)
-                        {
-                            println!("\nValue : {value}");
-                            others_relations.push_str(&format!("{},", value));
+                }
+                '/' if chars.peek() == Some(&'*') => {
+                    let mut previous = ' ';
+                    let mut closed = false;
+                    chars.next();
+                    for c in chars.by_ref() {
+                        if c == '\n' {
+                            line += 1;
+                        }
+                        if previous == '*' && c == '/' {
+                            closed = true;
+                            break;
                         }
+                        previous = c;
                     }
-                    others_relations.pop();
-                    result.insert_str(5, &format!(" {} ", others_relations));
-                    result.pop();
-                    result.push_str(";");
-
-                    return Ok(result);
+                    current.push_str(if closed { " " } else { "/*" });
                 }
-                _ => {
-                    return Err(String::from(
-                        "Your query is not yet supported by the funtion.",
-                    ))
+                ';' => {
+                    if !current.trim().is_empty() {
+                        statements.push((start_line, String::from(current.trim())));
+                    }
+                    current.clear();
                 }
-            }
+                _ => current.push(c),
+            },
         }
-        _ => Err(String::from("This function only support SQL queries.")),
     }
+    let rest = match current.trim() {
+        "" => None,
+        rest => Some((start_line, String::from(rest))),
+    };
+    (statements, rest)
 }
 
 #[test]
@@ -254,10 +1088,94 @@ fn test_ast_sql() {
 #[test]
 fn test_generation() {
     let sql_query = r#"SELECT t.order from toto t left join juju j on t.order=j.id;"#;
-    let cypher_query = generate_cypher_query(sql_query).unwrap();
+    let cypher_query = generate_cypher_query(sql_query, &Mapping::default()).unwrap();
     println!("\nSQL : {}\nCypher : {}",sql_query,cypher_query);
+    assert_eq!(
+        cypher_query,
+        "match (t:TOTO)\noptional match (t)-[:TOTO_ref_ORDER]->(j:JUJU)\nreturn t.order;"
+    );
 
     let sql_query = r#"SELECT t.order from toto t inner join juju j on t.order=j.id;"#;
-    let cypher_query = generate_cypher_query(sql_query).unwrap();
+    let cypher_query = generate_cypher_query(sql_query, &Mapping::default()).unwrap();
     println!("\nSQL : {}\nCypher : {}",sql_query,cypher_query);
-}
\ No newline at end of file
+    assert_eq!(
+        cypher_query,
+        "match (t:TOTO)-[:TOTO_ref_ORDER]->(j:JUJU)\nreturn t.order;"
+    );
+
+    // The type of the relationship chosen in the mapping is used.
+    let mapping = Mapping::from_value(serde_json::json!({
+        "tables": { "toto": { "columns": { "order": { "relationship": "PLACED" } } } }
+    }));
+    assert_eq!(
+        generate_cypher_query(sql_query, &mapping).unwrap(),
+        "match (t:TOTO)-[:PLACED]->(j:JUJU)\nreturn t.order;"
+    );
+}
+
+#[test]
+fn test_generation_clauses() {
+    let sql_query = "SELECT company_name, contact_name FROM customers WHERE city LIKE 'A%' OR country NOT IN ('Germany', 'Mexico') ORDER BY company_name DESC LIMIT 10";
+    assert_eq!(
+        generate_cypher_query(sql_query, &Mapping::default()).unwrap(),
+        "match (customers:CUSTOMERS)\n\
+         where customers.city starts with 'A' or not customers.country in ['Germany', 'Mexico']\n\
+         return customers.company_name, customers.contact_name\n\
+         order by customers.company_name desc\nlimit 10;"
+    );
+
+    let sql_query = "SELECT customer_id, sum(freight) AS Total FROM orders GROUP BY customer_id HAVING sum(freight) > $1";
+    let query = translate_query(sql_query, &Mapping::default()).unwrap();
+    assert_eq!(
+        query.to_string(),
+        "match (orders:ORDERS)\n\
+         with orders.customer_id as column1, sum(orders.freight) as total\n\
+         where total > $p1\n\
+         return column1 as `orders.customer_id`, total;"
+    );
+    assert_eq!(query.parameters(), vec![String::from("p1")]);
+
+    // The NULL arguments of concat are skipped and the numbers are joined as text.
+    assert_eq!(
+        generate_cypher_query("SELECT concat(o.ship_name, NULL) FROM orders o", &Mapping::default()).unwrap(),
+        "match (o:ORDERS)\nreturn coalesce(toString(o.ship_name), '') + coalesce(toString(null), '');"
+    );
+    assert_eq!(
+        generate_cypher_query("SELECT concat(o.order_id, o.freight) FROM orders o", &Mapping::default()).unwrap(),
+        "match (o:ORDERS)\nreturn coalesce(toString(o.order_id), '') + coalesce(toString(o.freight), '');"
+    );
+}
+
+#[test]
+fn test_translate_logarithms() {
+    let expr = translate_expression("ln(price) + log(price)", "products", "m").unwrap();
+    assert_eq!(expr.to_string(), "log(m.price) + log10(m.price)");
+    let expr = translate_expression("log(2, price)", "products", "m").unwrap();
+    assert_eq!(expr.to_string(), "log(m.price) / log(2)");
+}
+
+#[test]
+fn test_split_sql_statements() {
+    let content = "SELECT 'a;b' FROM t;\n-- comment ; here\nSELECT *\nFROM \"x;y\" /* ; */;\n\nSELECT 1";
+    assert_eq!(
+        split_sql_statements(content),
+        vec![
+            (1, String::from("SELECT 'a;b' FROM t")),
+            (3, String::from("SELECT *\nFROM \"x;y\"")),
+            (6, String::from("SELECT 1")),
+        ]
+    );
+}
+
+#[test]
+fn test_translate_expression() {
+    let expr = translate_expression("((unit_price >= (0)::numeric) AND (discount <= 1.5))", "products", "m").unwrap();
+    assert_eq!(expr.to_string(), "m.unit_price >= 0 and m.discount <= 1.5");
+    let expr = translate_expression(
+        "((status)::text = ANY ((ARRAY['open'::character varying, 'closed'::character varying])::text[]))",
+        "orders",
+        "m",
+    )
+    .unwrap();
+    assert_eq!(expr.to_string(), "toString(m.status) in ['open', 'closed']");
+}
@@ -0,0 +1,175 @@
+//! This module contains the ```translate``` command : an interactive SQL-to-Cypher REPL and the
+//! batch translation of a SQL file into a Cypher file.
+
+use std::fs;
+use std::io::{self, BufRead, Write};
+
+use crate::mapping::Mapping;
+use crate::neo4j::Neo4j;
+use crate::postgresql::PostgreSQL;
+use crate::translate::*;
+use crate::utils::*;
+
+const REPL_HELP: &str = "Enter a SQL query terminated by ';' (it can span multiple lines).
+Commands :
+  \\q         Quit the REPL
+  \\x         Enable/disable the execution of the queries on PostgreSQL and Neo4j
+  \\h         Display this help";
+
+/// The width of the SQL column when the results are displayed side by side.
+const RESULT_WIDTH: usize = 80;
+
+/// Return the statements of the buffer of the REPL once the last one is terminated by a ```;```, the comments
+/// after it are ignored (```SELECT 1; -- the first query```). Return ```None``` while a statement, a string or a
+/// comment isn't terminated.
+fn complete_statements(buffer: &str) -> Option<Vec<String>> {
+    match split_terminated_statements(buffer) {
+        (statements, None) => Some(statements.into_iter().map(|(_, statement)| statement).collect()),
+        (_, Some(_)) => None,
+    }
+}
+
+/// Run the interactive REPL on the standard input.<br>
+/// When the ```databases``` are given, the execution of the queries can be enabled with ```\x``` :
+/// the SQL query is run on PostgreSQL, the Cypher query on Neo4j and the results are displayed side by side.
+pub fn interactive(databases: Option<(&PostgreSQL, &Neo4j)>, mapping: &Mapping) -> Result<String, String> {
+    println!("Neo4j-Migrator - SQL to Cypher translation\n{}\n", REPL_HELP);
+    let mut execute = databases.is_some();
+    let mut buffer = String::new();
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("{}", if buffer.is_empty() { "sql> " } else { "...> " });
+        io::stdout().flush().map_err(|error| format!("{}", error))?;
+
+        let line = match lines.next() {
+            Some(line) => line.map_err(|error| format!("ERROR : when try to read the user input.\n{}", error))?,
+            None => break,
+        };
+
+        if buffer.is_empty() {
+            match line.trim() {
+                "" => continue,
+                "\\q" | "exit" | "quit" => break,
+                "\\h" | "help" => {
+                    println!("{}\n", REPL_HELP);
+                    continue;
+                }
+                "\\x" => {
+                    if databases.is_some() {
+                        execute = !execute;
+                        println!("Execution of the queries : {}\n", if execute { "ON" } else { "OFF" });
+                    } else {
+                        println!("The execution isn't available, run the command with '--execute'.\n");
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+        }
+
+        buffer.push_str(&line);
+        buffer.push('\n');
+        let Some(statements) = complete_statements(&buffer) else {
+            continue;
+        };
+
+        for sql_query in statements {
+            match generate_cypher_query(&sql_query, mapping) {
+                Ok(cypher_query) => {
+                    println!("\n{}\n", cypher_query);
+                    if let (true, Some((db_postgresql, db_neo4j))) = (execute, databases) {
+                        let sql_result = db_postgresql
+                            .execute_query(&sql_query, false)
+                            .unwrap_or_else(|error| error);
+                        let cypher_result = db_neo4j
+                            .execute_query(&cypher_query)
+                            .unwrap_or_else(|error| error);
+                        println!(
+                            "{}",
+                            side_by_side("PostgreSQL", &sql_result, "Neo4j", cypher_result.trim_start(), RESULT_WIDTH)
+                        );
+                    }
+                }
+                Err(error) => println!("\n{}\n", error),
+            }
+        }
+        buffer.clear();
+    }
+    Ok(String::from("\nBye !"))
+}
+
+/// Translate all the SQL queries of the file ```sql_path``` and save them in the Cypher file ```cypher_path```.<br>
+/// The queries that can't be translated are kept as comments with their error in the Cypher file,
+/// and listed in the result.
+pub fn translate_file(sql_path: &str, cypher_path: &str, mapping: &Mapping) -> Result<String, String> {
+    let content = fs::read_to_string(sql_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", sql_path, error))?;
+    let statements = split_sql_statements(&content);
+
+    let mut cypher_content = format!("// Cypher queries translated from {} by Neo4j-Migrator\n", sql_path);
+    let mut errors = Vec::new();
+    for (index, (line, sql_query)) in statements.iter().enumerate() {
+        cypher_content.push_str(&format!("\n// Query {} (line {})\n", index + 1, line));
+        match generate_cypher_query(sql_query, mapping) {
+            Ok(cypher_query) => {
+                cypher_content.push_str(&cypher_query);
+                cypher_content.push('\n');
+            }
+            Err(error) => {
+                for error_line in error.lines() {
+                    cypher_content.push_str(&format!("// {}\n", error_line));
+                }
+                for sql_line in sql_query.lines() {
+                    cypher_content.push_str(&format!("// {}\n", sql_line));
+                }
+                errors.push(format!(
+                    "Query {} (line {}) : {}",
+                    index + 1,
+                    line,
+                    error.replace('\n', " ")
+                ));
+            }
+        }
+    }
+    write_file(cypher_content, cypher_path)?;
+
+    let mut result = format!(
+        "Successfully translate {}/{} queries into {}",
+        statements.len() - errors.len(),
+        statements.len(),
+        cypher_path
+    );
+    if !errors.is_empty() {
+        result.push_str(&format!("\n\n{} queries can't be translated :\n{}", errors.len(), errors.join("\n")));
+    }
+    Ok(result)
+}
+
+#[test]
+fn test_complete_statements() {
+    assert_eq!(complete_statements("SELECT 1; -- x\n"), Some(vec![String::from("SELECT 1")]));
+    assert_eq!(
+        complete_statements("SELECT 1;\nSELECT 2 /* the second ; */;\n"),
+        Some(vec![String::from("SELECT 1"), String::from("SELECT 2")])
+    );
+    assert_eq!(complete_statements("-- only a comment\n"), Some(vec![]));
+    assert_eq!(complete_statements("SELECT 1\n"), None);
+    assert_eq!(complete_statements("SELECT 1; SELECT 2\n"), None);
+    assert_eq!(complete_statements("SELECT 'a;\n"), None);
+    assert_eq!(complete_statements("SELECT 1; /* not closed ;\n"), None);
+
+    // The queries of a file are translated one by one, the errors are kept as comments.
+    let folder = std::env::temp_dir();
+    let sql_path = format!("{}", folder.join("neo4j_migrator_translate.sql").display());
+    let cypher_path = format!("{}", folder.join("neo4j_migrator_translate.cql").display());
+    write_file(String::from("SELECT name FROM customers; -- the names\nDELETE FROM customers;\n"), &sql_path).unwrap();
+    let result = translate_file(&sql_path, &cypher_path, &Mapping::default()).unwrap();
+    assert!(result.starts_with("Successfully translate 1/2 queries"));
+    let cypher = fs::read_to_string(&cypher_path).unwrap();
+    assert!(cypher.contains("// Query 1 (line 1)\nmatch (customers:CUSTOMERS)\nreturn customers.name;\n"));
+    assert!(cypher.contains("// Query 2 (line 2)\n"));
+    let _ = fs::remove_file(&sql_path);
+    let _ = fs::remove_file(&cypher_path);
+}
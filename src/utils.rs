@@ -1,4 +1,26 @@
-use std::fs;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+
+use polars::prelude::{
+    Column, CsvReadOptions, CsvWriter, DataFrame, DataType, IntoColumn, NamedFrom, PlSmallStr, SerReader, SerWriter, Series,
+};
+
+/// The warnings displayed during the migration, they are written in the migration report.
+static WARNINGS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Display the warning and keep it for the migration report.
+pub fn warning(message: String) {
+    println!("\nWARNING : {}", message);
+    if let Ok(mut warnings) = WARNINGS.lock() {
+        warnings.push(message);
+    }
+}
+
+/// Return the warnings displayed since the last call.
+pub fn take_warnings() -> Vec<String> {
+    WARNINGS.lock().map(|mut warnings| std::mem::take(&mut *warnings)).unwrap_or_default()
+}
 
 pub fn clean_directory(folder_path: &str) -> Result<String, String> {
     //! Delete all the CSV files in the folder in input.
@@ -17,3 +39,214 @@ pub fn clean_directory(folder_path: &str) -> Result<String, String> {
     }
     Ok("Successfully clean the directory !".to_string())
 }
+
+/// This simple function write the ```content``` in the ```file_path```<br>
+/// It use the ```OpenOptions``` struct with the following args :<br>
+/// write = true ; create = true ; truncate = true
+pub fn write_file(content: String, file_path: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(file_path)
+        .map_err(|error| format!("ERROR : when try to open the follosing file : {}\n {}", file_path, error))?;
+    match file.write_all(content.as_bytes()) {
+        Ok(_) => Ok(()),
+        Err(error) => Err(format!("ERROR : when try to write in {}\n {}", file_path, error))
+    }
+}
+
+/// Create a column of strings for the Dataframes written in the CSV files.
+pub fn string_column<'a>(name: &str, values: impl Iterator<Item = &'a str>) -> Column {
+    Series::new(name.into(), values.collect::<Vec<&str>>()).into_column()
+}
+
+/// Append the Dataframe without its header in the CSV file.
+pub fn append_dataframe(mut df: DataFrame, file_path: &str) -> Result<(), String> {
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(file_path)
+        .map_err(|error| format!("{}", error))?;
+    CsvWriter::new(&mut file)
+        .include_header(false)
+        .with_separator(b';')
+        .finish(&mut df)
+        .map_err(|error| format!("ERROR : when try to write the Dataframe in {}\n{}", file_path, error))
+}
+
+/// A part of a file shared by the items of a pool of workers : (file, part).
+pub type FilePart = (String, String);
+
+/// Return the file where the ```part``` of a pool of workers (a table, a foreign key) buffers the rows it appends
+/// to ```file_path```, a file shared with other parts.
+pub fn part_path(file_path: &str, part: &str) -> String {
+    format!("{}.{}.part", file_path, part)
+}
+
+/// Append the parts to their files (file, part) in their order then remove them : the rows of the shared files
+/// are in the order of the items of the pool, whatever the order in which they are done.
+pub fn merge_parts(parts: &[FilePart]) -> Result<(), String> {
+    for (file_path, part_path) in parts {
+        let mut part = fs::File::open(part_path)
+            .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", part_path, error))?;
+        let mut file = OpenOptions::new()
+            .append(true)
+            .open(file_path)
+            .map_err(|error| format!("ERROR : when try to open the follosing file : {}\n {}", file_path, error))?;
+        io::copy(&mut part, &mut file)
+            .map_err(|error| format!("ERROR : when try to write in {}\n {}", file_path, error))?;
+        let _ = fs::remove_file(part_path);
+    }
+    Ok(())
+}
+
+/// Read the CSV file by batches of about ```batch_bytes``` bytes in memory (```usize::MAX``` to read it at once),
+/// only the ```columns``` are read if they are given. The ```process``` is called with each batch and the index
+/// of its first row in the file, at least once. Return the number of rows of the file.
+pub fn read_csv_batches(
+    file_path: &str,
+    columns: Option<&[&str]>,
+    batch_bytes: usize,
+    mut process: impl FnMut(DataFrame, usize) -> Result<(), String>,
+) -> Result<usize, String> {
+    let mut reader = CsvReadOptions::default()
+        .with_has_header(true)
+        .with_columns(columns.map(|columns| columns.iter().map(|column| PlSmallStr::from(*column)).collect()))
+        .try_into_reader_with_file_path(Some(file_path.into()))
+        .map_err(|e| format!("ERROR : when try to read the file {}\n{}", file_path, e))?;
+    let mut batches = reader
+        .batched_borrowed()
+        .map_err(|e| format!("ERROR : when try to read the file {}\n{}", file_path, e))?;
+    let mut rows = 0;
+    let mut batch: Option<DataFrame> = None;
+    loop {
+        let chunks = batches
+            .next_batches(1)
+            .map_err(|e| format!("ERROR : when try to read the file {}\n{}", file_path, e))?;
+        let is_last = chunks.is_none();
+        for chunk in chunks.into_iter().flatten() {
+            batch = Some(match batch {
+                Some(mut batch) => {
+                    batch.vstack_mut(&chunk).map_err(|e| format!("{}", e))?;
+                    batch
+                }
+                None => chunk,
+            });
+        }
+        let is_full = batch.as_ref().is_some_and(|batch| batch.estimated_size() >= batch_bytes);
+        if is_last || is_full {
+            if let Some(mut batch) = batch.take() {
+                batch.rechunk_mut();
+                let height = batch.height();
+                process(batch, rows)?;
+                rows += height;
+            }
+        }
+        if is_last {
+            break;
+        }
+    }
+    // A table without rows is still processed once, with its columns.
+    if rows == 0 {
+        let df = CsvReadOptions::default()
+            .with_has_header(true)
+            .with_columns(columns.map(|columns| columns.iter().map(|column| PlSmallStr::from(*column)).collect()))
+            .try_into_reader_with_file_path(Some(file_path.into()))
+            .map_err(|e| format!("ERROR : when try to read the file {}\n{}", file_path, e))?
+            .finish()
+            .map_err(|e| format!("ERROR : when try to read the file {}\n{}", file_path, e))?;
+        process(df, 0)?;
+    }
+    Ok(rows)
+}
+
+/// Return the values of the column as strings.
+pub fn string_values(df: &DataFrame, column_name: &str) -> Result<Vec<Option<String>>, String> {
+    let column = df
+        .column(column_name)
+        .map_err(|e| format!("{}", e))?
+        .cast(&DataType::String)
+        .map_err(|e| format!("{}", e))?;
+    Ok(column
+        .str()
+        .map_err(|e| format!("{}", e))?
+        .into_iter()
+        .map(|value| value.map(String::from))
+        .collect())
+}
+
+/// Display the two texts in two columns, separated by a ```|```.<br>
+/// The lines of the left column are truncated to ```width``` characters.
+pub fn side_by_side(left_title: &str, left: &str, right_title: &str, right: &str, width: usize) -> String {
+    let left_lines = left.lines().collect::<Vec<&str>>();
+    let right_lines = right.lines().collect::<Vec<&str>>();
+    let width = left_lines
+        .iter()
+        .map(|line| line.chars().count())
+        .chain([left_title.chars().count()])
+        .max()
+        .unwrap_or(0)
+        .min(width);
+
+    let mut result = format!("{:<width$} | {}\n", left_title, right_title, width = width);
+    result.push_str(&format!("{}-+-{}\n", "-".repeat(width), "-".repeat(right_title.len().max(1))));
+    for index in 0..left_lines.len().max(right_lines.len()) {
+        let left_line = left_lines
+            .get(index)
+            .map(|line| line.chars().take(width).collect::<String>())
+            .unwrap_or_default();
+        let right_line = right_lines.get(index).copied().unwrap_or_default();
+        result.push_str(&format!("{:<width$} | {}\n", left_line, right_line, width = width));
+    }
+    result
+}
+
+#[test]
+fn test_read_csv_batches() {
+    let file_path = format!("{}", std::env::temp_dir().join("neo4j_migrator_batches.csv").display());
+    let content = (0..5000).map(|index| format!("{},value {}\n", index, index)).collect::<String>();
+    write_file(format!("id,name\n{}", content), &file_path).unwrap();
+
+    let mut offsets = Vec::new();
+    let mut ids = Vec::new();
+    let rows = read_csv_batches(&file_path, Some(&["id"]), 1024, |df, offset| {
+        assert_eq!(df.get_column_names(), vec!["id"]);
+        offsets.push(offset);
+        ids.extend(string_values(&df, "id")?);
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(rows, 5000);
+    assert!(offsets.len() > 1 && offsets[0] == 0);
+    assert_eq!(ids, (0..5000).map(|index| Some(index.to_string())).collect::<Vec<Option<String>>>());
+
+    let mut batches = 0;
+    let rows = read_csv_batches(&file_path, None, usize::MAX, |_, _| {
+        batches += 1;
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!(rows, 5000);
+    assert_eq!(batches, 1);
+
+    write_file(String::from("id,name\n"), &file_path).unwrap();
+    let mut columns = Vec::new();
+    let rows = read_csv_batches(&file_path, None, usize::MAX, |df, _| {
+        columns.push(df.get_column_names().len());
+        Ok(())
+    })
+    .unwrap();
+    assert_eq!((rows, columns), (0, vec![2]));
+
+    // The parts are appended in their order, whatever the order in which they are written.
+    let parts = vec![
+        (String::clone(&file_path), part_path(&file_path, "0")),
+        (String::clone(&file_path), part_path(&file_path, "1")),
+    ];
+    write_file(String::from("1,b\n"), &parts[1].1).unwrap();
+    write_file(String::from("0,a\n"), &parts[0].1).unwrap();
+    merge_parts(&parts).unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "id,name\n0,a\n1,b\n");
+    assert!(!std::path::Path::new(&parts[0].1).exists());
+    let _ = fs::remove_file(&file_path);
+}
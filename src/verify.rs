@@ -0,0 +1,508 @@
+//! This module verify a migration once the files are imported in Neo4j : for each table the number of rows
+//! is compared to the number of nodes of its label, for each foreign key the number of values to the number
+//! of relationships. The rows sampled by primary key are compared to their nodes and a checksum of the
+//! values, that doesn't depend on the order of the rows, is computed on both databases : the rows are read
+//! in pages ordered by primary key, only the sum of their hashes is kept.
+
+use std::fs;
+
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::cypher::escape_string;
+use crate::equivalence::*;
+use crate::neo4j::Neo4j;
+use crate::postgresql::PostgreSQL;
+
+/// The number of rows read by query to compute the checksums.
+const CHECKSUM_PAGE_SIZE: usize = 10000;
+
+/// The relative difference allowed between the numbers of the sampled rows and of their nodes.
+const TOLERANCE: f64 = 1e-6;
+
+/// The PostgreSQL types whose values are compared : their text is the same in both databases once
+/// normalized like in the equivalence tests.
+const COMPARABLE_TYPES: [&str; 12] = [
+    "smallint",
+    "integer",
+    "bigint",
+    "numeric",
+    "real",
+    "double precision",
+    "boolean",
+    "text",
+    "character varying",
+    "character",
+    "date",
+    "timestamp without time zone",
+];
+
+/// What is verified for a table, read from the meta-data and the foreign keys file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableVerification {
+    pub table: String,
+    pub label: String,
+    /// The rows of a partitioned table are in its partitions.
+    pub partitioned: bool,
+    /// The columns whose values are compared.
+    pub columns: Vec<String>,
+    /// The primary key and its type, used to sample the rows and to read them by page, when it's a single
+    /// comparable column.
+    pub primary_key: Option<(String, String)>,
+    /// The foreign keys migrated as relationships, with the type of their relationships.
+    pub foreign_keys: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckKind {
+    Count,
+    Relationships,
+    Sample,
+    Checksum,
+}
+
+/// The result of one verification.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Check {
+    pub kind: CheckKind,
+    /// The label, or the type of the relationships.
+    pub target: String,
+    pub postgresql: String,
+    pub neo4j: String,
+    pub passed: bool,
+    pub details: Option<String>,
+}
+
+impl CheckKind {
+    fn name(&self) -> &'static str {
+        match self {
+            CheckKind::Count => "count",
+            CheckKind::Relationships => "relationships",
+            CheckKind::Sample => "sample",
+            CheckKind::Checksum => "checksum",
+        }
+    }
+}
+
+/// Read the tables to verify in the meta-data and the foreign keys file written by ```generate_import_files```.
+pub fn read_verifications(meta_data_path: &str, foreign_key_path: &str) -> Result<Vec<TableVerification>, String> {
+    let content = fs::read_to_string(meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    let foreign_keys = fs::read_to_string(foreign_key_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", foreign_key_path, error))?;
+    let foreign_keys = foreign_keys
+        .lines()
+        .filter_map(|line| {
+            let elements = line.split(';').collect::<Vec<&str>>();
+            let (label, _) = elements.first()?.split_once("_ref_")?;
+            let column = elements.get(1)?;
+            let relationship_type = match elements.get(3) {
+                Some(relationship_type) => String::from(relationship_type.trim()),
+                None => format!("{}_ref_{}", label, column.to_uppercase()),
+            };
+            Some((String::from(label), String::from(*column), relationship_type))
+        })
+        .collect::<Vec<(String, String, String)>>();
+
+    let mut verifications = Vec::new();
+    for table in json_object.as_array().into_iter().flatten() {
+        let table_name = table["table_name"].as_str().ok_or_else(|| {
+            format!("Error when try to get the 'table_name' field in {}", table)
+        })?;
+        let label = table_name.to_uppercase();
+        let mut columns = Vec::new();
+        let mut primary_keys = Vec::new();
+        let mut types = Vec::new();
+        for column in table["columns"].as_array().into_iter().flatten() {
+            let Some(column_name) = column["column_name"].as_str() else {
+                continue;
+            };
+            if column["primary_key"].as_bool() == Some(true) {
+                primary_keys.push(column_name);
+            }
+            let data_type = column["domain"]["base_type"]
+                .as_str()
+                .or(column["data_type"].as_str())
+                .unwrap_or_default();
+            // The base type of a domain can have modifiers, like 'character varying(50)'.
+            let data_type = data_type.split('(').next().unwrap_or_default().trim();
+            if column["foreign_key"].is_null()
+                && column["enum_values"].is_null()
+                && COMPARABLE_TYPES.contains(&data_type)
+            {
+                columns.push(String::from(column_name));
+                types.push(String::from(data_type));
+            }
+        }
+        let primary_key = match primary_keys.as_slice() {
+            [primary_key] => columns
+                .iter()
+                .position(|column| column == primary_key)
+                .map(|position| (String::from(*primary_key), String::clone(&types[position]))),
+            _ => None,
+        };
+        verifications.push(TableVerification {
+            table: String::from(table_name),
+            foreign_keys: foreign_keys
+                .iter()
+                .filter(|(fk_label, _, _)| *fk_label == label)
+                .map(|(_, column, relationship_type)| (String::clone(column), String::clone(relationship_type)))
+                .collect(),
+            label,
+            partitioned: table["partitioned"].as_bool().unwrap_or(false),
+            columns,
+            primary_key,
+        });
+    }
+    Ok(verifications)
+}
+
+/// Add the rows to the checksum, it doesn't depend on their order : the hashes of the normalized rows
+/// are summed, so the rows can be added page by page.
+pub fn add_to_checksum(checksum: u64, rows: &[Vec<Cell>]) -> u64 {
+    rows.iter().fold(checksum, |sum, row| {
+        let hash = Sha256::digest(format!("{:?}", row).as_bytes());
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&hash[..8]);
+        sum.wrapping_add(u64::from_be_bytes(bytes))
+    })
+}
+
+/// Return the raw value of a key read in Neo4j as a Cypher literal : the temporal values aren't quoted by
+/// *cypher-shell*.
+fn cypher_key_literal(value: &str, data_type: &str) -> String {
+    match data_type {
+        _ if value.starts_with('"') => String::from(value),
+        "date" => format!("date('{}')", value),
+        "timestamp without time zone" => format!("localdatetime('{}')", value),
+        _ => String::from(value),
+    }
+}
+
+/// Compute the checksum of the rows of the table in PostgreSQL, read by page after the last key read.
+fn postgresql_checksum(
+    db_postgresql: &PostgreSQL,
+    verification: &TableVerification,
+    sql_columns: &str,
+    primary_key: &str,
+) -> Result<String, String> {
+    let position = verification.columns.iter().position(|column| column == primary_key).unwrap_or_default();
+    let mut checksum = 0u64;
+    let mut last_key: Option<String> = None;
+    loop {
+        let after = match &last_key {
+            Some(key) => format!(" WHERE \"{}\" > '{}'", primary_key, key.replace('\'', "''")),
+            None => String::new(),
+        };
+        let (_, records) = parse_psql_records(&db_postgresql.execute_query(
+            &format!(
+                "SELECT {} FROM {}{} ORDER BY \"{}\" LIMIT {}",
+                sql_columns, verification.table, after, primary_key, CHECKSUM_PAGE_SIZE
+            ),
+            true,
+        )?)?;
+        let rows = records
+            .iter()
+            .map(|record| record.iter().map(|value| psql_cell(value.as_deref())).collect())
+            .collect::<Vec<Vec<Cell>>>();
+        checksum = add_to_checksum(checksum, &rows);
+        match records.last().and_then(|record| record[position].clone()) {
+            Some(key) if records.len() == CHECKSUM_PAGE_SIZE => last_key = Some(key),
+            _ => return Ok(format!("{:016x}", checksum)),
+        }
+    }
+}
+
+/// Compute the checksum of the nodes of the label in Neo4j, read by page after the last key read.
+fn neo4j_checksum(
+    db_neo4j: &Neo4j,
+    verification: &TableVerification,
+    cypher_columns: &str,
+    primary_key: &(String, String),
+) -> Result<String, String> {
+    let (primary_key, data_type) = primary_key;
+    let position = verification.columns.iter().position(|column| column == primary_key).unwrap_or_default();
+    let mut checksum = 0u64;
+    let mut last_key: Option<String> = None;
+    loop {
+        let after = match &last_key {
+            Some(key) => format!(" AND n.`{}` > {}", primary_key, cypher_key_literal(key, data_type)),
+            None => String::new(),
+        };
+        let (_, records) = parse_cypher_records(&db_neo4j.execute_query(&format!(
+            "MATCH (n:{}) WHERE n.`{}` IS NOT NULL{} RETURN {} ORDER BY n.`{}` LIMIT {}",
+            verification.label, primary_key, after, cypher_columns, primary_key, CHECKSUM_PAGE_SIZE
+        ))?)?;
+        let rows = records
+            .iter()
+            .map(|record| record.iter().map(|value| cypher_cell(value)).collect())
+            .collect::<Vec<Vec<Cell>>>();
+        checksum = add_to_checksum(checksum, &rows);
+        match records.last() {
+            Some(record) if records.len() == CHECKSUM_PAGE_SIZE => last_key = Some(String::clone(&record[position])),
+            _ => return Ok(format!("{:016x}", checksum)),
+        }
+    }
+}
+
+/// Return the value as a Cypher string, the sampled nodes are found with ```toString()``` on their key
+/// because the text of PostgreSQL doesn't tell if ```12``` is a number or a string.
+fn key_literal(cell: &Cell) -> String {
+    match cell {
+        Cell::Null => String::from("null"),
+        Cell::Boolean(value) => format!("'{}'", value),
+        Cell::Number(value) => format!("'{}'", value),
+        Cell::Text(value) => format!("'{}'", escape_string(value)),
+    }
+}
+
+/// Return the single value of the result set, like a count.
+fn single_value(result: &ResultSet) -> String {
+    match result.rows.first().and_then(|row| row.first()) {
+        Some(Cell::Number(value)) => value.to_string(),
+        Some(cell) => format!("{:?}", cell),
+        None => String::from("NULL"),
+    }
+}
+
+fn run_postgresql(db_postgresql: &PostgreSQL, query: &str) -> Result<ResultSet, String> {
+    parse_psql_csv(&db_postgresql.execute_query(query, true)?)
+}
+
+fn run_neo4j(db_neo4j: &Neo4j, query: &str) -> Result<ResultSet, String> {
+    parse_cypher_plain(&db_neo4j.execute_query(query)?)
+}
+
+/// Compare the number of rows and the number of nodes (or relationships).
+fn count_check(kind: CheckKind, target: &str, postgresql: Result<ResultSet, String>, neo4j: Result<ResultSet, String>) -> Check {
+    match (postgresql, neo4j) {
+        (Ok(postgresql), Ok(neo4j)) => {
+            let (postgresql, neo4j) = (single_value(&postgresql), single_value(&neo4j));
+            Check { kind, target: String::from(target), passed: postgresql == neo4j, postgresql, neo4j, details: None }
+        }
+        (Err(error), _) | (_, Err(error)) => error_check(kind, target, error),
+    }
+}
+
+fn error_check(kind: CheckKind, target: &str, error: String) -> Check {
+    Check {
+        kind,
+        target: String::from(target),
+        postgresql: String::new(),
+        neo4j: String::new(),
+        passed: false,
+        details: Some(error),
+    }
+}
+
+/// Run the verifications of the tables on both databases, with ```sample_size``` rows sampled by table.
+pub fn run_verify(
+    db_postgresql: &PostgreSQL,
+    db_neo4j: &Neo4j,
+    verifications: &[TableVerification],
+    sample_size: usize,
+) -> Vec<Check> {
+    let mut checks = Vec::new();
+    for verification in verifications {
+        let label = &verification.label;
+        // The nodes of a label include the rows of the tables that inherit it, like the table without ONLY.
+        checks.push(count_check(
+            CheckKind::Count,
+            label,
+            run_postgresql(db_postgresql, &format!("SELECT count(*) FROM {}", verification.table)),
+            run_neo4j(db_neo4j, &format!("MATCH (n:{}) RETURN count(n)", label)),
+        ));
+
+        // The relationships of a table are generated from its own rows only.
+        let only = if verification.partitioned { "" } else { "ONLY " };
+        for (column, relationship_type) in &verification.foreign_keys {
+            checks.push(count_check(
+                CheckKind::Relationships,
+                relationship_type,
+                run_postgresql(db_postgresql, &format!("SELECT count(\"{}\") FROM {}{}", column, only, verification.table)),
+                run_neo4j(db_neo4j, &format!("MATCH (:{})-[r:{}]->() RETURN count(r)", label, relationship_type)),
+            ));
+        }
+
+        if verification.columns.is_empty() {
+            continue;
+        }
+        let sql_columns = verification
+            .columns
+            .iter()
+            .map(|column| format!("\"{}\"", column))
+            .collect::<Vec<String>>()
+            .join(", ");
+        let cypher_columns = verification
+            .columns
+            .iter()
+            .map(|column| format!("n.`{}`", column))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let Some(primary_key) = &verification.primary_key else {
+            continue;
+        };
+        if sample_size > 0 {
+            let primary_key = &primary_key.0;
+            let position = verification.columns.iter().position(|column| column == primary_key).unwrap_or_default();
+            let check = run_postgresql(
+                db_postgresql,
+                &format!(
+                    "SELECT {} FROM {} ORDER BY md5(\"{}\"::text) LIMIT {}",
+                    sql_columns, verification.table, primary_key, sample_size
+                ),
+            )
+            .and_then(|sample| {
+                let keys = sample
+                    .rows
+                    .iter()
+                    .map(|row| key_literal(&row[position]))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+                let nodes = run_neo4j(
+                    db_neo4j,
+                    &format!("MATCH (n:{}) WHERE toString(n.`{}`) IN [{}] RETURN {}", label, primary_key, keys, cypher_columns),
+                )?;
+                let details = match compare_result_sets(&sample, &nodes, TOLERANCE) {
+                    Status::Equivalent => None,
+                    Status::Mismatch(details) | Status::Untranslatable(details) | Status::ExecutionError(details) => Some(details),
+                };
+                Ok(Check {
+                    kind: CheckKind::Sample,
+                    target: String::clone(label),
+                    postgresql: format!("{} rows", sample.rows.len()),
+                    neo4j: format!("{} nodes", nodes.rows.len()),
+                    passed: details.is_none(),
+                    details,
+                })
+            });
+            checks.push(check.unwrap_or_else(|error| error_check(CheckKind::Sample, label, error)));
+        }
+
+        checks.push(
+            match (
+                postgresql_checksum(db_postgresql, verification, &sql_columns, &primary_key.0),
+                neo4j_checksum(db_neo4j, verification, &cypher_columns, primary_key),
+            ) {
+                (Ok(postgresql), Ok(neo4j)) => Check {
+                    kind: CheckKind::Checksum,
+                    target: String::clone(label),
+                    passed: postgresql == neo4j,
+                    postgresql,
+                    neo4j,
+                    details: Some(format!("Columns : {}", verification.columns.join(", "))),
+                },
+                (Err(error), _) | (_, Err(error)) => error_check(CheckKind::Checksum, label, error),
+            },
+        );
+    }
+    checks
+}
+
+/// Generate the report of the verification in a human readable form and in JSON.
+pub fn format_verify_report(checks: &[Check]) -> (String, Value) {
+    let failed = checks.iter().filter(|check| !check.passed).count();
+    let status = if failed == 0 { "PASS" } else { "FAIL" };
+    let mut text = format!(
+        "Verification report : {}\n  Checks : {}\n  Passed : {}\n  Failed : {}\n",
+        status,
+        checks.len(),
+        checks.len() - failed,
+        failed
+    );
+    let mut json_checks = Vec::new();
+    for check in checks {
+        text.push_str(&format!(
+            "\n{} {} {} : PostgreSQL {} | Neo4j {}\n",
+            if check.passed { "PASS" } else { "FAIL" },
+            check.kind.name(),
+            check.target,
+            check.postgresql,
+            check.neo4j
+        ));
+        if let (false, Some(details)) = (check.passed, &check.details) {
+            for line in details.lines() {
+                text.push_str(&format!("  {}\n", line));
+            }
+        }
+        json_checks.push(json!({
+            "kind": check.kind.name(),
+            "target": check.target,
+            "postgresql": check.postgresql,
+            "neo4j": check.neo4j,
+            "passed": check.passed,
+            "details": check.details,
+        }));
+    }
+    let json_report = json!({
+        "summary": {
+            "status": status.to_lowercase(),
+            "checks": checks.len(),
+            "passed": checks.len() - failed,
+            "failed": failed,
+        },
+        "checks": json_checks,
+    });
+    (text, json_report)
+}
+
+#[test]
+fn test_verify() {
+    let rows = vec![
+        vec![Cell::Number(1.0), Cell::Text(String::from("a"))],
+        vec![Cell::Number(2.0), Cell::Null],
+    ];
+    let reversed = rows.iter().rev().cloned().collect::<Vec<Vec<Cell>>>();
+    assert_eq!(add_to_checksum(0, &rows), add_to_checksum(0, &reversed));
+    assert_eq!(add_to_checksum(add_to_checksum(0, &rows[..1]), &rows[1..]), add_to_checksum(0, &rows));
+    assert_ne!(add_to_checksum(0, &rows), add_to_checksum(0, &rows[..1]));
+    assert_eq!(cypher_key_literal("2024-01-31", "date"), "date('2024-01-31')");
+    assert_eq!(cypher_key_literal("\"ALFKI\"", "character"), "\"ALFKI\"");
+    assert_eq!(key_literal(&Cell::Text(String::from("it's"))), "'it\\'s'");
+    assert_eq!(key_literal(&Cell::Number(12.0)), "'12'");
+
+    let folder = format!("{}/verify_test", std::env::temp_dir().display());
+    fs::create_dir_all(&folder).unwrap();
+    let meta_data_path = format!("{}/meta_data.json", folder);
+    let foreign_key_path = format!("{}/FK.csv", folder);
+    let meta_data = json!([{
+        "table_name": "employees",
+        "columns": [
+            { "column_name": "id", "data_type": "integer", "primary_key": true, "foreign_key": null },
+            { "column_name": "name", "data_type": "text", "primary_key": null, "foreign_key": null },
+            { "column_name": "photo", "data_type": "bytea", "primary_key": null, "foreign_key": null },
+            { "column_name": "reports_to", "data_type": "integer", "primary_key": null,
+              "foreign_key": [{ "referenced_table": "employees", "referenced_column": "id" }] }
+        ]
+    }]);
+    fs::write(&meta_data_path, meta_data.to_string()).unwrap();
+    fs::write(&foreign_key_path, "EMPLOYEES_ref_EMPLOYEES;reports_to;id;REPORTS_TO\n").unwrap();
+    let verifications = read_verifications(&meta_data_path, &foreign_key_path).unwrap();
+    assert_eq!(
+        verifications,
+        vec![TableVerification {
+            table: String::from("employees"),
+            label: String::from("EMPLOYEES"),
+            partitioned: false,
+            columns: vec![String::from("id"), String::from("name")],
+            primary_key: Some((String::from("id"), String::from("integer"))),
+            foreign_keys: vec![(String::from("reports_to"), String::from("REPORTS_TO"))],
+        }]
+    );
+    let _ = fs::remove_dir_all(&folder);
+
+    let checks = vec![Check {
+        kind: CheckKind::Count,
+        target: String::from("EMPLOYEES"),
+        postgresql: String::from("9"),
+        neo4j: String::from("8"),
+        passed: false,
+        details: None,
+    }];
+    let (text, json_report) = format_verify_report(&checks);
+    assert!(text.starts_with("Verification report : FAIL"));
+    assert!(text.contains("FAIL count EMPLOYEES : PostgreSQL 9 | Neo4j 8"));
+    assert_eq!(json_report["summary"]["failed"], 1);
+}
@@ -0,0 +1,210 @@
+//! This module translate the views of the PostgreSQL database into Cypher saved queries.
+
+use std::fs;
+
+use serde_json::Value;
+
+use crate::cypher::*;
+use crate::mapping::Mapping;
+use crate::neo4j::Neo4j;
+use crate::translate::*;
+use crate::utils::*;
+
+/// How the views are saved in the ***views.cql*** file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ViewsMode {
+    /// A catalog of named Cypher queries.
+    Catalog,
+    /// APOC custom procedures declared with ```apoc.custom.declareProcedure```,
+    /// the view ```active_customers``` is called with ```CALL custom.active_customers()```.
+    Procedures,
+}
+
+/// Give a name to each column returned by the query, the ```names``` PostgreSQL gives to the columns of the view
+/// (see ```postgresql_column_names```). The columns of the ```table.*``` wildcards keep the name of their variable.
+fn name_columns(query: &mut Query, names: &[Option<String>]) -> Result<Vec<String>, String> {
+    let projection = match query.clauses.last_mut() {
+        Some(Clause::Return(projection)) => projection,
+        _ => return Err(String::from("ERROR : The query doesn't end with a 'RETURN' clause.")),
+    };
+    if projection.items.len() != names.len() {
+        return Err(String::from("ERROR : The columns of the query don't match the columns of the view."));
+    }
+    let mut columns = Vec::new();
+    for (item, name) in projection.items.iter_mut().zip(names) {
+        match item {
+            ProjectionItem::All => {
+                return Err(String::from("ERROR : The columns of 'SELECT *' can't be named."))
+            }
+            ProjectionItem::Expr { expr, alias } => {
+                let name = match (name, &expr) {
+                    (Some(name), _) => String::clone(name),
+                    (None, Expr::Variable(variable)) => String::clone(variable),
+                    (None, _) => return Err(String::from("ERROR : A column of the view can't be named.")),
+                };
+                *alias = Some(String::clone(&name));
+                columns.push(name);
+            }
+        }
+    }
+    Ok(columns)
+}
+
+/// Return the view names used as tables in the query, they don't exist in the graph.
+fn dependent_views(query: &Query, views: &[String]) -> Vec<String> {
+    let mut dependencies = Vec::new();
+    for clause in &query.clauses {
+        if let Clause::Match { patterns, .. } = clause {
+            for pattern in patterns {
+                let nodes = std::iter::once(&pattern.start).chain(pattern.chain.iter().map(|(_, node)| node));
+                for node in nodes {
+                    for label in &node.labels {
+                        if let Some(view) = views.iter().find(|view| view.to_uppercase() == *label) {
+                            if !dependencies.contains(view) {
+                                dependencies.push(String::clone(view));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    dependencies
+}
+
+/// Translate the view into a Cypher query, return the query and the names of its columns.
+fn translate_view(definition: &str, views: &[String], mapping: &Mapping) -> Result<(Query, Vec<String>), String> {
+    let mut query = translate_query(definition, mapping)?;
+    let dependencies = dependent_views(&query, views);
+    if !dependencies.is_empty() {
+        return Err(format!(
+            "ERROR : The view depends on the views {:?} that don't exist in the graph.",
+            dependencies
+        ));
+    }
+    let columns = name_columns(&mut query, &postgresql_column_names(definition)?)?;
+    Ok((query, columns))
+}
+
+/// Generate the Cypher statement that declare the view as an APOC custom procedure.
+fn declare_procedure(view_name: &str, query: &Query, columns: &[String]) -> String {
+    let outputs = columns
+        .iter()
+        .map(|column| format!("{} :: ANY?", escape_identifier(column)))
+        .collect::<Vec<String>>();
+    let signature = format!("{}() :: ({})", escape_identifier(view_name), outputs.join(", "));
+    let mut statement = query.to_string();
+    statement.pop();
+    let call = Query::new(vec![Clause::Call {
+        procedure: String::from("apoc.custom.declareProcedure"),
+        args: vec![
+            Expr::string(&signature),
+            Expr::string(&statement),
+            Expr::string("read"),
+            Expr::string(&format!("Translation of the PostgreSQL view {}", view_name)),
+        ],
+        yield_items: vec![],
+    }]);
+    call.to_string()
+}
+
+/// Translate the views described in the JSON file ```views_meta_data_path``` (exported with the
+/// function ```export_views_metadata``` of ***meta_data.sql***) and save them in ```save_path```.<br>
+/// The views that can't be translated are kept as comments with their error, and listed in the result.
+pub fn generate_views_file(
+    views_meta_data_path: &str,
+    save_path: &str,
+    mode: ViewsMode,
+    mapping: &Mapping,
+) -> Result<String, String> {
+    let content = fs::read_to_string(views_meta_data_path)
+        .map_err(|error| format!("ERROR : when try to read the file : {}\n{}", views_meta_data_path, error))?;
+    let json_object: Value = serde_json::from_str(&content).map_err(|error| format!("{}", error))?;
+    let views = json_object
+        .as_array()
+        .ok_or_else(|| format!("Expected a Value::Array(Vec<_>) but found :\n{}", json_object))?;
+
+    let mut definitions = Vec::new();
+    for view in views {
+        let view_name = view["view_name"]
+            .as_str()
+            .ok_or_else(|| format!("Error when try to get the 'view_name' field in {}", view))?;
+        let definition = view["definition"]
+            .as_str()
+            .ok_or_else(|| format!("Error when try to get the 'definition' field in {}", view))?;
+        definitions.push((String::from(view_name), String::from(definition.trim())));
+    }
+    let view_names = definitions
+        .iter()
+        .map(|(name, _)| String::clone(name))
+        .collect::<Vec<String>>();
+
+    let mut views_content = String::from("// Cypher queries translated from the PostgreSQL views by Neo4j-Migrator\n");
+    let mut errors = Vec::new();
+    for (view_name, definition) in &definitions {
+        views_content.push_str(&format!("\n// View : {}\n", view_name));
+        match translate_view(definition, &view_names, mapping) {
+            Ok((query, columns)) => {
+                match mode {
+                    ViewsMode::Catalog => views_content.push_str(&query.to_string()),
+                    ViewsMode::Procedures => {
+                        views_content.push_str(&declare_procedure(view_name, &query, &columns))
+                    }
+                }
+                views_content.push('\n');
+            }
+            Err(error) => {
+                for line in error.lines().chain(definition.lines()) {
+                    views_content.push_str(&format!("// {}\n", line));
+                }
+                errors.push(format!("{} : {}", view_name, error.replace('\n', " ")));
+            }
+        }
+    }
+    write_file(views_content, save_path)?;
+
+    let mut result = format!(
+        "Successfully translate {}/{} views into {}",
+        definitions.len() - errors.len(),
+        definitions.len(),
+        save_path
+    );
+    if !errors.is_empty() {
+        result.push_str(&format!("\n\n{} views can't be translated :\n{}", errors.len(), errors.join("\n")));
+    }
+    Ok(result)
+}
+
+/// Declare the views as APOC custom procedures in the Neo4j database.
+pub fn declare_views(
+    db_neo4j: &Neo4j,
+    views_meta_data_path: &str,
+    save_path: &str,
+    mapping: &Mapping,
+) -> Result<String, String> {
+    let result = generate_views_file(views_meta_data_path, save_path, ViewsMode::Procedures, mapping)?;
+    db_neo4j.execute_script(save_path)?;
+    Ok(format!("{}\n\nSuccessfully create and run the Cypher script : {}", result, save_path))
+}
+
+#[test]
+fn test_translate_view() {
+    let views = vec![String::from("big_orders"), String::from("recent_big_orders")];
+    let (_, columns) = translate_view(
+        " SELECT o.order_id,\n    o.freight AS cost,\n    upper(o.ship_name),\n    o.freight * 2,\n    (o.freight)::integer\n   FROM orders o\n  WHERE o.freight > 100;",
+        &views,
+        &Mapping::default(),
+    )
+    .unwrap();
+    // The columns without alias are named like PostgreSQL names them.
+    assert_eq!(columns, vec!["order_id", "cost", "upper", "?column?", "freight"]);
+    let (query, columns) = translate_view("SELECT o.order_id, upper(o.ship_name) FROM orders o", &views, &Mapping::default()).unwrap();
+    assert_eq!(
+        declare_procedure("big_orders", &query, &columns),
+        "call apoc.custom.declareProcedure('big_orders() :: (order_id :: ANY?, upper :: ANY?)', \
+         'match (o:ORDERS)\\nreturn o.order_id as order_id, toUpper(o.ship_name) as upper', \
+         'read', 'Translation of the PostgreSQL view big_orders');"
+    );
+
+    assert!(translate_view("SELECT b.order_id FROM big_orders b", &views, &Mapping::default()).is_err());
+}
@@ -0,0 +1,80 @@
+//! This module contains the pool of workers used to export and transform the tables at the same time.<br>
+//! The number of workers bounds the number of tables in memory, the results are returned in the order of the
+//! tables whatever the order in which they are done.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+/// Run the ```task``` on each item with ```workers``` threads, the progress of the ```stage``` is displayed
+/// when an item is done.<br>
+/// Return the results in the order of the items, or the error of the first item that failed : the items
+/// not started yet are then skipped.
+pub fn run_pool<T, R, F>(stage: &str, items: &[(String, T)], workers: usize, task: F) -> Result<Vec<R>, String>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> Result<R, String> + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let done = AtomicUsize::new(0);
+    let failed = AtomicBool::new(false);
+    let results = Mutex::new((0..items.len()).map(|_| None).collect::<Vec<Option<Result<R, String>>>>());
+    let width = items.len().to_string().len();
+
+    thread::scope(|scope| {
+        for _ in 0..workers.clamp(1, items.len().max(1)) {
+            scope.spawn(|| loop {
+                let index = next.fetch_add(1, Ordering::SeqCst);
+                if index >= items.len() || failed.load(Ordering::SeqCst) {
+                    break;
+                }
+                let (name, item) = &items[index];
+                let start = Instant::now();
+                let result = task(item);
+                let count = done.fetch_add(1, Ordering::SeqCst) + 1;
+                match &result {
+                    Ok(_) => println!(
+                        "[{:>width$}/{}] {} : {} ({:.1} s)",
+                        count,
+                        items.len(),
+                        stage,
+                        name,
+                        start.elapsed().as_secs_f64(),
+                        width = width
+                    ),
+                    Err(_) => {
+                        failed.store(true, Ordering::SeqCst);
+                        println!("[{:>width$}/{}] {} : {} FAILED", count, items.len(), stage, name, width = width);
+                    }
+                }
+                results.lock().unwrap()[index] = Some(result);
+            });
+        }
+    });
+
+    let mut ordered = Vec::new();
+    for (result, (name, _)) in results.into_inner().unwrap().into_iter().zip(items) {
+        match result {
+            Some(Ok(result)) => ordered.push(result),
+            Some(Err(error)) => return Err(format!("ERROR : {} failed for {}\n{}", stage, name, error)),
+            None => return Err(format!("ERROR : {} was stopped before {}", stage, name)),
+        }
+    }
+    Ok(ordered)
+}
+
+#[test]
+fn test_run_pool() {
+    let items = (0..20).map(|index| (format!("table_{}", index), index)).collect::<Vec<(String, u64)>>();
+    let results = run_pool("Square", &items, 4, |index| {
+        thread::sleep(std::time::Duration::from_millis(20 - index));
+        Ok(index * index)
+    })
+    .unwrap();
+    assert_eq!(results, (0..20).map(|index| index * index).collect::<Vec<u64>>());
+
+    let error = run_pool("Check", &items, 3, |index| if *index == 5 { Err(String::from("invalid")) } else { Ok(()) }).unwrap_err();
+    assert!(error.contains("table_5") && error.contains("invalid"));
+}